@@ -3,132 +3,360 @@
 //! 当程序运行时，会使用一些参数来完成一系列的操作，当程序接收到命令行参数时，
 //! 由该模块完成对参数的解析和处理。
 //!
+//! 所有命令、参数和选项都在 `command` 模块中定义，可以通过 `crm help <command>` 或
+//! `crm <command> --help` 查看某个命令的详细用法。
+//!
 //! 目前可接收的运行时参数主要包括:
 //!   - `crm best`:                    评估网络延迟并自动切换到最优的镜像
 //!   - `crm best git`:                仅评估 git 镜像源
 //!   - `crm best sparse`:             仅评估支持 sparse 协议的镜像源
 //!   - `crm best git-download`:       仅评估能够快速下载软件包的 git 镜像源 (推荐使用)
 //!   - `crm best sparse-download`:    仅评估能够快速下载软件包且支持 sparse 协议的镜像源 (推荐使用)
+//!   - `crm alias <action> [alias]`: 列出、添加或删除镜像的别名，别名可以代替镜像名
+//!   - `crm bench [name]`:            下载较大的软件包以评估下载速度
 //!   - `crm current`:                 获取当前所使用的镜像
 //!   - `crm default`:                 恢复为官方默认镜像
+//!   - `crm disable <name>`:          禁用镜像，被禁用的镜像不会出现在镜像列表、测试和 `best` 中
+//!   - `crm enable <name>`:           重新启用被禁用的镜像
+//!   - `crm freshness [name]`:        将镜像的索引与官方索引进行比较，检查镜像落后了多少个版本
 //!   - `crm install [args]`:          使用官方镜像执行 `cargo install`
 //!   - `crm list`:                    从镜像配置文件中获取镜像列表
+//!   - `crm probe [name]`:            检查 sparse 镜像的 config.json 和索引文件是否正确
+//!   - `crm profile <action> [name]`: 应用、列出、保存或删除将镜像和 `Cargo` 网络配置组合在一起的配置方案
 //!   - `crm publish [args]`:          使用官方镜像执行 `cargo publish`
 //!   - `crm remove <name>`:           在镜像配置文件中删除镜像
+//!   - `crm rename <old> <new>`:     重命名自定义的镜像
 //!   - `crm save <name> <addr> <dl>`: 在镜像配置文件中添加/更新镜像
 //!   - `crm test [name]`:             下载测试包以评估网络延迟
 //!   - `crm update [args]`:           使用官方镜像执行 `cargo update`
 //!   - `crm use <name>`:              切换为要使用的镜像
+//!   - `crm use <name> --local`:      在距离当前目录最近的项目级配置文件中切换镜像
+//!   - `crm use <name> --path <dir>`: 在距离 `dir` 目录最近的项目级配置文件中切换镜像
+//!   - `crm verify [name]`:           下载软件包并将其 SHA-256 与镜像索引和官方索引中的校验和进行比较
 //!   - `crm version`:                 查看当前版本
 //!   - `crm check-update`:            检测版本更新
+//!   - `crm history [n]`:             查看配置文件的快照列表，或查看第 n 个快照与当前文件的差异
+//!   - `crm rollback [n]`:            将配置文件恢复为第 n 个快照 (默认为最新的快照)
+//!   - `crm completions <shell>`:     输出 bash、zsh、fish 或 elvish 的自动补全脚本
+//!   - `crm help [command]`:          查看帮助信息
+//!
+//! `use`、`default` 和 `best` 命令都可以通过 `--local` 或 `--path <dir>` 参数来修改项目级的
+//! `.cargo/config.toml` 配置文件，而不是 `${CARGO_HOME}/config.toml` 全局配置文件。
+//!
+//! `use`、`default`、`best`、`save` 和 `remove` 命令都可以通过 `--dry-run` 参数来预览配置文件的变化，
+//! 此时只会输出将要修改的内容的统一差异 (unified diff)，而不会写入到文件中。
+//!
+//! `list`、`current`、`test` 和 `best` 命令都可以通过 `--format json` 或 `--format tsv` 参数输出结构化的数据，
+//! `best` 命令的评估模式和 `--filter <expr>` 参数决定参与评估的镜像，镜像的协议由镜像地址的 `sparse+` 前缀决定，
+//! 是否能够快速下载软件包由 `proxies-downloads` 字段或 `dl` 是否指向 `crates.io` 决定，所以自定义的镜像也可以参与评估。
+//!
+//! `test` 和 `best` 命令还可以通过 `--timeout <secs>` 参数指定每次网络请求的超时时间，
+//! 通过 `--samples <n>` 参数对每个镜像进行多次测试，并根据中位数进行排名。
 //!
 //! 其中，`save`、`remove` 命令只修改 `${HOME}/.crmrc` 配置文件，
 //! 而不对 `${CARGO_HOME}/.cargo/config` 文件做任何的操作。
 //! 如果需要操作 `.crmrc` 镜像配置时仍要修改 `config` 配置文件，
 //! 请在操作完镜像配置文件后手动执行相应的操作镜像的命令。
 
-use std::env::args_os;
+use std::{
+    env::{self, args_os},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::{
-    constants::APP_VERSION,
+    command::{command_help, find_command, overview, parse, Complete, Matches},
+    completion::{complete, Shell},
+    constants::{APP_VERSION, CAINFO, PROXY},
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+    net,
     registry::Registry,
-    utils::{get_newest_version, not_command, to_out},
+    utils::{cargo_config_path, get_newest_version, local_cargo_config_path},
 };
 
-type Args = (String, Vec<String>);
-
 /// 解析程序运行时所传递的命令行参数
 ///
-/// 当参数解析完之后会返回一个元组，元组的第一项为要执行的命令，
-/// 元组的第二项为执行该命令所要用到的参数。
-pub fn parse_args() -> Args {
-    let mut args_os = args_os().map(|os_string| os_string.into_string().unwrap());
+/// 参数会根据 `command` 模块中的命令定义进行校验，不是有效 `UTF-8` 字符串的参数、
+/// 不支持的选项以及多余的参数都会返回错误。
+pub fn parse_args() -> CrmResult<Matches> {
+    let args = args_os()
+        .skip(1)
+        .map(|os_string| {
+            os_string.into_string().map_err(|v| {
+                CrmError::InvalidValue(Msg::InvalidUtf8.format(&[&format!("{:?}", v)]))
+            })
+        })
+        .collect::<CrmResult<Vec<String>>>()?;
+
+    parse(args)
+}
+
+/// 根据运行时参数来处理要执行的命令
+///
+/// 该函数传递一个解析后的运行时参数对象，其中包括命令、位置参数和选项。
+pub fn handle_command(m: Matches) -> CrmResult<()> {
+    match m.command.name {
+        // 查看帮助信息
+        "help" => {
+            let help = match m.arg(0) {
+                Some(name) => match find_command(name) {
+                    Some(command) => command_help(command),
+                    None => return Err(CrmError::UnknownCommand(name.to_string())),
+                },
+                None => overview(),
+            };
+
+            println!("{}", help);
+            return Ok(());
+        }
+
+        // 查看当前的版本
+        "version" => {
+            println!("  crm v{APP_VERSION}");
+            return Ok(());
+        }
+
+        // 输出 shell 的自动补全脚本
+        "completions" => {
+            let shell: Shell = m.arg(0).map_or("", |v| v.as_str()).parse()?;
+
+            print!("{}", shell.script());
+            return Ok(());
+        }
+
+        // 输出补全的候选项，供补全脚本使用
+        "__complete" => {
+            for candidate in complete(&m.args) {
+                println!("{}", candidate);
+            }
+
+            return Ok(());
+        }
+
+        _ => {}
+    }
+
+    let mut r = Registry::new(target_config_path(&m)?)?;
 
-    args_os.next();
+    // 只有发出网络请求的命令 (即支持 `--proxy` 的命令) 才需要设置代理和 CA 证书，
+    // 以免无效的网络配置影响到 `list`、`current` 等不需要访问网络的命令
+    if m.command.option("proxy").is_some() {
+        // 网络请求使用的代理
+        let proxy = net::http_setting(
+            PROXY,
+            m.value("proxy")
+                .map(|v| (v.to_string(), "--proxy".to_string())),
+            || r.cargo_http(PROXY),
+        )?;
 
-    let command = args_os.next();
+        // 额外信任的 CA 证书
+        let cainfo = net::http_setting(
+            CAINFO,
+            m.value("cacert")
+                .map(|v| (v.to_string(), "--cacert".to_string())),
+            || r.cargo_http(CAINFO),
+        )?;
 
-    if command.is_none() {
-        not_command("");
+        net::init(proxy, cainfo)?;
     }
 
-    (
-        command.unwrap(),
-        args_os
-            .map(|v| v.trim().to_string())
-            .collect::<Vec<String>>(),
-    )
-}
+    // 只输出配置文件的变化，而不写入到文件中
+    r.set_dry_run(m.flag("dry-run"));
 
-/// 根据运行时参数来处理要执行的命令
-///
-/// 该函数传递一个运行时参数对象，其中包括命令和执行命令用到的参数。
-pub fn handle_command((command, args): Args) {
-    let mut r = Registry::new();
+    // 输出结构化的数据
+    if let Some(format) = m.value("format") {
+        r.set_format(format.parse()?);
+    }
+
+    // 网络请求的超时时间
+    if let Some(timeout) = m.number("timeout") {
+        r.set_timeout(Duration::from_secs(timeout));
+    }
+
+    // 对每个镜像进行测试的次数
+    if let Some(samples) = m.number("samples") {
+        r.set_samples(samples as usize);
+    }
+
+    // 同时测试的镜像的最大数量
+    if let Some(jobs) = m.number("jobs") {
+        r.set_jobs(jobs as usize);
+    }
+
+    // 所有测试的总时限
+    if let Some(deadline) = m.number("deadline") {
+        r.set_deadline(Duration::from_secs(deadline));
+    }
+
+    // 是否分阶段计时
+    r.set_detailed(m.flag("detailed"));
+
+    // 测试记录的标签
+    if let Some(label) = m.value("label") {
+        r.set_label(label)?;
+    }
+
+    // 根据最近的测试记录选择镜像
+    r.set_from_history(m.flag("from-history"));
+
+    // 统计最近多少天的测试记录
+    if let Some(days) = m.number("days") {
+        r.set_days(days);
+    }
+
+    // 输出镜像的详细信息
+    r.set_verbose(m.flag("verbose"));
+
+    // 包含被禁用的镜像
+    r.set_all(m.flag("all"));
+
+    // 只使用地区或标签匹配的镜像
+    if let Some(tag) = m.value("tag") {
+        r.set_tag(tag);
+    }
+
+    // `best` 使用的筛选条件
+    if let Some(filter) = m.value("filter") {
+        r.add_filter(filter)?;
+    }
+
+    // 对镜像进行排名的依据
+    if let Some(rank) = m.value("by") {
+        r.set_rank(rank.parse()?);
+    }
+
+    // 测试下载速度或校验软件包时下载的软件包
+    if let Some(crates) = m.value("crates") {
+        match m.command.name {
+            "verify" => r.set_verify_crates(crates)?,
+            _ => r.set_bench_crates(crates)?,
+        }
+    }
+
+    // 检查 `sparse` 索引时请求的软件包
+    if let Some(krate) = m.value("crate") {
+        r.set_probe_crate(krate)?;
+    }
+
+    // 用于比较索引的软件包
+    if let Some(crates) = m.value("index-crates") {
+        r.set_freshness_crates(crates)?;
+    }
+
+    // 排除软件包校验失败的镜像
+    r.set_verify(m.flag("verify"));
+
+    // 排除索引落后太多的镜像
+    if let Some(max_lag) = m.number("max-lag") {
+        r.set_max_lag(max_lag as usize);
+    }
+
+    // 测试下载速度时每个镜像最多下载的字节数和最多花费的时间
+    r.set_bench_limits(
+        m.number("max-bytes"),
+        m.number("max-time").map(Duration::from_secs),
+    );
+
+    // 第一个参数是镜像名时，将其中的别名转换为镜像名
+    let resolved = match m.command.args.first() {
+        Some(arg) if arg.complete == Complete::Registry => m.arg(0).map(|v| r.resolve(v)),
+        _ => None,
+    };
+    let (name, addr, dl) = (resolved.as_ref().or(m.arg(0)), m.arg(1), m.arg(2));
+
+    match m.command.name {
+        // 评估网络延迟并自动切换到最优的镜像
+        "best" => r.best(name)?,
 
-    match command.trim().to_lowercase().as_str() {
         // 列出镜像
-        "list" => println!("{}", r.list(&r.current().0)),
+        "list" => println!("{}", r.list(&r.current()?.0)),
 
         // 恢复默认镜像
-        "default" => r.default(),
+        "default" => r.default()?,
 
         // 切换镜像
-        "use" => r.select(args.first()),
+        "use" => r.select(name)?,
 
         // 删除镜像
-        "remove" => r.remove(args.first()),
+        "remove" => r.remove(name)?,
+
+        // 添加/更新镜像
+        "save" => r.save(name, addr, dl)?,
+
+        // 重命名镜像
+        "rename" => r.rename(name, addr)?,
+
+        // 禁用镜像
+        "disable" => r.set_registry_disabled(name, true)?,
+
+        // 启用镜像
+        "enable" => r.set_registry_disabled(name, false)?,
+
+        // 管理镜像的别名
+        "alias" => r.alias(name, addr, dl)?,
 
         // 使用官方镜像执行 `cargo publish`
-        "publish" => r.publish(args.join(" ")),
+        "publish" => r.publish(m.args.clone())?,
 
         // 使用官方镜像执行 `cargo update`
-        "update" => r.update(args.join(" ")),
+        "update" => r.update(m.args.clone())?,
 
         // 使用官方镜像执行 `cargo install`
-        "install" => r.install(args.join(" ")),
+        "install" => r.install(m.args.clone())?,
 
-        // 对镜像源网络延迟进行评估
-        "test" => r.test(&r.current().0, args.first()),
+        // 查看配置文件的快照
+        "history" => println!("{}", r.history(name)?),
 
-        // 获取当前镜像
-        "current" => {
-            let (name, addr) = r.current();
+        // 恢复配置文件的快照
+        "rollback" => r.rollback(name)?,
 
-            match addr {
-                Some(addr) => to_out(format!("{}: {}", name, addr)),
-                None => to_out(name),
-            };
-        }
+        // 检查 `sparse` 镜像的 `config.json` 和索引文件
+        "probe" => r.probe(&r.current()?.0, name)?,
 
-        // 查看当前的版本
-        "version" => {
-            println!("  crm v{APP_VERSION}");
-        }
+        // 检查镜像索引的同步情况
+        "freshness" => r.freshness(&r.current()?.0, name)?,
 
-        // 检查版本更新
-        "check-update" => {
-            if let Some(newest) = get_newest_version() {
-                if newest != APP_VERSION {
-                    return println!("  检测到新版本: {newest}，请切换到官方镜像源以执行更新");
-                }
-            };
+        // 校验镜像提供的软件包
+        "verify" => r.verify(&r.current()?.0, name)?,
 
-            println!("  暂无更新");
-        }
+        // 对镜像源下载速度进行评估
+        "bench" => r.bench(&r.current()?.0, name)?,
+
+        // 对镜像源网络延迟进行评估
+        "test" => r.test(&r.current()?.0, name)?,
 
-        command => {
-            let (name, addr, dl) = (args.get(0), args.get(1), args.get(2));
+        // 根据测试记录输出每个镜像的趋势
+        "report" => r.report(name)?,
 
-            match command {
-                // 评估网络延迟并自动切换到最优的镜像
-                "best" => r.best(name),
+        // 管理配置方案
+        "profile" => r.profile(name, addr, dl)?,
 
-                // 添加/更新镜像
-                "save" => r.save(name, addr, dl),
+        // 获取当前镜像
+        "current" => println!("{}", r.show_current()?),
 
-                _ => not_command(command),
+        // 检查版本更新
+        // 与之前的版本一样，无法获取最新版本时视为暂无更新
+        "check-update" => match get_newest_version() {
+            Ok(newest) if newest != APP_VERSION => {
+                println!("{}", Msg::NewVersion.format(&[&newest]))
             }
-        }
+            _ => println!("{}", Msg::NoUpdate),
+        },
+
+        command => return Err(CrmError::UnknownCommand(command.to_string())),
+    }
+
+    Ok(())
+}
+
+/// 根据 `--local` 和 `--path <dir>` 参数获取切换镜像时要修改的 `Cargo` 配置文件的路径
+///
+/// 如果都没有指定，则修改 `${CARGO_HOME}/config.toml` 全局配置文件。
+fn target_config_path(m: &Matches) -> CrmResult<PathBuf> {
+    match m.value("path") {
+        Some(dir) => local_cargo_config_path(Path::new(dir)),
+        None if m.flag("local") => local_cargo_config_path(&env::current_dir()?),
+        None => cargo_config_path(),
     }
 }
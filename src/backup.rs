@@ -0,0 +1,294 @@
+//! # 配置文件快照
+//!
+//! 在 `crm` 修改 `Cargo` 配置文件或 `.crmrc` 文件之前，会先将原文件的内容保存为一个带时间戳的快照，
+//! 快照存放在 `${HOME}/.crm/backups` 目录中。每个配置文件最多保留 `CRM_BACKUP_LIMIT` 个快照 (默认为 10 个)，
+//! 将其设置为 `0` 可以关闭快照功能。
+
+use std::{
+    env,
+    fs::{create_dir_all, read_dir, read_to_string, remove_file, write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    constants::{BACKUPS, CRM_BACKUP_LIMIT, DEFAULT_BACKUP_LIMIT},
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+    lock::FileLock,
+    toml::Toml,
+    utils::crm_dir,
+};
+
+/// 快照文件第一行的前缀，后面跟着原文件的路径
+const HEADER: &str = "# crm backup: ";
+
+/// 原文件不存在时，快照文件第一行的前缀
+const MISSING_HEADER: &str = "# crm backup (missing): ";
+
+/// 配置文件快照
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// 创建快照时的时间戳 (毫秒)
+    pub time: u128,
+
+    /// 原文件的路径
+    pub origin: PathBuf,
+
+    /// 快照文件的路径
+    pub file: PathBuf,
+
+    /// 创建快照时原文件是否存在，不存在时恢复快照会删除原文件
+    pub existed: bool,
+}
+
+impl Snapshot {
+    /// 读取快照中保存的原文件内容
+    pub fn content(&self) -> CrmResult<String> {
+        let data = read_to_string(&self.file)?;
+
+        Ok(match data.split_once('\n') {
+            Some((_, content)) => content.to_string(),
+            None => String::new(),
+        })
+    }
+
+    /// 从快照文件中解析快照
+    fn from_file(file: PathBuf) -> Option<Snapshot> {
+        let name = file.file_name()?.to_str()?;
+        let time = name.split_once('-')?.0.parse().ok()?;
+        let data = read_to_string(&file).ok()?;
+        let header = data.lines().next()?;
+        let (origin, existed) = match header.strip_prefix(HEADER) {
+            Some(origin) => (origin, true),
+            None => (header.strip_prefix(MISSING_HEADER)?, false),
+        };
+
+        Some(Snapshot {
+            time,
+            origin: PathBuf::from(origin),
+            file,
+            existed,
+        })
+    }
+}
+
+/// 获取每个配置文件最多保留的快照数量
+fn backup_limit() -> CrmResult<usize> {
+    match env::var(CRM_BACKUP_LIMIT) {
+        Ok(value) => value.trim().parse().map_err(|_| {
+            CrmError::InvalidValue(Msg::InvalidBackupLimit.format(&[&CRM_BACKUP_LIMIT, &value]))
+        }),
+        Err(_) => Ok(DEFAULT_BACKUP_LIMIT),
+    }
+}
+
+/// 获取快照的存放目录
+fn backup_dir() -> CrmResult<PathBuf> {
+    Ok(crm_dir()?.join(BACKUPS))
+}
+
+/// 获取所有的快照，最新的快照排在最前面
+pub fn snapshots() -> CrmResult<Vec<Snapshot>> {
+    snapshots_in(&backup_dir()?)
+}
+
+/// 获取 `dir` 目录中的所有快照，最新的快照排在最前面
+fn snapshots_in(dir: &Path) -> CrmResult<Vec<Snapshot>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut snapshots: Vec<Snapshot> = read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Snapshot::from_file(entry.path()))
+        .collect();
+
+    snapshots.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| b.file.cmp(&a.file)));
+
+    Ok(snapshots)
+}
+
+/// 在修改 `path` 文件之前为其创建快照，并删除超出数量限制的旧快照
+///
+/// 如果文件不存在，则保存一个标记为不存在的空快照，恢复它时会删除该文件。
+/// 如果文件的内容与最近一次的快照相同，则不会重复创建快照。
+pub fn snapshot(path: &Path) -> CrmResult<()> {
+    snapshot_in(&backup_dir()?, backup_limit()?, path)
+}
+
+/// 在 `dir` 目录中为 `path` 文件创建快照，最多保留 `limit` 个快照
+fn snapshot_in(dir: &Path, limit: usize, path: &Path) -> CrmResult<()> {
+    if limit == 0 {
+        return Ok(());
+    }
+
+    let existed = path.is_file();
+    let content = read_to_string(path).unwrap_or_default();
+    let history: Vec<Snapshot> = snapshots_in(dir)?
+        .into_iter()
+        .filter(|v| v.origin == path)
+        .collect();
+
+    if let Some(latest) = history.first() {
+        if latest.existed == existed && latest.content()? == content {
+            return Ok(());
+        }
+    }
+
+    create_dir_all(dir)?;
+
+    let name = path
+        .file_name()
+        .map(|v| v.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| v.as_millis())
+        .unwrap_or_default();
+
+    // 避免在同一毫秒内创建的快照相互覆盖
+    let file = loop {
+        let file = dir.join(format!("{}-{}", time, name));
+
+        if !file.exists() {
+            break file;
+        }
+
+        time += 1;
+    };
+
+    let header = if existed { HEADER } else { MISSING_HEADER };
+
+    write(file, format!("{}{}\n{}", header, path.display(), content))?;
+
+    // 新快照已经存在，所以只需要保留 `limit - 1` 个旧快照
+    for old in history.iter().skip(limit - 1) {
+        remove_file(&old.file)?;
+    }
+
+    Ok(())
+}
+
+/// 将快照恢复到原文件中，恢复之前会为原文件创建快照，以便能够撤销本次恢复
+///
+/// 创建快照时原文件不存在的，恢复时会删除原文件。
+pub fn restore(snapshot: &Snapshot) -> CrmResult<()> {
+    let _lock = FileLock::acquire(&snapshot.origin)?;
+
+    restore_in(&backup_dir()?, backup_limit()?, snapshot)
+}
+
+/// 将快照恢复到原文件中，恢复之前在 `dir` 目录中为原文件创建快照
+fn restore_in(dir: &Path, limit: usize, snapshot: &Snapshot) -> CrmResult<()> {
+    let content = snapshot.content()?;
+    let toml = Toml::parse(&content).map_err(|_| CrmError::ConfigParse(snapshot.file.clone()))?;
+
+    snapshot_in(dir, limit, &snapshot.origin)?;
+
+    match snapshot.existed {
+        true => toml.write(&snapshot.origin),
+        false if snapshot.origin.is_file() => Ok(remove_file(&snapshot.origin)?),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+
+    /// 创建一个空的临时目录，返回快照目录和被快照的文件
+    fn setup(name: &str) -> (PathBuf, PathBuf) {
+        let root = env::temp_dir().join(format!("crm-{}-backup-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        (root.join("backups"), root.join("config.toml"))
+    }
+
+    fn contents(dir: &Path) -> Vec<String> {
+        snapshots_in(dir)
+            .unwrap()
+            .iter()
+            .map(|v| v.content().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn records_missing_files_and_skips_duplicates() {
+        let (dir, path) = setup("missing");
+
+        snapshot_in(&dir, 10, &path).unwrap();
+        snapshot_in(&dir, 10, &path).unwrap();
+        fs::write(&path, "").unwrap();
+        snapshot_in(&dir, 10, &path).unwrap();
+
+        let list = snapshots_in(&dir).unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert!(list[0].existed);
+        assert!(!list[1].existed);
+        assert!(list.iter().all(|v| v.origin == path));
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn keeps_at_most_limit_snapshots() {
+        let (dir, path) = setup("limit");
+
+        for i in 0..5 {
+            fs::write(&path, format!("a = {}", i)).unwrap();
+            snapshot_in(&dir, 3, &path).unwrap();
+        }
+
+        assert_eq!(contents(&dir), ["a = 4", "a = 3", "a = 2"]);
+
+        snapshot_in(&dir, 0, &path.with_file_name("other.toml")).unwrap();
+
+        assert_eq!(snapshots_in(&dir).unwrap().len(), 3);
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn reads_limit_from_env() {
+        env::set_var(CRM_BACKUP_LIMIT, " 3 ");
+        assert_eq!(backup_limit().unwrap(), 3);
+
+        env::set_var(CRM_BACKUP_LIMIT, "-1");
+        assert!(matches!(backup_limit(), Err(CrmError::InvalidValue(_))));
+
+        env::remove_var(CRM_BACKUP_LIMIT);
+        assert_eq!(backup_limit().unwrap(), DEFAULT_BACKUP_LIMIT);
+    }
+
+    #[test]
+    fn restores_content_and_removes_created_files() {
+        let (dir, path) = setup("restore");
+
+        snapshot_in(&dir, 10, &path).unwrap();
+        fs::write(&path, "a = 1").unwrap();
+        snapshot_in(&dir, 10, &path).unwrap();
+        fs::write(&path, "a = 2").unwrap();
+
+        let list = snapshots_in(&dir).unwrap();
+
+        restore_in(&dir, 10, &list[0]).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a = 1");
+
+        // 恢复为文件不存在时的快照会删除文件，而不是写入一个空文件
+        restore_in(&dir, 10, &list[1]).unwrap();
+
+        assert!(!path.exists());
+
+        // 恢复之前的内容也被保存为快照，以便撤销本次恢复
+        assert_eq!(snapshots_in(&dir).unwrap().len(), 4);
+        assert_eq!(contents(&dir)[1], "a = 2");
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+}
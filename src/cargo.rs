@@ -3,219 +3,430 @@
 //! 该模块会解析 `Cargo` 配置，并将其反序列化为对象的形式，修改完成后再序列化为相应的文件。
 //! `CargoConfig` 是一个操作 `Cargo` 配置文件的对象，有了它一切都好办了。
 
-use std::process;
+use std::{
+    fmt,
+    fs::{read_to_string, remove_file, write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use toml_edit::{table, value, Item, Table};
 
 use crate::{
+    backup::snapshot,
     constants::{
-        CRATES_IO, GIT_FETCH_WITH_CLI, NET, PLEASE_TRY, REGISTRIES, REGISTRY, REPLACE_WITH,
-        RUST_LANG, SOURCE, STRING, TABLE,
+        CRATES_IO, GIT_FETCH_WITH_CLI, HTTP, NET, REGISTRIES, REGISTRY, REPLACE_WITH, RUST_LANG,
+        SOURCE,
     },
     description::RegistryDescription,
+    diff::unified_diff,
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+    lock::FileLock,
+    profile::{get_path, leaves, remove_path, reserved_error, same_value, set_path, RESERVED_KEYS},
     toml::Toml,
-    utils::{cargo_config_path, field_eprint, get_cargo_config, to_out},
+    utils::{cargo_config_path, get_cargo_config},
 };
 
-/// 验证字段是否存在
-fn verify_field_exists(data: &mut Table, key: &str) {
-    if data.contains_key(key) {
-        if !data[key].is_table() {
-            field_eprint(key, TABLE);
-            process::exit(5);
+/// 创建字段类型错误
+fn field_error<T: ToString>(path: &Path, field: T, expected: Msg) -> CrmError {
+    CrmError::FieldType {
+        path: path.to_path_buf(),
+        field: field.to_string(),
+        expected,
+    }
+}
+
+/// 验证字段存在时是不是表
+fn verify_field_type(path: &Path, data: &Table, key: &str) -> CrmResult<()> {
+    match data.get(key) {
+        Some(item) if !item.is_table() => Err(field_error(path, key, Msg::Table)),
+        _ => Ok(()),
+    }
+}
+
+/// 获取 `crm` 在 `[source.xxx]` 或 `[registries.xxx]` 表中写入的镜像地址的字段名
+fn attribute_name(key: &str) -> &'static str {
+    match key {
+        SOURCE => REGISTRY,
+        REGISTRIES => "index",
+        _ => unreachable!("{key} 不是预期的属性名"),
+    }
+}
+
+/// `Cargo` 配置文件的作用域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// 全局配置，即 `${CARGO_HOME}/config.toml`
+    Global,
+
+    /// 项目配置，即项目目录下的 `.cargo/config.toml`
+    Local,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Global => write!(f, "{}", Msg::ScopeGlobal),
+            Scope::Local => write!(f, "{}", Msg::ScopeLocal),
         }
-    } else {
-        data[key] = table();
-    };
+    }
 }
 
 /// `Cargo` 配置对象
 #[derive(Debug)]
 pub struct CargoConfig {
+    /// 配置文件的路径
+    path: PathBuf,
+
+    /// 配置文件的锁，只在修改配置文件时持有，写入之后释放
+    lock: Option<Arc<FileLock>>,
+
     /// 配置对象中的数据，它是一个经过反序列化的对象
     data: Toml,
 }
 
 impl CargoConfig {
-    /// 创建配置对象
-    pub fn new() -> Self {
-        let toml = get_cargo_config();
+    /// 根据 `Cargo` 配置文件的路径创建配置对象
+    ///
+    /// 全局配置文件的路径可以通过 `utils::cargo_config_path` 获取，
+    /// 项目级配置文件的路径可以通过 `utils::local_cargo_config_path` 获取。
+    ///
+    /// 创建配置对象时不会加锁，修改配置文件之前需要调用 `lock` 方法。
+    pub fn new(path: PathBuf) -> CrmResult<Self> {
+        Ok(CargoConfig {
+            data: CargoConfig::load(&path)?,
+            path,
+            lock: None,
+        })
+    }
 
-        match Toml::parse(&toml) {
-            Ok(mut config) => {
-                let data: &mut Table = config.table_mut();
+    /// 读取并解析配置文件，读取时不会添加任何字段
+    fn load(path: &Path) -> CrmResult<Toml> {
+        let toml = get_cargo_config(path);
+        let config = Toml::parse(&toml).map_err(|_| CrmError::ConfigParse(path.to_path_buf()))?;
+        let data: &Table = config.table();
 
-                // 如果没有则创建表，否则判断是不是表
-                verify_field_exists(data, SOURCE);
-                verify_field_exists(data, REGISTRIES);
-                verify_field_exists(data, NET);
+        verify_field_type(path, data, SOURCE)?;
+        verify_field_type(path, data, REGISTRIES)?;
+        verify_field_type(path, data, NET)?;
 
-                let net = data[NET].as_table().unwrap();
+        Ok(config)
+    }
 
-                if !net.contains_key(GIT_FETCH_WITH_CLI) {
-                    data[NET][GIT_FETCH_WITH_CLI] = value(true);
-                }
+    /// 在写入配置文件之前整理配置
+    ///
+    /// 只有全局配置文件会在没有 `net.git-fetch-with-cli` 字段时添加默认值 `true`，项目级配置文件保持原样。
+    /// 修改之后为空的 `[source.crates-io]`、`[source]`、`[registries]` 和 `[net]` 表不会被写入。
+    fn normalize(&mut self) -> CrmResult<()> {
+        if self.scope()? == Scope::Global {
+            let net = self.table_mut(NET)?;
 
-                CargoConfig { data: config }
+            if !net.contains_key(GIT_FETCH_WITH_CLI) {
+                net[GIT_FETCH_WITH_CLI] = value(true);
             }
+        }
 
-            Err(_) => {
-                to_out(format!(
-                    "{} 文件解析失败，{}",
-                    cargo_config_path().display(),
-                    PLEASE_TRY
-                ));
-                process::exit(5);
+        let data = self.data.table_mut();
+        let is_empty = |v: &Item| v.as_table().is_some_and(Table::is_empty);
+
+        if let Some(source) = data.get_mut(SOURCE).and_then(Item::as_table_mut) {
+            if source.get(CRATES_IO).is_some_and(is_empty) {
+                source.remove(CRATES_IO);
+            }
+        }
+
+        for key in [SOURCE, REGISTRIES, NET] {
+            if data.get(key).is_some_and(is_empty) {
+                data.remove(key);
             }
         }
+
+        Ok(())
+    }
+
+    /// 对配置文件加锁，并重新读取配置文件，以免覆盖其他进程在此之前写入的内容
+    ///
+    /// 已经加锁时什么也不做，锁会在 `make` 写入配置文件之后释放。
+    pub fn lock(&mut self) -> CrmResult<()> {
+        if self.lock.is_none() {
+            let lock = FileLock::acquire(&self.path)?;
+
+            self.data = CargoConfig::load(&self.path)?;
+            self.lock = Some(lock);
+        }
+
+        Ok(())
     }
 
-    /// 将 `Cargo` 配置写入到文件中
-    pub fn make(&self) {
-        self.data.write(cargo_config_path())
+    /// 获取配置文件中的 `key` 表，例如 `[source]`，不存在时创建它
+    ///
+    /// 新创建的表是隐式的，只包含子表时不会输出单独的 `[source]` 表头。
+    fn table_mut(&mut self, key: &str) -> CrmResult<&mut Table> {
+        let data = self.data.table_mut();
+
+        if !data.contains_key(key) {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            data[key] = Item::Table(table);
+        }
+
+        data[key]
+            .as_table_mut()
+            .ok_or_else(|| field_error(&self.path, key, Msg::Table))
     }
 
-    /// 如果 `Cargo` 配置文件中不包含 `[source.crates-io]` 属性，则为 `Cargo` 配置自动填充。
-    fn fill_crates_io(&mut self) {
-        let data: &mut Table = self.data.table_mut();
-        let source: &mut Table = data[SOURCE].as_table_mut().unwrap();
-
-        if source.contains_key(CRATES_IO) {
-            if !source[CRATES_IO].is_table() {
-                field_eprint(format!("[{SOURCE}.{CRATES_IO}]"), TABLE);
-                process::exit(5);
-            }
+    /// 获取配置文件中的 `[source]` 表
+    fn source_mut(&mut self) -> CrmResult<&mut Table> {
+        self.table_mut(SOURCE)
+    }
+
+    /// 获取配置文件的路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 获取配置文件的作用域
+    pub fn scope(&self) -> CrmResult<Scope> {
+        if self.path == cargo_config_path()? {
+            Ok(Scope::Global)
         } else {
-            data[SOURCE][CRATES_IO] = table();
+            Ok(Scope::Local)
         }
     }
 
-    /// 如果切换为默认镜像时，则删除 `replace_with` 属性。否则，
-    /// 则为 `[source.creates-io]` 添加 `replace-with` 属性，
-    /// 该属性用于指示要使用的外部镜像的名称。
-    fn replace_with(&mut self, registry_name: &str) {
-        self.fill_crates_io();
+    /// 获取配置文件中 `[http]` 属性下面的字符串字段，例如 `http.proxy`
+    pub fn http(&self, key: &str) -> Option<String> {
+        self.data
+            .table()
+            .get(HTTP)
+            .and_then(|http| http.get(key))
+            .and_then(Item::as_str)
+            .filter(|v| !v.trim().is_empty())
+            .map(str::to_string)
+    }
 
-        let data: &mut Table = self.data.table_mut();
-        let crates_io: &mut Item = &mut data[SOURCE][CRATES_IO];
+    /// 获取配置文件中的 `keys` 属性，返回一个只包含这些属性的表
+    ///
+    /// `net.git-fetch-with-cli = true` 是写入全局配置文件时自动添加的默认值，不是用户的配置，所以不会被包含在内。
+    pub fn settings(&self, keys: &[&str]) -> Table {
+        let mut settings = Table::new();
+
+        for key in keys {
+            let Some(mut item) = self
+                .data
+                .table()
+                .get(key)
+                .filter(|v| v.is_table_like())
+                .cloned()
+            else {
+                continue;
+            };
+
+            if let Some(net) = item.as_table_like_mut().filter(|_| *key == NET) {
+                if net.get(GIT_FETCH_WITH_CLI).and_then(Item::as_bool) == Some(true) {
+                    net.remove(GIT_FETCH_WITH_CLI);
+                }
+            }
 
-        // 去除属性
-        if registry_name.eq(RUST_LANG) && !crates_io.is_none() {
-            crates_io.as_table_mut().unwrap().remove(REPLACE_WITH);
-            return;
+            if item.as_table_like().is_some_and(|v| !v.is_empty()) {
+                settings[key] = item;
+            }
         }
 
-        // 追加属性
-        crates_io[REPLACE_WITH] = value(registry_name);
+        settings
     }
 
-    /// 从 `Cargo` 配置文件中获取正在使用的镜像，其中 `rust-lang` 是 `Cargo` 默认使用的镜像。
-    pub fn current(&mut self) -> (String, Option<String>) {
-        let data = self.data.table_mut();
-        let source = data[SOURCE].as_table_mut().unwrap();
+    /// 判断配置文件中是否通过 `replace-with` 属性替换了 `crates-io`
+    pub fn is_replaced(&self) -> bool {
+        self.data
+            .table()
+            .get(SOURCE)
+            .and_then(|source| source.get(CRATES_IO))
+            .and_then(|crates_io| crates_io.get(REPLACE_WITH))
+            .is_some()
+    }
+
+    /// 获取将 `Cargo` 配置写入到文件后，文件内容的变化
+    pub fn diff(&mut self) -> CrmResult<String> {
+        self.normalize()?;
+
+        Ok(unified_diff(
+            &get_cargo_config(&self.path),
+            &self.data.toml_string(),
+            &Msg::DiffCurrent.format(&[&self.path.display()]),
+            &Msg::DiffModified.format(&[&self.path.display()]),
+        ))
+    }
+
+    /// 将 `Cargo` 配置写入到文件中，写入之后释放锁
+    pub fn make(&mut self) -> CrmResult<()> {
+        self.normalize()?;
+        snapshot(&self.path)?;
+        self.data.write(&self.path)?;
+        self.lock = None;
+
+        Ok(())
+    }
+
+    /// 将配置写入到文件中，然后在仍然持有锁的时候执行 `commit`
+    ///
+    /// `commit` 失败时会将配置文件恢复为写入之前的内容 (原来不存在时则删除)，
+    /// 以便配置文件与 `commit` 写入的其他文件 (例如配置方案的记录) 保持一致。
+    pub fn make_with<F: FnOnce() -> CrmResult<()>>(&mut self, commit: F) -> CrmResult<()> {
+        let original = read_to_string(&self.path).ok();
+
+        self.normalize()?;
+        snapshot(&self.path)?;
+        self.data.write(&self.path)?;
+
+        let ret = commit().or_else(|e| {
+            match original {
+                Some(content) => write(&self.path, content)?,
+                None => remove_file(&self.path)?,
+            }
+
+            Err(e)
+        });
+
+        self.lock = None;
+
+        ret
+    }
+
+    /// 如果 `Cargo` 配置文件中不包含 `[source.crates-io]` 属性，则为 `Cargo` 配置自动填充。
+    fn fill_crates_io(&mut self) -> CrmResult<&mut Table> {
+        let path = self.path.clone();
+        let source = self.source_mut()?;
 
-        // 如果 `source` 不包含 `CRATES_IO` 键，则初始化它
         if !source.contains_key(CRATES_IO) {
             source[CRATES_IO] = table();
         }
 
-        let name = {
-            let crates_io = source[CRATES_IO].as_table().unwrap();
-
-            // 从配置文件中获取镜像名
-            if crates_io.contains_key(REPLACE_WITH) {
-                match crates_io[REPLACE_WITH].as_value().unwrap().as_str() {
-                    Some(name) => name,
-                    None => {
-                        field_eprint(
-                            format!("[{SOURCE}.{CRATES_IO}] 下的 {REPLACE_WITH}"),
-                            STRING,
-                        );
-                        process::exit(5);
-                    }
-                }
-            } else {
-                RUST_LANG
-            }
-        };
+        source[CRATES_IO]
+            .as_table_mut()
+            .ok_or_else(|| field_error(&path, format!("[{SOURCE}.{CRATES_IO}]"), Msg::Table))
+    }
 
-        if !source.contains_key(name) {
-            return (name.to_string(), None);
+    /// 如果切换为默认镜像时，则删除 `replace_with` 属性。否则，
+    /// 则为 `[source.creates-io]` 添加 `replace-with` 属性，
+    /// 该属性用于指示要使用的外部镜像的名称。
+    fn replace_with(&mut self, registry_name: &str) -> CrmResult<()> {
+        let crates_io = self.fill_crates_io()?;
+
+        // 去除属性
+        if registry_name.eq(RUST_LANG) {
+            crates_io.remove(REPLACE_WITH);
+            return Ok(());
         }
 
-        let source_name = source[name].as_table().unwrap();
+        // 追加属性
+        crates_io[REPLACE_WITH] = value(registry_name);
 
-        if !source_name.contains_key(REGISTRY) {
-            return (name.to_string(), None);
-        }
+        Ok(())
+    }
+
+    /// 从 `Cargo` 配置文件中获取正在使用的镜像，其中 `rust-lang` 是 `Cargo` 默认使用的镜像。
+    pub fn current(&mut self) -> CrmResult<(String, Option<String>)> {
+        let path = self.path.clone();
+
+        // 从配置文件中获取镜像名，如果 `source` 不包含 `CRATES_IO` 键，则初始化它
+        let name = match self.fill_crates_io()?.get(REPLACE_WITH) {
+            Some(item) => item
+                .as_str()
+                .ok_or_else(|| {
+                    field_error(
+                        &path,
+                        Msg::FieldUnder.format(&[&format!("{SOURCE}.{CRATES_IO}"), &REPLACE_WITH]),
+                        Msg::String,
+                    )
+                })?
+                .to_string(),
+            None => RUST_LANG.to_string(),
+        };
 
-        let addr = source_name[REGISTRY].as_str().map(|v| v.to_string());
+        let addr = self
+            .source_mut()?
+            .get(&name)
+            .and_then(Item::as_table)
+            .and_then(|source_name| source_name.get(REGISTRY))
+            .and_then(Item::as_str)
+            .map(|v| v.to_string());
 
-        (name.to_string(), addr)
+        Ok((name, addr))
     }
 
     /// 追加属性
-    fn append_attribute(&mut self, key: &str, registry_name: &str, addr: &str) {
-        let config: &mut Table = self.data.table_mut();
-        let source: &mut Item = &mut config[key];
+    fn append_attribute(&mut self, key: &str, registry_name: &str, addr: &str) -> CrmResult<()> {
+        let path = self.path.clone();
+        let source = self.table_mut(key)?;
 
         match source.get(registry_name) {
             Some(x) => {
                 if !x.is_table() {
-                    field_eprint(registry_name, TABLE);
-                    process::exit(5);
+                    return Err(field_error(
+                        &path,
+                        format!("[{key}.{registry_name}]"),
+                        Msg::Table,
+                    ));
                 }
             }
             None => source[registry_name] = table(),
         };
 
-        let attr = match key {
-            SOURCE => REGISTRY,
-            REGISTRIES => "index",
-            _ => {
-                to_out(format!("{key} 不是预期的属性名"));
-                process::exit(6);
-            }
-        };
-
         // 不管之前存在的值是什么，都要替换成新的值
-        source[registry_name][attr] = value(addr.to_string());
+        source[registry_name][attribute_name(key)] = value(addr.to_string());
+
+        Ok(())
     }
 
     /// 在 `Cargo` 配置文件中添加新的 `[source.xxx]` 镜像属性，并为其指定 `registry` 属性。
     /// `registry` 属性是强制添加的，`${CARGO_HOME}/.cargo/config` 文件中如果存在则会覆盖。
-    fn append_registry(&mut self, registry_name: &str, addr: String) {
-        self.append_attribute(SOURCE, registry_name, &addr);
+    fn append_registry(&mut self, registry_name: &str, addr: String) -> CrmResult<()> {
+        self.append_attribute(SOURCE, registry_name, &addr)
     }
 
     /// 在 `Cargo` 配置文件中添加新的 `[registries.xxx]` 镜像属性，并为其指定 `index` 属性。
     /// `index` 属性是强制添加的，`${CARGO_HOME}/.cargo/config` 文件中如果存在则会覆盖。
-    fn append_registries(&mut self, remaining_registries: &[(&str, &str)]) {
+    fn append_registries(&mut self, remaining_registries: &[(&str, &str)]) -> CrmResult<()> {
         remaining_registries
             .iter()
-            .for_each(|(registry_name, registry_addr)| {
-                self.append_attribute(REGISTRIES, registry_name, registry_addr);
-            });
+            .try_for_each(|(registry_name, registry_addr)| {
+                self.append_attribute(REGISTRIES, registry_name, registry_addr)
+            })
     }
 
     /// 删除老的属性
+    ///
+    /// 只删除 `crm` 写入的 `registry` 或 `index` 字段，用户添加的其他字段会被保留，
+    /// 没有其他字段时才会删除整个 `[source.xxx]` 表。
     fn remove_attribute(&mut self, key: &str, registry_name: &str) {
         if registry_name.eq(RUST_LANG) {
             return;
         }
 
-        let source: &mut Item = &mut self.data.table_mut()[key];
-
-        // 如果没有 `[source.xxx]` 属性
-        if let None = source.get(registry_name) {
+        // 如果没有 `[source.xxx]` 属性则什么也不做
+        let Some(source) = self
+            .data
+            .table_mut()
+            .get_mut(key)
+            .and_then(Item::as_table_mut)
+        else {
             return;
+        };
+
+        if let Some(table) = source
+            .get_mut(registry_name)
+            .and_then(Item::as_table_like_mut)
+        {
+            table.remove(attribute_name(key));
+
+            if !table.is_empty() {
+                return;
+            }
         }
 
-        source
-            .as_table_mut()
-            .unwrap()
-            .remove(registry_name)
-            .unwrap();
+        source.remove(registry_name);
     }
 
     /// 根据镜像名删除 `config` 中的旧的镜像属性
@@ -229,45 +440,344 @@ impl CargoConfig {
         });
     }
 
+    /// 删除配置文件中的 `[registries.xxx]` 属性，用于清理被禁用的镜像
+    pub fn remove_registries(&mut self, registry_names: &[String]) {
+        registry_names.iter().for_each(|registry_name| {
+            self.remove_attribute(REGISTRIES, registry_name);
+        });
+    }
+
     /// 切换 `Cargo` 配置文件中正在使用的镜像
     pub fn use_registry(
         &mut self,
         registry_name: &str,
-        registry_description: Option<&RegistryDescription>,
+        registry_description: &RegistryDescription,
         remaining_registries: Vec<(&str, &str)>,
-    ) -> Result<(), String> {
-        if registry_description.is_none() {
-            return Err(registry_name.to_string());
-        }
-
+    ) -> CrmResult<()> {
         // 获取老的镜像名
-        let (old_name, _) = self.current();
+        let (old_name, _) = self.current()?;
 
         // 替换镜像源
-        self.replace_with(registry_name);
+        self.replace_with(registry_name)?;
 
         // 删除老的镜像属性
         self.remove_old_registry(&old_name);
         self.remove_old_registries(&[(registry_name, "")]);
         self.remove_old_registries(&remaining_registries);
-        self.append_registries(&remaining_registries);
+        self.append_registries(&remaining_registries)?;
 
         if registry_name.eq(RUST_LANG) {
             return Ok(());
         }
 
         // 追加新的镜像属性
-        self.append_registry(
-            registry_name,
-            registry_description.unwrap().registry.to_string(),
-        );
+        self.append_registry(registry_name, registry_description.registry.to_string())
+    }
 
-        Ok(())
+    /// 将配置文件中的镜像 `old_name` 重命名为 `new_name`，`addr` 是镜像的地址，返回配置文件是否被修改
+    ///
+    /// 正在使用该镜像时会同时更新 `replace-with` 属性和 `[source.xxx]` 属性，`[registries.xxx]` 属性也会被更新。
+    pub fn rename_registry(
+        &mut self,
+        old_name: &str,
+        new_name: &str,
+        addr: &str,
+    ) -> CrmResult<bool> {
+        let mut changed = false;
+
+        if self.current()?.0 == old_name {
+            self.replace_with(new_name)?;
+            self.rename_attribute(SOURCE, old_name, new_name, addr)?;
+            changed = true;
+        }
+
+        let registered = self
+            .data
+            .table()
+            .get(REGISTRIES)
+            .and_then(|v| v.get(old_name))
+            .is_some();
+
+        if registered {
+            self.rename_attribute(REGISTRIES, old_name, new_name, addr)?;
+            changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    /// 将 `[key.old_name]` 表连同用户添加的字段一起移动到 `[key.new_name]`，并更新其中的镜像地址
+    fn rename_attribute(
+        &mut self,
+        key: &str,
+        old_name: &str,
+        new_name: &str,
+        addr: &str,
+    ) -> CrmResult<()> {
+        let source = self.table_mut(key)?;
+
+        if let Some(item) = source.remove(old_name) {
+            source.insert(new_name, item);
+        }
+
+        self.append_attribute(key, new_name, addr)
+    }
+
+    /// 将配置方案中的字段写入到配置中，返回这些字段原来的值
+    ///
+    /// 任何一个字段无法写入时都不会修改配置，`name` 是配置方案的名称，用于错误信息。
+    pub fn apply_settings(&mut self, name: &str, settings: &Table) -> CrmResult<Table> {
+        let settings = leaves(settings);
+        let root = self.data.doc.as_item();
+        let mut previous = Item::Table(Table::new());
+
+        for (path, _) in &settings {
+            if RESERVED_KEYS.contains(&path[0].as_str()) {
+                return Err(reserved_error(name, &path.join(".")));
+            }
+
+            // 路径中的某一级已经存在但不是表时，无法写入该字段
+            for i in 1..path.len() {
+                if get_path(root, &path[..i]).is_some_and(|v| !v.is_table_like()) {
+                    return Err(field_error(&self.path, path[..i].join("."), Msg::Table));
+                }
+            }
+
+            if let Some(item) = get_path(root, path) {
+                let _ = set_path(&mut previous, path, item.clone());
+            }
+        }
+
+        let root = self.data.doc.as_item_mut();
+
+        for (path, item) in settings {
+            set_path(root, &path, item).map_err(|v| field_error(&self.path, v, Msg::Table))?;
+        }
+
+        Ok(previous.into_table().unwrap_or_default())
+    }
+
+    /// 撤销配置方案写入的字段
+    ///
+    /// 仍然是写入时的值的字段会恢复为原来的值 (原来不存在时则删除)，之后被手动修改过的字段保持不变。
+    pub fn revert_settings(&mut self, settings: &Table, previous: &Table) {
+        let root = self.data.doc.as_item_mut();
+        let previous = Item::Table(previous.clone());
+
+        for (path, item) in leaves(settings) {
+            if !get_path(root, &path).is_some_and(|v| same_value(v, &item)) {
+                continue;
+            }
+
+            match get_path(&previous, &path) {
+                Some(old) => {
+                    let _ = set_path(root, &path, old.clone());
+                }
+                None => remove_path(root, &path),
+            }
+        }
     }
 }
 
-impl Default for CargoConfig {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    /// 读取内容为 `content` 的配置文件
+    fn config(name: &str, content: &str) -> CargoConfig {
+        let path = env::temp_dir().join(format!("crm-{}-{}.toml", process::id(), name));
+
+        fs::write(&path, content).unwrap();
+
+        let config = CargoConfig::new(path.clone()).unwrap();
+
+        fs::remove_file(path).unwrap();
+        config
+    }
+
+    fn settings(content: &str) -> Table {
+        Toml::parse(content).unwrap().table().clone()
+    }
+
+    /// 获取 `key` 对应的值，例如 `http.proxy`
+    fn get(config: &CargoConfig, key: &str) -> Option<String> {
+        let path: Vec<String> = key.split('.').map(str::to_string).collect();
+
+        get_path(config.data.doc.as_item(), &path).map(|v| v.to_string().trim().to_string())
+    }
+
+    #[test]
+    fn apply_switch_revert_round_trip() {
+        let mut config = config(
+            "round-trip",
+            "[http]\nproxy = \"http://orig\"\ntimeout = 30\n\n[net]\nretry = 2\n",
+        );
+        let a = settings("[http]\nproxy = \"http://a\"\n\n[net]\noffline = true\n");
+        let b = settings("[http]\nproxy = \"http://b\"\ntimeout = 60\n");
+
+        let previous_a = config.apply_settings("a", &a).unwrap();
+
+        assert_eq!(get(&config, "http.proxy").as_deref(), Some("\"http://a\""));
+        assert_eq!(get(&config, "net.offline").as_deref(), Some("true"));
+
+        // 切换到 b 时先撤销 a
+        config.revert_settings(&a, &previous_a);
+
+        let previous_b = config.apply_settings("b", &b).unwrap();
+
+        assert_eq!(get(&config, "http.proxy").as_deref(), Some("\"http://b\""));
+        assert_eq!(get(&config, "http.timeout").as_deref(), Some("60"));
+        assert_eq!(get(&config, "net.offline"), None);
+
+        config.revert_settings(&b, &previous_b);
+
+        assert_eq!(
+            get(&config, "http.proxy").as_deref(),
+            Some("\"http://orig\"")
+        );
+        assert_eq!(get(&config, "http.timeout").as_deref(), Some("30"));
+        assert_eq!(get(&config, "net.retry").as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn revert_keeps_manual_changes() {
+        let mut config = config("manual", "");
+        let a = settings("[http]\nproxy = \"http://a\"\ncheck-revoke = false\n");
+        let previous = config.apply_settings("a", &a).unwrap();
+
+        config.data.doc["http"]["proxy"] = value("http://manual");
+        config.revert_settings(&a, &previous);
+
+        assert_eq!(
+            get(&config, "http.proxy").as_deref(),
+            Some("\"http://manual\"")
+        );
+        assert_eq!(get(&config, "http.check-revoke"), None);
+    }
+
+    #[test]
+    fn apply_rejects_invalid_settings_without_changes() {
+        let mut config = config("invalid", "http = \"x\"\n");
+        let before = config.data.toml_string();
+
+        assert!(config
+            .apply_settings("a", &settings("[source.x]\nregistry = \"y\"\n"))
+            .is_err());
+        assert!(config
+            .apply_settings("a", &settings("[net]\nretry = 1\n[http]\nproxy = \"p\"\n"))
+            .is_err());
+        assert_eq!(config.data.toml_string(), before);
+    }
+
+    fn description(registry: &str) -> RegistryDescription {
+        RegistryDescription {
+            registry: registry.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn loading_does_not_add_fields() {
+        let config = config("load", "[build]\njobs = 2\n");
+
+        assert_eq!(config.data.toml_string(), "[build]\njobs = 2");
+        assert!(config.settings(&[NET]).is_empty());
+    }
+
+    #[test]
+    fn local_config_gets_no_defaults_or_empty_tables() {
+        let mut config = config("local", "[build]\njobs = 2\n");
+
+        config
+            .use_registry("tuna", &description("https://tuna/git"), vec![])
+            .unwrap();
+        config.normalize().unwrap();
+
+        assert_eq!(
+            config.data.toml_string(),
+            "[build]\njobs = 2\n\n[source.crates-io]\nreplace-with = \"tuna\"\n\n[source.tuna]\nregistry = \"https://tuna/git\""
+        );
+
+        config
+            .use_registry(RUST_LANG, &description(""), vec![])
+            .unwrap();
+        config.normalize().unwrap();
+
+        assert_eq!(config.data.toml_string(), "[build]\njobs = 2");
+    }
+
+    #[test]
+    fn global_config_gets_git_fetch_with_cli() {
+        let mut config = CargoConfig {
+            path: cargo_config_path().unwrap(),
+            lock: None,
+            data: Toml::parse("[net]\nretry = 3\n").unwrap(),
+        };
+
+        config.normalize().unwrap();
+
+        assert_eq!(
+            get(&config, "net.git-fetch-with-cli").as_deref(),
+            Some("true")
+        );
+        assert_eq!(get(&config, "net.retry").as_deref(), Some("3"));
+
+        // 用户设置的值不会被覆盖
+        config.data.doc["net"]["git-fetch-with-cli"] = value(false);
+        config.normalize().unwrap();
+
+        assert_eq!(
+            get(&config, "net.git-fetch-with-cli").as_deref(),
+            Some("false")
+        );
+    }
+
+    #[test]
+    fn switching_keeps_user_fields() {
+        let mut config = config(
+            "fields",
+            "[source.crates-io]\nreplace-with = \"mine\"\n\n[source.mine]\nregistry = \"https://m/git\"\ncustom = 1\n\n[registries.tuna]\nindex = \"https://tuna/git\"\ntoken = \"t\"\n",
+        );
+
+        config
+            .rename_registry("mine", "ours", "https://m/git")
+            .unwrap();
+
+        assert_eq!(
+            get(&config, "source.crates-io.replace-with").as_deref(),
+            Some("\"ours\"")
+        );
+        assert_eq!(get(&config, "source.ours.custom").as_deref(), Some("1"));
+        assert_eq!(get(&config, "source.mine"), None);
+
+        config
+            .use_registry(
+                "tuna",
+                &description("https://tuna/git"),
+                vec![("sjtu", "https://sjtu/git")],
+            )
+            .unwrap();
+
+        // `crm` 只删除自己写入的字段
+        assert_eq!(get(&config, "source.ours.registry"), None);
+        assert_eq!(get(&config, "source.ours.custom").as_deref(), Some("1"));
+        assert_eq!(get(&config, "registries.tuna.index"), None);
+        assert_eq!(
+            get(&config, "registries.tuna.token").as_deref(),
+            Some("\"t\"")
+        );
+        assert_eq!(
+            get(&config, "registries.sjtu.index").as_deref(),
+            Some("\"https://sjtu/git\"")
+        );
+
+        config
+            .use_registry(RUST_LANG, &description(""), vec![])
+            .unwrap();
+
+        // 没有其他字段的表会被删除
+        assert_eq!(get(&config, "source.tuna"), None);
     }
 }
@@ -0,0 +1,202 @@
+//! # 软件包校验
+//!
+//! 能够连上镜像并不代表镜像提供的软件包与官方的完全相同。`crm verify` 会通过镜像的 `dl`
+//! 下载一些软件包，计算它们的 `SHA-256`，并与镜像索引和官方索引中的 `cksum` 字段进行比较。
+//!
+//! 为了保持最小依赖，该模块实现了 `SHA-256` 算法 (参考 FIPS 180-4)。
+
+/// `SHA-256` 的轮常量
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// `SHA-256` 的初始哈希值
+const H: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// 处理一个 64 字节的分组
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+
+    for (i, chunk) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (v, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *v = v.wrapping_add(x);
+    }
+}
+
+/// 计算 `data` 的 `SHA-256`，以小写的十六进制字符串返回
+pub fn sha256(data: &[u8]) -> String {
+    let mut state = H;
+    let mut blocks = data.chunks_exact(64);
+
+    for block in &mut blocks {
+        compress(&mut state, block);
+    }
+
+    // 填充: 追加 `0x80`，然后补 `0` 直到长度模 64 余 56，最后追加以比特为单位的原始长度
+    let mut tail = blocks.remainder().to_vec();
+
+    tail.push(0x80);
+
+    while tail.len() % 64 != 56 {
+        tail.push(0);
+    }
+
+    tail.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in tail.chunks_exact(64) {
+        compress(&mut state, block);
+    }
+
+    state.iter().map(|v| format!("{:08x}", v)).collect()
+}
+
+/// 校验和不一致的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// 从镜像下载的软件包与官方索引中的 `cksum` 不一致
+    File,
+
+    /// 镜像索引中的 `cksum` 与官方索引中的不一致
+    Index,
+}
+
+impl MismatchKind {
+    /// 在输出中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MismatchKind::File => "file",
+            MismatchKind::Index => "index",
+        }
+    }
+}
+
+/// 一处校验和不一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// 软件包名
+    pub krate: String,
+
+    /// 软件包的版本
+    pub version: String,
+
+    /// 不一致的位置
+    pub kind: MismatchKind,
+
+    /// 官方索引中的 `cksum`
+    pub expected: String,
+
+    /// 镜像提供的校验和
+    pub actual: String,
+}
+
+/// 校验的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Integrity {
+    /// 校验过的软件包的数量
+    pub checked: usize,
+
+    /// 所有不一致的地方
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Integrity {
+    /// 是否所有的校验和都一致
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// 校验的结果，无法完成校验时为失败的原因
+pub type IntegrityCheck = Result<Integrity, String>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fips_180_4_vectors() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            sha256(&vec![b'a'; 1_000_000]),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn padding_edges() {
+        // 55 字节的填充刚好放得下一个分组，56 字节需要两个分组，64 字节刚好是一个完整的分组
+        for (len, expected) in [
+            (
+                55,
+                "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+            ),
+            (
+                56,
+                "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+            ),
+            (
+                64,
+                "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+            ),
+        ] {
+            assert_eq!(sha256(&vec![b'a'; len]), expected, "{}", len);
+        }
+    }
+}
@@ -0,0 +1,786 @@
+//! # 命令定义
+//!
+//! 该模块以声明的方式定义了 `crm` 支持的所有命令、位置参数和选项，
+//! 命令行参数的解析、校验以及帮助信息的生成都基于这些定义。
+
+use std::collections::HashMap;
+
+use crate::{
+    completion::SHELLS,
+    constants::APP_NAME,
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+    utils::append_end_spaces,
+};
+
+/// 选项值的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// 任意字符串
+    Text,
+
+    /// 正整数
+    Number,
+
+    /// 非负整数
+    Count,
+
+    /// 只能是其中的某一个值
+    Choice(&'static [&'static str]),
+}
+
+/// 命令行选项，例如 `--local` 或 `--format <fmt>`
+#[derive(Debug, Clone, Copy)]
+pub struct Opt {
+    /// 选项名，不包含 `--` 前缀
+    pub name: &'static str,
+
+    /// 短选项名，例如 `-v` 中的 `v`
+    pub short: Option<char>,
+
+    /// 选项值的名称和类型，为 `None` 时表示这是一个开关选项
+    pub value: Option<(&'static str, ValueKind)>,
+
+    /// 帮助信息
+    pub help: Msg,
+}
+
+/// 位置参数的补全方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Complete {
+    /// 不补全
+    Nothing,
+
+    /// 补全为镜像名
+    Registry,
+
+    /// 补全为命令名
+    Command,
+
+    /// 补全为其中的某一个值
+    Values(&'static [&'static str]),
+}
+
+/// 位置参数
+#[derive(Debug, Clone, Copy)]
+pub struct Positional {
+    /// 参数名
+    pub name: &'static str,
+
+    /// 是否是必填参数
+    pub required: bool,
+
+    /// 帮助信息
+    pub help: Msg,
+
+    /// 补全方式
+    pub complete: Complete,
+}
+
+impl Positional {
+    /// 设置补全方式
+    const fn complete(mut self, complete: Complete) -> Self {
+        self.complete = complete;
+        self
+    }
+}
+
+/// 命令
+#[derive(Debug, Clone, Copy)]
+pub struct Command {
+    /// 命令名
+    pub name: &'static str,
+
+    /// 帮助信息
+    pub help: Msg,
+
+    /// 位置参数
+    pub args: &'static [Positional],
+
+    /// 可选的子模式，例如 `crm best git`，仅用于生成帮助信息
+    pub modes: &'static [(&'static str, Msg)],
+
+    /// 选项
+    pub options: &'static [Opt],
+
+    /// 是否将剩余的参数原样传递给 `cargo`
+    pub passthrough: bool,
+
+    /// 是否在帮助信息和补全中隐藏该命令
+    pub hidden: bool,
+}
+
+impl Command {
+    /// 创建一个没有任何参数的命令
+    const fn new(name: &'static str, help: Msg) -> Self {
+        Command {
+            name,
+            help,
+            args: &[],
+            modes: &[],
+            options: &[],
+            passthrough: false,
+            hidden: false,
+        }
+    }
+
+    /// 设置位置参数
+    const fn args(mut self, args: &'static [Positional]) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// 设置子模式
+    const fn modes(mut self, modes: &'static [(&'static str, Msg)]) -> Self {
+        self.modes = modes;
+        self
+    }
+
+    /// 设置选项
+    const fn options(mut self, options: &'static [Opt]) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 将剩余的参数原样传递给 `cargo`
+    const fn passthrough(mut self) -> Self {
+        self.passthrough = true;
+        self
+    }
+
+    /// 在帮助信息和补全中隐藏该命令
+    const fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// 获取命令的用法，例如 `crm save <name> <addr> <dl>`
+    pub fn usage(&self) -> String {
+        let mut usage = format!("{} {}", APP_NAME, self.name);
+
+        for arg in self.args {
+            if arg.required {
+                usage.push_str(&format!(" <{}>", arg.name));
+            } else {
+                usage.push_str(&format!(" [{}]", arg.name));
+            }
+        }
+
+        if self.passthrough {
+            usage.push_str(" [args]");
+        }
+
+        usage
+    }
+
+    /// 根据选项名查找选项
+    pub fn option(&self, name: &str) -> Option<&'static Opt> {
+        self.options.iter().find(|v| v.name == name)
+    }
+}
+
+/// 创建位置参数
+const fn arg(name: &'static str, required: bool, help: Msg) -> Positional {
+    Positional {
+        name,
+        required,
+        help,
+        complete: Complete::Nothing,
+    }
+}
+
+/// 创建开关选项
+const fn flag(name: &'static str, help: Msg) -> Opt {
+    Opt {
+        name,
+        short: None,
+        value: None,
+        help,
+    }
+}
+
+/// 创建带值的选项
+const fn value(name: &'static str, value: &'static str, kind: ValueKind, help: Msg) -> Opt {
+    Opt {
+        name,
+        short: None,
+        value: Some((value, kind)),
+        help,
+    }
+}
+
+impl Opt {
+    /// 设置短选项名
+    const fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+}
+
+const LOCAL: Opt = flag("local", Msg::HelpLocal);
+const PATH: Opt = value("path", "dir", ValueKind::Text, Msg::HelpPath);
+const DRY_RUN: Opt = flag("dry-run", Msg::HelpDryRun);
+const FORMAT: Opt = value(
+    "format",
+    "fmt",
+    ValueKind::Choice(&["text", "json", "tsv"]),
+    Msg::HelpFormat,
+);
+const TIMEOUT: Opt = value("timeout", "secs", ValueKind::Number, Msg::HelpTimeout);
+
+const SAMPLES: Opt = value("samples", "n", ValueKind::Number, Msg::HelpSamples);
+
+const CRATES: Opt = value("crates", "list", ValueKind::Text, Msg::HelpCrates);
+const MAX_BYTES: Opt = value("max-bytes", "bytes", ValueKind::Number, Msg::HelpMaxBytes);
+const MAX_TIME: Opt = value("max-time", "secs", ValueKind::Number, Msg::HelpMaxTime);
+const CRATE: Opt = value("crate", "name", ValueKind::Text, Msg::HelpCrate);
+const INDEX_CRATES: Opt = value(
+    "index-crates",
+    "list",
+    ValueKind::Text,
+    Msg::HelpIndexCrates,
+);
+const MAX_LAG: Opt = value("max-lag", "n", ValueKind::Count, Msg::HelpMaxLag);
+const VERIFY: Opt = flag("verify", Msg::HelpVerifyFlag);
+const PROXY: Opt = value("proxy", "url", ValueKind::Text, Msg::HelpProxy);
+const JOBS: Opt = value("jobs", "n", ValueKind::Number, Msg::HelpJobs);
+const DEADLINE: Opt = value("deadline", "secs", ValueKind::Number, Msg::HelpDeadline);
+const DETAILED: Opt = flag("detailed", Msg::HelpDetailed);
+const LABEL: Opt = value("label", "name", ValueKind::Text, Msg::HelpLabel);
+const FROM_HISTORY: Opt = flag("from-history", Msg::HelpFromHistory);
+const DAYS: Opt = value("days", "n", ValueKind::Number, Msg::HelpDays);
+const VERBOSE: Opt = flag("verbose", Msg::HelpVerbose).short('v');
+const TAG: Opt = value("tag", "tag", ValueKind::Text, Msg::HelpTag);
+const FILTER: Opt = value("filter", "expr", ValueKind::Text, Msg::HelpFilter);
+const ALL: Opt = flag("all", Msg::HelpAll);
+const CACERT: Opt = value("cacert", "file", ValueKind::Text, Msg::HelpCaCert);
+const BY: Opt = value(
+    "by",
+    "rank",
+    ValueKind::Choice(&["latency", "throughput"]),
+    Msg::HelpBy,
+);
+
+/// `crm` 支持的所有命令
+pub const COMMANDS: &[Command] = &[
+    Command::new("best", Msg::HelpBest)
+        .args(&[
+            arg("mode", false, Msg::HelpBestMode).complete(Complete::Values(&[
+                "git",
+                "sparse",
+                "git-download",
+                "sparse-download",
+            ])),
+        ])
+        .modes(&[
+            ("git", Msg::HelpBestGit),
+            ("sparse", Msg::HelpBestSparse),
+            ("git-download", Msg::HelpBestGitDownload),
+            ("sparse-download", Msg::HelpBestSparseDownload),
+        ])
+        .options(&[
+            LOCAL,
+            PATH,
+            DRY_RUN,
+            FORMAT,
+            TIMEOUT,
+            SAMPLES,
+            JOBS,
+            DEADLINE,
+            BY,
+            CRATES,
+            MAX_BYTES,
+            MAX_TIME,
+            MAX_LAG,
+            INDEX_CRATES,
+            VERIFY,
+            FROM_HISTORY,
+            LABEL,
+            TAG,
+            FILTER,
+            ALL,
+            PROXY,
+            CACERT,
+        ]),
+    Command::new("alias", Msg::HelpAlias)
+        .args(&[
+            arg("action", true, Msg::HelpAliasAction)
+                .complete(Complete::Values(&["list", "save", "remove"])),
+            arg("alias", false, Msg::HelpAliasName),
+            arg("name", false, Msg::HelpAliasRegistry).complete(Complete::Registry),
+        ])
+        .modes(&[
+            ("list", Msg::HelpAliasList),
+            ("save", Msg::HelpAliasSave),
+            ("remove", Msg::HelpAliasRemove),
+        ])
+        .options(&[DRY_RUN, FORMAT]),
+    Command::new("bench", Msg::HelpBench)
+        .args(&[arg("name", false, Msg::HelpBenchName).complete(Complete::Registry)])
+        .options(&[
+            FORMAT, TIMEOUT, CRATES, MAX_BYTES, MAX_TIME, LABEL, ALL, PROXY, CACERT,
+        ]),
+    Command::new("current", Msg::HelpCurrent).options(&[FORMAT]),
+    Command::new("default", Msg::HelpDefault).options(&[LOCAL, PATH, DRY_RUN]),
+    Command::new("disable", Msg::HelpDisable)
+        .args(&[arg("name", true, Msg::HelpName).complete(Complete::Registry)])
+        .options(&[LOCAL, PATH, DRY_RUN]),
+    Command::new("enable", Msg::HelpEnable)
+        .args(&[arg("name", true, Msg::HelpName).complete(Complete::Registry)])
+        .options(&[DRY_RUN]),
+    Command::new("freshness", Msg::HelpFreshness)
+        .args(&[arg("name", false, Msg::HelpFreshnessName).complete(Complete::Registry)])
+        .options(&[
+            FORMAT,
+            TIMEOUT,
+            JOBS,
+            DEADLINE,
+            INDEX_CRATES,
+            ALL,
+            PROXY,
+            CACERT,
+        ]),
+    Command::new("install", Msg::HelpInstall).passthrough(),
+    Command::new("list", Msg::HelpList).options(&[FORMAT, VERBOSE, TAG, ALL]),
+    Command::new("probe", Msg::HelpProbe)
+        .args(&[arg("name", false, Msg::HelpProbeName).complete(Complete::Registry)])
+        .options(&[FORMAT, TIMEOUT, JOBS, DEADLINE, CRATE, ALL, PROXY, CACERT]),
+    Command::new("profile", Msg::HelpProfile)
+        .args(&[
+            arg("action", true, Msg::HelpProfileAction).complete(Complete::Values(&[
+                "use", "reset", "list", "save", "remove",
+            ])),
+            arg("name", false, Msg::HelpProfileName),
+            arg("registry", false, Msg::HelpProfileRegistry).complete(Complete::Registry),
+        ])
+        .modes(&[
+            ("use", Msg::HelpProfileUse),
+            ("reset", Msg::HelpProfileReset),
+            ("list", Msg::HelpProfileList),
+            ("save", Msg::HelpProfileSave),
+            ("remove", Msg::HelpProfileRemove),
+        ])
+        .options(&[LOCAL, PATH, DRY_RUN, FORMAT]),
+    Command::new("publish", Msg::HelpPublish).passthrough(),
+    Command::new("remove", Msg::HelpRemove)
+        .args(&[arg("name", true, Msg::HelpName).complete(Complete::Registry)])
+        .options(&[DRY_RUN]),
+    Command::new("rename", Msg::HelpRename)
+        .args(&[
+            arg("old", true, Msg::HelpRenameOld).complete(Complete::Registry),
+            arg("new", true, Msg::HelpRenameNew),
+        ])
+        .options(&[LOCAL, PATH, DRY_RUN]),
+    Command::new("report", Msg::HelpReport)
+        .args(&[arg("name", false, Msg::HelpReportName).complete(Complete::Registry)])
+        .options(&[FORMAT, DAYS, LABEL]),
+    Command::new("save", Msg::HelpSave)
+        .args(&[
+            arg("name", true, Msg::HelpName),
+            arg("addr", true, Msg::HelpAddr),
+            arg("dl", true, Msg::HelpDl),
+        ])
+        .options(&[DRY_RUN]),
+    Command::new("test", Msg::HelpTest)
+        .args(&[arg("name", false, Msg::HelpTestName).complete(Complete::Registry)])
+        .options(&[
+            FORMAT, TIMEOUT, SAMPLES, JOBS, DEADLINE, DETAILED, LABEL, ALL, PROXY, CACERT,
+        ]),
+    Command::new("update", Msg::HelpUpdate).passthrough(),
+    Command::new("use", Msg::HelpUse)
+        .args(&[arg("name", true, Msg::HelpName).complete(Complete::Registry)])
+        .options(&[LOCAL, PATH, DRY_RUN]),
+    Command::new("verify", Msg::HelpVerify)
+        .args(&[arg("name", false, Msg::HelpVerifyName).complete(Complete::Registry)])
+        .options(&[FORMAT, TIMEOUT, JOBS, DEADLINE, CRATES, ALL, PROXY, CACERT]),
+    Command::new("version", Msg::HelpVersion),
+    Command::new("check-update", Msg::HelpCheckUpdate).options(&[PROXY, CACERT]),
+    Command::new("history", Msg::HelpHistory).args(&[arg("n", false, Msg::HelpSnapshotIndex)]),
+    Command::new("rollback", Msg::HelpRollback).args(&[arg("n", false, Msg::HelpSnapshotIndex)]),
+    Command::new("completions", Msg::HelpCompletions)
+        .args(&[arg("shell", true, Msg::HelpShell).complete(Complete::Values(SHELLS))]),
+    Command::new("help", Msg::HelpHelp)
+        .args(&[arg("command", false, Msg::HelpCommandName).complete(Complete::Command)]),
+    Command::new("__complete", Msg::HelpComplete)
+        .passthrough()
+        .hidden(),
+];
+
+/// 根据命令名查找命令
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|v| v.name == name)
+}
+
+/// 用法一栏的宽度
+const USAGE_WIDTH: usize = 30;
+
+/// 将用法和帮助信息对齐到同一行，如果用法太长，则将帮助信息放到下一行
+fn help_line(indent: usize, usage: &str, help: Msg) -> String {
+    let usage = format!("{}{}", " ".repeat(indent), usage);
+
+    if usage.chars().count() < USAGE_WIDTH {
+        format!("{}{}", append_end_spaces(&usage, Some(USAGE_WIDTH)), help)
+    } else {
+        format!("{}\n{}{}", usage, " ".repeat(USAGE_WIDTH), help)
+    }
+}
+
+/// 获取所有命令的帮助信息
+pub fn overview() -> String {
+    let mut lines = vec![String::new()];
+
+    for command in COMMANDS.iter().filter(|v| !v.hidden) {
+        lines.push(help_line(2, &command.usage(), command.help));
+
+        for (mode, help) in command.modes {
+            let usage = format!("{} {} {}", APP_NAME, command.name, mode);
+            lines.push(help_line(4, &usage, *help));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push(Msg::HelpMore.format(&[&APP_NAME]));
+    lines.push(String::new());
+
+    lines.join("\n")
+}
+
+/// 获取某个命令的详细帮助信息
+pub fn command_help(command: &Command) -> String {
+    let mut lines = vec![
+        String::new(),
+        format!("  {}", command.help),
+        String::new(),
+        Msg::HelpUsage.format(&[&command.usage()]),
+    ];
+
+    if !command.args.is_empty() || !command.modes.is_empty() {
+        lines.push(String::new());
+        lines.push(Msg::HelpArguments.to_string());
+
+        for arg in command.args {
+            lines.push(help_line(4, arg.name, arg.help));
+        }
+
+        for (mode, help) in command.modes {
+            lines.push(help_line(6, mode, *help));
+        }
+    }
+
+    if command.passthrough {
+        lines.push(String::new());
+        lines.push(Msg::HelpPassthrough.format(&[&command.name]));
+    } else {
+        lines.push(String::new());
+        lines.push(Msg::HelpOptions.to_string());
+
+        for opt in command.options {
+            let usage = match opt.value {
+                Some((value, _)) => format!("--{} <{}>", opt.name, value),
+                None => format!("--{}", opt.name),
+            };
+            let usage = match opt.short {
+                Some(short) => format!("-{}, {}", short, usage),
+                None => usage,
+            };
+
+            lines.push(help_line(4, &usage, opt.help));
+        }
+
+        lines.push(help_line(4, "-h, --help", Msg::HelpHelp));
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// 解析后的命令行参数
+#[derive(Debug)]
+pub struct Matches {
+    /// 要执行的命令
+    pub command: &'static Command,
+
+    /// 位置参数，对于需要将参数传递给 `cargo` 的命令，则为剩余的所有参数
+    pub args: Vec<String>,
+
+    /// 选项及其值，开关选项的值为 `None`
+    options: HashMap<&'static str, Option<String>>,
+}
+
+impl Matches {
+    /// 获取第 `index` 个位置参数
+    pub fn arg(&self, index: usize) -> Option<&String> {
+        self.args.get(index)
+    }
+
+    /// 判断是否指定了开关选项
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    /// 获取选项的值
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|v| v.as_deref())
+    }
+
+    /// 获取数字类型的选项的值，在解析时已经校验过了
+    pub fn number(&self, name: &str) -> Option<u64> {
+        self.value(name).and_then(|v| v.parse().ok())
+    }
+}
+
+/// 创建用法错误
+fn usage_error(command: &Command, message: String) -> CrmError {
+    CrmError::Usage {
+        command: command.name.to_string(),
+        message,
+    }
+}
+
+/// 校验选项的值
+fn check_value(command: &Command, opt: &Opt, value: &str) -> CrmResult<()> {
+    let valid = match opt.value {
+        Some((_, ValueKind::Number)) => value.parse::<u64>().is_ok_and(|v| v > 0),
+        Some((_, ValueKind::Count)) => value.parse::<u64>().is_ok(),
+        Some((_, ValueKind::Choice(choices))) => choices.contains(&value),
+        _ => true,
+    };
+
+    if valid {
+        return Ok(());
+    }
+
+    let expected = match opt.value {
+        Some((_, ValueKind::Choice(choices))) => choices.join(Msg::Separator.text()),
+        Some((_, ValueKind::Count)) => Msg::NonNegativeInteger.to_string(),
+        _ => Msg::PositiveInteger.to_string(),
+    };
+
+    Err(usage_error(
+        command,
+        Msg::InvalidOptionValue.format(&[&opt.name, &value, &expected]),
+    ))
+}
+
+/// 解析命令行参数，不包括程序名
+pub fn parse(mut args: Vec<String>) -> CrmResult<Matches> {
+    if args.is_empty() {
+        return Err(CrmError::UnknownCommand(String::new()));
+    }
+
+    let name = args.remove(0).trim().to_lowercase();
+    let name = match name.as_str() {
+        "-h" | "--help" => "help".to_string(),
+        "-v" | "--version" => "version".to_string(),
+        _ => name,
+    };
+    let command = find_command(&name).ok_or(CrmError::UnknownCommand(name))?;
+
+    // 剩余的参数原样传递给 `cargo`，包括 `--help` 以及参数中的空白字符
+    if command.passthrough {
+        return Ok(Matches {
+            command,
+            args,
+            options: HashMap::new(),
+        });
+    }
+
+    let mut matches = Matches {
+        command,
+        args: vec![],
+        options: HashMap::new(),
+    };
+    let mut iter = args.into_iter().map(|v| v.trim().to_string());
+
+    while let Some(arg) = iter.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Matches {
+                command: find_command("help").unwrap(),
+                args: vec![command.name.to_string()],
+                options: HashMap::new(),
+            });
+        }
+
+        // 将短选项转换为对应的长选项，例如 `-v` 转换为 `--verbose`
+        let arg = match command
+            .options
+            .iter()
+            .find(|v| v.short.is_some_and(|c| arg == format!("-{}", c)))
+        {
+            Some(opt) => format!("--{}", opt.name),
+            None => arg,
+        };
+
+        let Some(option) = arg.strip_prefix("--") else {
+            if matches.args.len() >= command.args.len() {
+                return Err(usage_error(command, Msg::ExtraArgument.format(&[&arg])));
+            }
+
+            matches.args.push(arg);
+            continue;
+        };
+
+        let (name, inline) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option, None),
+        };
+        let opt = command
+            .option(name)
+            .ok_or_else(|| usage_error(command, Msg::UnknownOption.format(&[&name])))?;
+
+        let value = match opt.value {
+            Some((value, _)) => {
+                let v = inline.or_else(|| iter.next()).ok_or_else(|| {
+                    usage_error(
+                        command,
+                        Msg::MissingOptionValue.format(&[&opt.name, &value]),
+                    )
+                })?;
+
+                check_value(command, opt, &v)?;
+                Some(v)
+            }
+            None if inline.is_some() => {
+                return Err(usage_error(
+                    command,
+                    Msg::UnexpectedOptionValue.format(&[&opt.name]),
+                ))
+            }
+            None => None,
+        };
+
+        matches.options.insert(opt.name, value);
+    }
+
+    // 必填的位置参数都排在可选的位置参数前面，所以只需要检查缺少的参数中是否有必填参数
+    if let Some(arg) = command
+        .args
+        .iter()
+        .skip(matches.args.len())
+        .find(|v| v.required)
+    {
+        return Err(usage_error(
+            command,
+            Msg::MissingPositional.format(&[&arg.name]),
+        ));
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> CrmResult<Matches> {
+        parse(args.iter().map(|v| v.to_string()).collect())
+    }
+
+    fn usage_message(args: &[&str]) -> String {
+        match run(args) {
+            Err(CrmError::Usage { message, .. }) => message,
+            v => panic!("expected a usage error, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn parses_arguments_and_options() {
+        let matches = run(&["test", "tuna", "--timeout=3", "--format", "json", "--all"]).unwrap();
+
+        assert_eq!(matches.command.name, "test");
+        assert_eq!(matches.arg(0).map(String::as_str), Some("tuna"));
+        assert_eq!(matches.number("timeout"), Some(3));
+        assert_eq!(matches.value("format"), Some("json"));
+        assert!(matches.flag("all"));
+        assert!(!matches.flag("detailed"));
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert_eq!(
+            usage_message(&["use", "tuna", "--unknown"]),
+            Msg::UnknownOption.format(&[&"unknown"])
+        );
+        assert_eq!(
+            usage_message(&["list", "--timeout=3"]),
+            Msg::UnknownOption.format(&[&"timeout"])
+        );
+    }
+
+    #[test]
+    fn rejects_extra_and_missing_arguments() {
+        assert_eq!(
+            usage_message(&["use", "tuna", "sjtu"]),
+            Msg::ExtraArgument.format(&[&"sjtu"])
+        );
+        assert_eq!(
+            usage_message(&["use"]),
+            Msg::MissingPositional.format(&[&"name"])
+        );
+        assert_eq!(
+            usage_message(&["save", "mine", "https://m/git"]),
+            Msg::MissingPositional.format(&[&"dl"])
+        );
+    }
+
+    #[test]
+    fn validates_option_values() {
+        let positive = Msg::PositiveInteger.to_string();
+
+        assert_eq!(
+            usage_message(&["test", "--timeout", "0"]),
+            Msg::InvalidOptionValue.format(&[&"timeout", &"0", &positive])
+        );
+        assert_eq!(
+            usage_message(&["test", "--samples=abc"]),
+            Msg::InvalidOptionValue.format(&[&"samples", &"abc", &positive])
+        );
+        assert_eq!(
+            usage_message(&["list", "--format", "xml"]),
+            Msg::InvalidOptionValue.format(&[
+                &"format",
+                &"xml",
+                &["text", "json", "tsv"].join(Msg::Separator.text())
+            ])
+        );
+        assert_eq!(
+            usage_message(&["test", "--timeout"]),
+            Msg::MissingOptionValue.format(&[&"timeout", &"secs"])
+        );
+        assert_eq!(
+            usage_message(&["test", "--all=1"]),
+            Msg::UnexpectedOptionValue.format(&[&"all"])
+        );
+    }
+
+    #[test]
+    fn maps_help_and_version_flags() {
+        assert_eq!(run(&["-h"]).unwrap().command.name, "help");
+        assert_eq!(run(&["--help"]).unwrap().command.name, "help");
+        assert_eq!(run(&["-v"]).unwrap().command.name, "version");
+        assert_eq!(run(&["--version"]).unwrap().command.name, "version");
+
+        let matches = run(&["use", "-h"]).unwrap();
+
+        assert_eq!(matches.command.name, "help");
+        assert_eq!(matches.args, ["use"]);
+
+        // 命令的短选项优先于 `-v`
+        assert!(run(&["list", "-v"]).unwrap().flag("verbose"));
+        assert!(matches!(
+            run(&["--unknown"]),
+            Err(CrmError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn passes_arguments_through_verbatim() {
+        for name in ["publish", "update", "install"] {
+            let args = [name, "--help", " --features", "a b ", "--unknown"];
+            let matches = run(&args).unwrap();
+
+            assert_eq!(matches.command.name, name);
+            assert_eq!(matches.args, &args[1..]);
+        }
+    }
+}
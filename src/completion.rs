@@ -0,0 +1,193 @@
+//! # 自动补全
+//!
+//! `crm completions <shell>` 会输出对应 shell 的自动补全脚本。补全脚本本身只负责收集命令行中的单词，
+//! 然后调用隐藏的 `crm __complete` 命令来获取候选项，所以命令、子模式、选项以及保存在 `.crmrc`
+//! 中的自定义镜像都能够被补全，并且始终与 `command` 模块中的命令定义保持一致。
+
+use std::{collections::BTreeSet, str::FromStr};
+
+use crate::{
+    command::{find_command, Complete, ValueKind, COMMANDS},
+    error::CrmError,
+    i18n::Msg,
+    runtime::RuntimeConfig,
+};
+
+/// 支持的 shell
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "elvish"];
+
+/// `bash` 的自动补全脚本
+const BASH: &str = r#"_crm() {
+    local IFS=$'\n'
+    COMPREPLY=($(crm __complete "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null))
+}
+
+complete -o default -F _crm crm
+"#;
+
+/// `zsh` 的自动补全脚本
+const ZSH: &str = r#"#compdef crm
+
+_crm() {
+    local -a candidates
+    candidates=(${(f)"$(crm __complete "${(@)words[2,CURRENT]}" 2>/dev/null)"})
+    compadd -a candidates
+}
+
+if [ "$funcstack[1]" = "_crm" ]; then
+    _crm "$@"
+else
+    compdef _crm crm
+fi
+"#;
+
+/// `fish` 的自动补全脚本
+const FISH: &str = r#"function __crm_complete
+    set -l tokens (commandline -opc)
+    set -e tokens[1]
+    crm __complete $tokens (commandline -ct) 2>/dev/null
+end
+
+complete -c crm -f -a '(__crm_complete)'
+"#;
+
+/// `elvish` 的自动补全脚本
+const ELVISH: &str = r#"set edit:completion:arg-completer[crm] = {|@words|
+    crm __complete (all $words[1..]) 2>/dev/null | from-lines
+}
+"#;
+
+/// shell 的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+}
+
+impl FromStr for Shell {
+    type Err = CrmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "elvish" => Ok(Shell::Elvish),
+            _ => Err(CrmError::InvalidValue(
+                Msg::InvalidShell.format(&[&SHELLS.join(Msg::Separator.text()), &s]),
+            )),
+        }
+    }
+}
+
+impl Shell {
+    /// 获取自动补全脚本
+    pub fn script(&self) -> &'static str {
+        match self {
+            Shell::Bash => BASH,
+            Shell::Zsh => ZSH,
+            Shell::Fish => FISH,
+            Shell::Elvish => ELVISH,
+        }
+    }
+}
+
+/// 获取所有没有隐藏的命令名
+fn command_names() -> Vec<String> {
+    COMMANDS
+        .iter()
+        .filter(|v| !v.hidden)
+        .map(|v| v.name.to_string())
+        .collect()
+}
+
+/// 获取所有的镜像名和别名，读取 `.crmrc` 失败时返回空列表
+fn registry_names() -> Vec<String> {
+    let names: BTreeSet<String> = RuntimeConfig::new()
+        .map(|mut rc| {
+            rc.set_include_disabled(true);
+            rc.registry_names()
+                .into_iter()
+                .chain(rc.aliases().keys().cloned())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    names.into_iter().collect()
+}
+
+/// 根据命令行中的单词获取补全的候选项
+///
+/// `words` 是 `crm` 之后的所有单词，最后一个单词是正在输入的单词 (可能为空字符串)。
+pub fn complete(words: &[String]) -> Vec<String> {
+    let (current, before) = match words.split_last() {
+        Some((current, before)) => (current.as_str(), before),
+        None => ("", words),
+    };
+
+    let candidates: Vec<String> = match before.split_first() {
+        // 补全命令名
+        None => command_names(),
+
+        Some((name, rest)) => match find_command(name) {
+            Some(command) if !command.passthrough => {
+                let previous = rest.last().and_then(|v| v.strip_prefix("--"));
+
+                match previous
+                    .and_then(|v| command.option(v))
+                    .and_then(|v| v.value)
+                {
+                    // 补全选项的值
+                    Some((_, ValueKind::Choice(choices))) => {
+                        choices.iter().map(|v| v.to_string()).collect()
+                    }
+                    Some(_) => vec![],
+
+                    // 补全选项名
+                    None if current.starts_with('-') => command
+                        .options
+                        .iter()
+                        .filter(|opt| !rest.iter().any(|v| v == &format!("--{}", opt.name)))
+                        .map(|opt| format!("--{}", opt.name))
+                        .chain(["--help".to_string()])
+                        .collect(),
+
+                    // 补全位置参数
+                    None => {
+                        let mut index = 0;
+                        let mut iter = rest.iter();
+
+                        while let Some(word) = iter.next() {
+                            match word.strip_prefix("--") {
+                                Some(v) if !v.contains('=') => {
+                                    if command.option(v).is_some_and(|v| v.value.is_some()) {
+                                        iter.next();
+                                    }
+                                }
+                                Some(_) => {}
+                                None => index += 1,
+                            }
+                        }
+
+                        match command.args.get(index).map(|v| v.complete) {
+                            Some(Complete::Registry) => registry_names(),
+                            Some(Complete::Command) => command_names(),
+                            Some(Complete::Values(values)) => {
+                                values.iter().map(|v| v.to_string()).collect()
+                            }
+                            _ => vec![],
+                        }
+                    }
+                }
+            }
+            _ => vec![],
+        },
+    };
+
+    candidates
+        .into_iter()
+        .filter(|v| v.starts_with(current))
+        .collect()
+}
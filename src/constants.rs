@@ -19,6 +19,17 @@ pub const NET: &str = "net";
 /// @reference https://doc.rust-lang.org/cargo/reference/config.html#netgit-fetch-with-cli
 pub const GIT_FETCH_WITH_CLI: &str = "git-fetch-with-cli";
 
+/// `${CARGO_HOME}/.cargo/config` 文件中的 `[http]` 属性
+/// @note 其中的字段可以使用环境变量 `CARGO_HTTP_<FIELD>` 来进行覆盖，例如 `CARGO_HTTP_PROXY`
+/// @reference https://doc.rust-lang.org/cargo/reference/config.html#http
+pub const HTTP: &str = "http";
+
+/// `${CARGO_HOME}/.cargo/config` 文件中的 `[http]` 属性下面的 `proxy` 属性
+pub const PROXY: &str = "proxy";
+
+/// `${CARGO_HOME}/.cargo/config` 文件中的 `[http]` 属性下面的 `cainfo` 属性
+pub const CAINFO: &str = "cainfo";
+
 /// `${CARGO_HOME}/.cargo/config` 文件中的 `[source.crates-io]` 属性
 pub const CRATES_IO: &str = "crates-io";
 
@@ -37,9 +48,78 @@ pub const RUST_LANG: &str = "rust-lang";
 /// `${HOME}` 目录下的 `.crmrc` 文件的文件名
 pub const CRMRC: &str = ".crmrc";
 
+/// `${HOME}` 目录下的 `.crmrc` 文件中的 `[alias]` 属性，用于存放镜像的别名
+pub const ALIAS: &str = "alias";
+
+/// `${HOME}` 目录下的 `.crmrc` 文件中的 `disabled` 属性，用于存放被禁用的镜像名
+pub const DISABLED: &str = "disabled";
+
 /// `${HOME}` 目录下的 `.crmrc` 文件的文件路径
 pub const CRMRC_PATH: &str = "~/.crmrc";
 
+/// `${HOME}` 目录下存放 `crm` 数据的文件夹
+pub const CRM_DIR: &str = ".crm";
+
+/// `${HOME}/.crm` 目录下存放配置文件快照的文件夹
+pub const BACKUPS: &str = "backups";
+
+/// `${HOME}/.crm` 目录下存放文件锁的文件夹
+pub const LOCKS: &str = "locks";
+
+/// `${HOME}/.crm` 目录下存放测试记录的文件
+pub const HISTORY: &str = "history.tsv";
+
+/// `${HOME}/.crm` 目录下记录每个 `Cargo` 配置文件最后应用的配置方案的文件
+pub const PROFILES: &str = "profiles.toml";
+
+/// 测试记录的保留天数
+pub const HISTORY_RETENTION_DAYS: u64 = 90;
+
+/// `crm best --from-history` 使用的测试记录的最长时间 (小时)
+pub const HISTORY_MAX_AGE_HOURS: u64 = 24;
+
+/// `crm report` 默认统计的天数
+pub const DEFAULT_REPORT_DAYS: u64 = 7;
+
+/// 用于指定每个配置文件最多保留多少个快照的环境变量
+pub const CRM_BACKUP_LIMIT: &str = "CRM_BACKUP_LIMIT";
+
+/// 用于指定输出语言的环境变量，优先于 `LC_ALL` 和 `LANG`
+pub const CRM_LANG: &str = "CRM_LANG";
+
+/// 网络请求默认的超时时间 (秒)
+pub const DEFAULT_TIMEOUT: u64 = 5;
+
+/// 默认最多同时测试的镜像数量
+pub const DEFAULT_JOBS: usize = 16;
+
+/// 测试下载速度时默认下载的软件包
+pub const DEFAULT_BENCH_CRATES: &[(&str, &str)] = &[("libc", "0.2.155"), ("windows-sys", "0.52.0")];
+
+/// 测试下载速度时每个镜像默认最多下载的字节数
+pub const DEFAULT_BENCH_MAX_BYTES: u64 = 32_000_000;
+
+/// 测试下载速度时每个镜像默认最多花费的时间 (秒)
+pub const DEFAULT_BENCH_MAX_TIME: u64 = 10;
+
+/// 校验软件包时默认下载的软件包，选择体积较小的软件包，以免每个镜像都下载大量的数据
+pub const DEFAULT_VERIFY_CRATES: &[(&str, &str)] = &[("cfg-if", "1.0.0"), ("itoa", "1.0.11")];
+
+/// 校验软件包或请求 git 引用列表时最多下载的字节数，超过时视为失败
+pub const MAX_DOWNLOAD_BYTES: u64 = 16 * 1024 * 1024;
+
+/// 检查 `sparse` 索引时默认请求的软件包
+pub const DEFAULT_PROBE_CRATE: &str = "serde";
+
+/// 官方的 `sparse` 索引地址，用于检查镜像索引的同步情况
+pub const OFFICIAL_SPARSE_INDEX: &str = "https://index.crates.io/";
+
+/// 检查镜像索引的同步情况时默认比较的软件包，这些软件包都会频繁地发布新版本
+pub const DEFAULT_FRESHNESS_CRATES: &[&str] = &["aws-sdk-s3", "syn", "tokio"];
+
+/// 每个配置文件默认最多保留的快照数量
+pub const DEFAULT_BACKUP_LIMIT: usize = 10;
+
 /// 用户查找 `"${CARGO_HOME}"` 环境变量
 pub const CARGO_HOME: &str = "CARGO_HOME";
 
@@ -55,6 +135,12 @@ pub const CONFIG: &str = "config";
 /// `${CARGO_HOME}/.cargo` 目录下的 `config.toml` 文件 (推荐)
 pub const CONFIG_TOML: &str = "config.toml";
 
+/// 项目的 `Cargo.toml` 清单文件
+pub const CARGO_TOML: &str = "Cargo.toml";
+
+/// `Cargo.toml` 文件中的 `[workspace]` 属性
+pub const WORKSPACE: &str = "workspace";
+
 /// `dl`
 pub const DL: &str = "dl";
 
@@ -64,15 +150,6 @@ pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
 /// 应用程序版本号
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-/// 表
-pub const TABLE: &str = "表";
-
-/// 字符串
-pub const STRING: &str = "字符串";
-
-/// 请修改/删除后重试
-pub const PLEASE_TRY: &str = "请修改/删除后重试";
-
 /// `UNC` 路径前缀
 pub const UNC_PREFIX: &str = r"\\?\";
 
@@ -84,84 +161,169 @@ pub const CRMRC_FILE: &str = r#"
 [source.rust-lang]
 registry = "https://github.com/rust-lang/crates.io-index"
 dl = "https://crates.io/api/v1/crates"
+display-name = "官方源"
+region = "global"
+tags = ["official"]
+homepage = "https://crates.io"
+proxies-downloads = false
 
 # 上海交通大学
 [source.sjtu]
 registry = "https://mirrors.sjtug.sjtu.edu.cn/git/crates.io-index"
 dl = "https://mirror.sjtu.edu.cn/crates.io/crates/{crate}/{crate}-{version}.crate"
+display-name = "上海交通大学"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.sjtug.sjtu.edu.cn"
+proxies-downloads = true
 
 # 上海交通大学 - sparse
 [source.sjtu-sparse]
 registry = "sparse+https://mirrors.sjtug.sjtu.edu.cn/crates.io-index/"
 dl = "https://mirror.sjtu.edu.cn/crates.io/crates/{crate}/{crate}-{version}.crate"
+display-name = "上海交通大学 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.sjtug.sjtu.edu.cn"
+proxies-downloads = true
 
 # 中科大
 [source.ustc]
 registry = "https://mirrors.ustc.edu.cn/crates.io-index"
 dl = "https://crates-io.proxy.ustclug.org/api/v1/crates"
+display-name = "中科大"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.ustc.edu.cn"
+proxies-downloads = true
 
 # 中科大 - sparse
 [source.ustc-sparse]
 registry = "sparse+https://mirrors.ustc.edu.cn/crates.io-index/"
 dl = "https://crates-io.proxy.ustclug.org/api/v1/crates"
+display-name = "中科大 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.ustc.edu.cn"
+proxies-downloads = true
 
 # 字节跳动
 [source.rsproxy]
 registry = "https://rsproxy.cn/crates.io-index"
 dl = "https://rsproxy.cn/api/v1/crates"
+display-name = "字节跳动"
+region = "cn"
+tags = ["company"]
+homepage = "https://rsproxy.cn"
+proxies-downloads = true
 
 # 字节跳动 - sparse
 [source.rsproxy-sparse]
 registry = "sparse+https://rsproxy.cn/index/"
 dl = "https://rsproxy.cn/api/v1/crates"
+display-name = "字节跳动 - sparse"
+region = "cn"
+tags = ["company"]
+homepage = "https://rsproxy.cn"
+proxies-downloads = true
 
 # 清华大学
 [source.tuna]
 registry = "https://mirrors.tuna.tsinghua.edu.cn/git/crates.io-index.git"
 dl = "https://crates.io/api/v1/crates"
+display-name = "清华大学"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.tuna.tsinghua.edu.cn"
+proxies-downloads = false
 
 # 清华大学 - sparse
 [source.tuna-sparse]
 registry = "sparse+https://mirrors.tuna.tsinghua.edu.cn/crates.io-index/"
 dl = "https://crates.io/api/v1/crates"
+display-name = "清华大学 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.tuna.tsinghua.edu.cn"
+proxies-downloads = false
 
 # 北京外国语大学
 [source.bfsu]
 registry = "https://mirrors.bfsu.edu.cn/git/crates.io-index.git"
 dl = "https://crates.io/api/v1/crates"
+display-name = "北京外国语大学"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.bfsu.edu.cn"
+proxies-downloads = false
 
 # 北京外国语大学 - sparse
 [source.bfsu-sparse]
 registry = "sparse+https://mirrors.bfsu.edu.cn/crates.io-index/"
 dl = "https://crates.io/api/v1/crates"
+display-name = "北京外国语大学 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.bfsu.edu.cn"
+proxies-downloads = false
 
 # 南京大学
 [source.nju]
 registry = "https://mirror.nju.edu.cn/git/crates.io-index.git"
 dl = "https://crates.io/api/v1/crates"
+display-name = "南京大学"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirror.nju.edu.cn"
+proxies-downloads = false
 
 # 重庆大学 - sparse
 [source.cqu-sparse]
 registry = "sparse+https://mirrors.cqu.edu.cn/crates.io-index/"
 dl = "https://crates.io/api/v1/crates"
+display-name = "重庆大学 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.cqu.edu.cn"
+proxies-downloads = false
 
 # 浙江大学 - sparse
 [source.zju-sparse]
 registry = "sparse+https://mirrors.zju.edu.cn/crates.io-index/"
 dl = "https://crates.io/api/v1/crates"
+display-name = "浙江大学 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.zju.edu.cn"
+proxies-downloads = false
 
 # CERNET聚合镜像
 [source.cernet]
 registry = "https://mirrors.cernet.edu.cn/crates.io-index.git"
 dl = "https://crates.io/api/v1/crates"
+display-name = "CERNET聚合镜像"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.cernet.edu.cn"
+proxies-downloads = false
 
 # CERNET聚合镜像 - sparse
 [source.cernet-sparse]
 registry = "sparse+https://mirrors.cernet.edu.cn/crates.io-index/"
 dl = "https://crates.io/api/v1/crates"
+display-name = "CERNET聚合镜像 - sparse"
+region = "cn"
+tags = ["edu"]
+homepage = "https://mirrors.cernet.edu.cn"
+proxies-downloads = false
 
 # 阿里云 - sparse
 [source.aliyun-sparse]
 registry = "sparse+https://mirrors.aliyun.com/crates.io-index/"
 dl = "https://crates.io/api/v1/crates"
+display-name = "阿里云 - sparse"
+region = "cn"
+tags = ["company"]
+homepage = "https://developer.aliyun.com/mirror/"
+proxies-downloads = false
 "#;
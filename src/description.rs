@@ -1,20 +1,125 @@
 //! 镜像描述模块
 //!
-//! 该模块定义了 `RegistryDescription` 结构体
+//! 该模块定义了 `RegistryDescription` 结构体。除了必需的 `registry` 和 `dl` 之外，
+//! 内置镜像和 `.crmrc` 中的镜像都可以通过以下可选字段描述镜像:
+//!
+//! ```toml
+//! [source.sjtu]
+//! registry = "https://mirrors.sjtug.sjtu.edu.cn/git/crates.io-index"
+//! dl = "https://mirror.sjtu.edu.cn/crates.io/crates/{crate}/{crate}-{version}.crate"
+//! display-name = "上海交通大学"
+//! description = "..."
+//! region = "cn"
+//! tags = ["edu"]
+//! homepage = "https://mirrors.sjtug.sjtu.edu.cn"
+//! proxies-downloads = true
+//! priority = 0
+//! ```
+
+/// 显示名称
+pub const DISPLAY_NAME: &str = "display-name";
+
+/// 描述
+pub const DESCRIPTION: &str = "description";
+
+/// 地区
+pub const REGION: &str = "region";
+
+/// 标签
+pub const TAGS: &str = "tags";
+
+/// 主页
+pub const HOMEPAGE: &str = "homepage";
+
+/// 协议
+pub const PROTOCOL: &str = "protocol";
+
+/// 是否代理了软件包的下载
+pub const PROXIES_DOWNLOADS: &str = "proxies-downloads";
+
+/// 优先级
+pub const PRIORITY: &str = "priority";
+
+/// 支持的协议
+pub const PROTOCOLS: &[&str] = &["git", "sparse"];
+
+/// 官方的软件包下载地址的主机
+const OFFICIAL_DOWNLOAD_HOSTS: &[&str] = &["crates.io", "static.crates.io"];
 
 /// 镜像描述
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RegistryDescription {
     /// 镜像地址
     pub registry: String,
 
     /// 镜像 `dl`
     pub dl: String,
+
+    /// 显示名称，例如 "上海交通大学"
+    pub display_name: Option<String>,
+
+    /// 描述
+    pub description: Option<String>,
+
+    /// 地区，例如 `cn`
+    pub region: Option<String>,
+
+    /// 标签，例如 `edu`
+    pub tags: Vec<String>,
+
+    /// 主页
+    pub homepage: Option<String>,
+
+    /// `dl` 是否由镜像代理下载，而不是直接指向 `crates.io`
+    pub proxies_downloads: Option<bool>,
+
+    /// 优先级，`best` 的测试结果相同时数字越大越优先，默认为 `0`
+    pub priority: Option<u64>,
 }
 
 impl RegistryDescription {
     /// 创建一个镜像描述对象
     pub fn new(registry: String, dl: String) -> Self {
-        RegistryDescription { registry, dl }
+        RegistryDescription {
+            registry,
+            dl,
+            ..Default::default()
+        }
+    }
+
+    /// 获取镜像的协议，以 `sparse+` 开头的镜像为 `sparse`，否则为 `git`
+    ///
+    /// 协议总是根据地址判断，配置中的 `protocol` 字段不会被保存，与地址不一致时只会输出警告。
+    pub fn protocol(&self) -> &str {
+        match self.registry.starts_with("sparse+") {
+            true => "sparse",
+            false => "git",
+        }
+    }
+
+    /// 判断镜像是否代理了软件包的下载
+    ///
+    /// 没有指定 `proxies-downloads` 时，如果 `dl` 指向的不是 `crates.io` 或 `static.crates.io`，则认为镜像代理了下载。
+    pub fn proxies_downloads(&self) -> bool {
+        self.proxies_downloads.unwrap_or_else(|| {
+            let host = self
+                .dl
+                .split_once("://")
+                .map_or(self.dl.as_str(), |v| v.1)
+                .split(['/', ':', '?'])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+
+            !host.is_empty() && !OFFICIAL_DOWNLOAD_HOSTS.contains(&host.as_str())
+        })
+    }
+
+    /// 判断镜像的地区或标签是否与 `tag` 相同，不区分大小写
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.region
+            .iter()
+            .chain(self.tags.iter())
+            .any(|v| v.eq_ignore_ascii_case(tag))
     }
 }
@@ -0,0 +1,182 @@
+//! # 文本差异
+//!
+//! 该模块用于比较两段文本，并生成 `diff -u` 格式的统一差异。
+
+/// 差异中的一行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    /// 两边都存在的行
+    Same(&'a str),
+
+    /// 被删除的行
+    Delete(&'a str),
+
+    /// 被添加的行
+    Insert(&'a str),
+}
+
+/// 上下文的行数
+const CONTEXT: usize = 3;
+
+/// 使用最长公共子序列计算逐行差异
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec![];
+
+    while i < n && j < m {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(Line::Delete(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Insert(new[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(old[i..].iter().map(|v| Line::Delete(v)));
+    lines.extend(new[j..].iter().map(|v| Line::Insert(v)));
+
+    lines
+}
+
+/// 格式化 hunk 头中的行号范围
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start.saturating_sub(1)),
+        1 => format!("{}", start),
+        _ => format!("{},{}", start, len),
+    }
+}
+
+/// 生成统一差异，如果两段文本相同，则返回空字符串
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    // 找出所有发生变化的行，并将相邻的变化合并为 hunk
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| !matches!(v, Line::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut hunks: Vec<(usize, usize)> = vec![];
+
+    for i in changed {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    for (start, end) in hunks {
+        // 计算 hunk 之前两边各自已经经过的行数
+        let old_start = lines[..start]
+            .iter()
+            .filter(|v| !matches!(v, Line::Insert(_)))
+            .count();
+        let new_start = lines[..start]
+            .iter()
+            .filter(|v| !matches!(v, Line::Delete(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|v| !matches!(v, Line::Insert(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|v| !matches!(v, Line::Delete(_)))
+            .count();
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start + 1, old_len),
+            range(new_start + 1, new_len)
+        ));
+
+        for line in hunk {
+            match line {
+                Line::Same(v) => out.push_str(&format!(" {}\n", v)),
+                Line::Delete(v) => out.push_str(&format!("-{}\n", v)),
+                Line::Insert(v) => out.push_str(&format!("+{}\n", v)),
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_text_has_no_diff() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb", "old", "new"), "");
+        assert_eq!(unified_diff("", "", "old", "new"), "");
+    }
+
+    #[test]
+    fn changed_line() {
+        assert_eq!(
+            unified_diff("a\nb\nc\n", "a\nB\nc\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn new_and_removed_file() {
+        assert_eq!(
+            unified_diff("", "x\ny\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+x\n+y\n"
+        );
+        assert_eq!(
+            unified_diff("x\n", "", "old", "new"),
+            "--- old\n+++ new\n@@ -1 +0,0 @@\n-x\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_are_split_into_hunks() {
+        let old: Vec<String> = (1..=10).map(|v| v.to_string()).collect();
+        let mut new = old.clone();
+
+        new[0] = "x".to_string();
+        new[9] = "y".to_string();
+
+        let diff = unified_diff(&old.join("\n"), &new.join("\n"), "old", "new");
+
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(diff.contains("@@ -1,4 +1,4 @@\n-1\n+x\n 2\n 3\n 4\n"));
+        assert!(diff.contains("@@ -7,4 +7,4 @@\n 7\n 8\n 9\n-10\n+y\n"));
+    }
+}
@@ -0,0 +1,233 @@
+//! # 错误
+//!
+//! 该模块定义了 `crm` 在运行过程中可能产生的所有错误。
+//! 库中的所有公开方法都会返回 `Result<T, CrmError>`，只有在 `main.rs` 中才会将错误输出并退出程序。
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    command::overview,
+    constants::{APP_NAME, CRMRC, DL, REGISTRY},
+    i18n::Msg,
+};
+
+/// 命令行参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Argument {
+    /// 镜像名
+    Name,
+
+    /// 镜像地址
+    Addr,
+
+    /// 镜像 `dl`
+    Dl,
+}
+
+/// 对镜像进行的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// 切换镜像
+    Use,
+
+    /// 删除镜像
+    Remove,
+
+    /// 重命名镜像
+    Rename,
+
+    /// 测试镜像
+    Test,
+}
+
+/// `crm` 错误
+#[derive(Debug)]
+pub enum CrmError {
+    /// 缺少必要的命令行参数
+    MissingArgument(Argument),
+
+    /// 命令无效
+    UnknownCommand(String),
+
+    /// 命令的用法不正确
+    Usage { command: String, message: String },
+
+    /// 参数的值无效
+    InvalidValue(String),
+
+    /// 配置文件解析失败
+    ConfigParse(PathBuf),
+
+    /// 配置文件中的字段类型不正确
+    FieldType {
+        path: PathBuf,
+        field: String,
+        expected: Msg,
+    },
+
+    /// 配置文件中的镜像缺少必要的字段
+    MissingField { path: PathBuf, field: String },
+
+    /// 检测到了两种形式的 `Cargo` 配置文件
+    ConfigConflict { obsolete: PathBuf, path: PathBuf },
+
+    /// 镜像不存在
+    UnknownRegistry {
+        name: String,
+        operation: Operation,
+        candidates: Vec<String>,
+    },
+
+    /// 试图删除内置镜像
+    BuiltinRemoval(String),
+
+    /// 试图重命名内置镜像
+    BuiltinRename(String),
+
+    /// 读写文件或执行命令失败
+    Io(io::Error),
+
+    /// 网络请求失败
+    Network(String),
+
+    /// 镜像提供的软件包与官方索引中的校验和不一致
+    ChecksumMismatch(Vec<String>),
+}
+
+impl CrmError {
+    /// 获取错误对应的程序退出码
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CrmError::MissingArgument(Argument::Name) => 1,
+            CrmError::MissingArgument(Argument::Addr) => 2,
+            CrmError::MissingArgument(Argument::Dl) => 3,
+            CrmError::UnknownCommand(_) | CrmError::Usage { .. } => 4,
+            CrmError::ConfigParse(path)
+            | CrmError::FieldType { path, .. }
+            | CrmError::MissingField { path, .. } => {
+                if is_runtime_config(path) {
+                    12
+                } else {
+                    5
+                }
+            }
+            CrmError::BuiltinRemoval(_) | CrmError::BuiltinRename(_) => 7,
+            CrmError::UnknownRegistry { operation, .. } => match operation {
+                Operation::Use | Operation::Remove | Operation::Rename => 8,
+                Operation::Test => 11,
+            },
+            CrmError::InvalidValue(_) => 9,
+            CrmError::Network(_) => 15,
+            CrmError::Io(_) => 13,
+            CrmError::ConfigConflict { .. } => 14,
+            CrmError::ChecksumMismatch(_) => 16,
+        }
+    }
+}
+
+/// 判断路径是否是 `.crmrc` 文件
+fn is_runtime_config(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == CRMRC)
+}
+
+impl fmt::Display for CrmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrmError::MissingArgument(Argument::Name) => write!(f, "{}", Msg::MissingName),
+            CrmError::MissingArgument(Argument::Addr) => write!(f, "{}", Msg::MissingAddr),
+            CrmError::MissingArgument(Argument::Dl) => write!(f, "{}", Msg::MissingDl),
+            CrmError::UnknownCommand(command) => {
+                write!(f, "{}", Msg::UnknownCommand.format(&[command, &overview()]))
+            }
+            CrmError::Usage { command, message } => {
+                write!(f, "{}", Msg::SeeHelp.format(&[message, &APP_NAME, command]))
+            }
+            CrmError::InvalidValue(message) => write!(f, "{}", message),
+            CrmError::ConfigParse(path) => write!(
+                f,
+                "{}",
+                Msg::ConfigParse.format(&[&path.display(), &Msg::PleaseTry])
+            ),
+            CrmError::FieldType {
+                path,
+                field,
+                expected,
+            } => write!(
+                f,
+                "{}",
+                Msg::FieldType.format(&[&path.display(), field, expected, &Msg::PleaseTry])
+            ),
+            CrmError::MissingField { path, field } => write!(
+                f,
+                "{}",
+                Msg::MissingField.format(&[
+                    &path.display(),
+                    field,
+                    &REGISTRY,
+                    &DL,
+                    &Msg::PleaseTry
+                ])
+            ),
+            CrmError::ConfigConflict { obsolete, path } => write!(
+                f,
+                "{}",
+                Msg::ConfigConflict.format(&[&obsolete.display(), &path.display()])
+            ),
+            CrmError::UnknownRegistry {
+                name,
+                operation,
+                candidates,
+            } => match operation {
+                Operation::Use if candidates.is_empty() => {
+                    write!(f, "{}", Msg::EmptyRegistryList.format(&[name]))
+                }
+                Operation::Use => {
+                    let candidates = candidates
+                        .iter()
+                        .map(|key| format!("  - {}", key))
+                        .collect::<Vec<String>>()
+                        .join("\n");
+
+                    write!(f, "{}", Msg::UnknownRegistry.format(&[name, &candidates]))
+                }
+                Operation::Remove => write!(f, "{}", Msg::RemoveMissing.format(&[name])),
+                Operation::Rename => write!(f, "{}", Msg::RenameMissing.format(&[name])),
+                Operation::Test => write!(f, "{}", Msg::TestMissing.format(&[name])),
+            },
+            CrmError::BuiltinRemoval(name) => {
+                write!(f, "{}", Msg::BuiltinRemoval.format(&[name]))
+            }
+            CrmError::BuiltinRename(name) => {
+                write!(f, "{}", Msg::BuiltinRename.format(&[name]))
+            }
+            CrmError::Io(e) => write!(f, "{}", Msg::IoFailed.format(&[e])),
+            CrmError::Network(message) => write!(f, "{}", Msg::NetworkFailed.format(&[message])),
+            CrmError::ChecksumMismatch(names) => write!(
+                f,
+                "{}",
+                Msg::ChecksumMismatch.format(&[&names.join(Msg::Separator.text())])
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CrmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CrmError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CrmError {
+    fn from(e: io::Error) -> Self {
+        CrmError::Io(e)
+    }
+}
+
+/// `crm` 中使用的 `Result` 类型
+pub type CrmResult<T> = Result<T, CrmError>;
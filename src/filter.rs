@@ -0,0 +1,179 @@
+//! # 镜像筛选
+//!
+//! `crm best` 通过筛选条件决定参与评估的镜像，筛选条件由逗号分隔的多个条件组成，镜像需要满足所有的条件:
+//!
+//!   - `protocol=<git|sparse>`: 只使用指定协议的镜像，协议由镜像地址的 `sparse+` 前缀决定
+//!   - `tag=<tag>`:             只使用地区或标签匹配的镜像
+//!   - `downloads`:             只使用代理了软件包下载的镜像
+//!   - `custom`:                只使用 `.crmrc` 中自定义的镜像
+//!   - `!<name>`:               排除指定的镜像
+//!
+//! 例如 `crm best --filter "protocol=sparse,tag=edu,!tuna-sparse"`。
+//! `best` 的评估模式也会被转换为筛选条件，例如 `sparse-download` 相当于 `protocol=sparse,downloads`。
+
+use crate::{
+    description::{RegistryDescription, PROTOCOLS},
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+};
+
+/// 镜像的筛选条件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryFilter {
+    /// 协议
+    pub protocol: Option<String>,
+
+    /// 地区或标签
+    pub tags: Vec<String>,
+
+    /// 是否只使用代理了软件包下载的镜像
+    pub downloads: bool,
+
+    /// 是否只使用自定义的镜像
+    pub custom: bool,
+
+    /// 排除的镜像
+    pub exclude: Vec<String>,
+}
+
+impl RegistryFilter {
+    /// 添加 `best` 的评估模式对应的筛选条件
+    pub fn add_mode(&mut self, mode: &str) -> CrmResult<()> {
+        let (protocol, downloads) = match mode {
+            "git" => ("git", false),
+            "sparse" => ("sparse", false),
+            "git-download" => ("git", true),
+            "sparse-download" => ("sparse", true),
+            _ => return Err(CrmError::InvalidValue(Msg::InvalidBestMode.to_string())),
+        };
+
+        self.set_protocol(protocol)?;
+        self.downloads |= downloads;
+
+        Ok(())
+    }
+
+    /// 添加筛选表达式中的所有条件
+    pub fn add_expr(&mut self, expr: &str) -> CrmResult<()> {
+        let invalid = |term: &str| CrmError::InvalidValue(Msg::InvalidFilter.format(&[&term]));
+
+        for term in expr.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            match term.split_once('=') {
+                Some(("protocol", v)) => self.set_protocol(&v.trim().to_lowercase())?,
+                Some(("tag", v)) if !v.trim().is_empty() => self.tags.push(v.trim().to_string()),
+                Some(_) => return Err(invalid(term)),
+                None => match term {
+                    "downloads" => self.downloads = true,
+                    "custom" => self.custom = true,
+                    _ => match term.strip_prefix('!').map(str::trim) {
+                        Some(name) if !name.is_empty() => self.exclude.push(name.to_string()),
+                        _ => return Err(invalid(term)),
+                    },
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 设置协议，与已经设置的协议冲突时返回错误
+    fn set_protocol(&mut self, protocol: &str) -> CrmResult<()> {
+        if !PROTOCOLS.contains(&protocol) {
+            return Err(CrmError::InvalidValue(
+                Msg::InvalidFilter.format(&[&format!("protocol={}", protocol)]),
+            ));
+        }
+
+        match &self.protocol {
+            Some(v) if v != protocol => Err(CrmError::InvalidValue(
+                Msg::ConflictingProtocols.format(&[v, &protocol]),
+            )),
+            _ => {
+                self.protocol = Some(protocol.to_string());
+
+                Ok(())
+            }
+        }
+    }
+
+    /// 判断镜像是否满足所有的条件，`custom` 表示镜像是否是 `.crmrc` 中自定义的镜像
+    pub fn matches(&self, name: &str, description: &RegistryDescription, custom: bool) -> bool {
+        self.protocol
+            .as_ref()
+            .is_none_or(|v| v == description.protocol())
+            && self.tags.iter().all(|v| description.has_tag(v))
+            && (!self.downloads || description.proxies_downloads())
+            && (!self.custom || custom)
+            && !self.exclude.iter().any(|v| v == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description(registry: &str, dl: &str) -> RegistryDescription {
+        RegistryDescription::new(registry.to_string(), dl.to_string())
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let mut filter = RegistryFilter::default();
+
+        filter
+            .add_expr(" protocol=sparse , tag=edu,downloads,custom,!tuna ,")
+            .unwrap();
+
+        assert_eq!(filter.protocol.as_deref(), Some("sparse"));
+        assert_eq!(filter.tags, ["edu"]);
+        assert!(filter.downloads && filter.custom);
+        assert_eq!(filter.exclude, ["tuna"]);
+    }
+
+    #[test]
+    fn rejects_invalid_terms() {
+        for expr in ["protocol=ftp", "tag=", "foo", "!", "region=cn"] {
+            assert!(
+                RegistryFilter::default().add_expr(expr).is_err(),
+                "{}",
+                expr
+            );
+        }
+
+        assert!(RegistryFilter::default().add_mode("fast").is_err());
+    }
+
+    #[test]
+    fn modes_conflict_with_other_protocols() {
+        let mut filter = RegistryFilter::default();
+
+        filter.add_mode("sparse-download").unwrap();
+
+        assert!(filter.downloads);
+        assert!(filter.add_expr("protocol=sparse").is_ok());
+        assert!(filter.add_expr("protocol=git").is_err());
+    }
+
+    #[test]
+    fn matches_descriptions() {
+        let mut filter = RegistryFilter::default();
+        let mut mirror = description("sparse+https://m.cn/index/", "https://m.cn/crates");
+        let official = description("sparse+https://m.cn/index/", "https://static.crates.io/c");
+        let git = description("https://m.cn/git/index", "https://m.cn/crates");
+
+        mirror.tags = vec!["EDU".to_string()];
+        filter
+            .add_expr("protocol=sparse,downloads,tag=edu,!b")
+            .unwrap();
+
+        assert!(filter.matches("a", &mirror, false));
+        assert!(!filter.matches("b", &mirror, false));
+        assert!(!filter.matches("a", &official, false));
+        assert!(!filter.matches("a", &git, false));
+
+        filter.add_expr("custom").unwrap();
+
+        assert!(!filter.matches("a", &mirror, false));
+        assert!(filter.matches("a", &mirror, true));
+    }
+}
@@ -0,0 +1,147 @@
+//! # `git` 索引检查
+//!
+//! 直接请求 git 镜像的地址只能得到一个网页，并不能说明索引是可以克隆的。该模块按照 git 的 smart HTTP 协议
+//! 请求 `<repo>/info/refs?service=git-upload-pack`，校验返回的 pkt-line 格式的引用列表，
+//! 并记录其中 `HEAD` 指向的提交，以便比较不同的 git 镜像是否同步到了同一个提交。
+//!
+//! @reference https://git-scm.com/docs/http-protocol
+
+use std::time::{Duration, Instant};
+
+use crate::{i18n::Msg, utils::download};
+
+/// smart HTTP 响应的开头: 服务声明和 flush-pkt
+const SERVICE_HEADER: &[u8] = b"001e# service=git-upload-pack\n0000";
+
+/// 检查通过时的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRefs {
+    /// `HEAD` 指向的提交
+    pub head: String,
+
+    /// 引用的数量，不包括 `HEAD`
+    pub refs: usize,
+
+    /// 请求的耗时
+    pub elapsed: Duration,
+}
+
+impl GitRefs {
+    /// 提交的短哈希
+    pub fn short_head(&self) -> &str {
+        &self.head[..self.head.len().min(12)]
+    }
+}
+
+/// 检查的结果，失败时为失败的原因
+pub type GitCheck = Result<GitRefs, String>;
+
+/// 获取 git 镜像的引用列表的地址。如果不是通过 `HTTP` 访问的 git 镜像，则返回 `None`
+pub fn info_refs_url(registry: &str) -> Option<String> {
+    if !registry.starts_with("http://") && !registry.starts_with("https://") {
+        return None;
+    }
+
+    Some(format!(
+        "{}/info/refs?service=git-upload-pack",
+        registry.trim_end_matches('/')
+    ))
+}
+
+/// 将 smart HTTP 的响应拆分为 pkt-line，`None` 表示 flush-pkt (`0000`)
+fn pkt_lines(mut data: &[u8]) -> Result<Vec<Option<&[u8]>>, String> {
+    let mut lines = vec![];
+
+    while !data.is_empty() {
+        let len = data
+            .get(..4)
+            .and_then(|v| std::str::from_utf8(v).ok())
+            .and_then(|v| usize::from_str_radix(v, 16).ok())
+            .ok_or_else(|| Msg::GitInvalidPktLine.to_string())?;
+
+        if len == 0 {
+            lines.push(None);
+            data = &data[4..];
+            continue;
+        }
+
+        let line = data
+            .get(4..len)
+            .ok_or_else(|| Msg::GitInvalidPktLine.to_string())?;
+
+        lines.push(Some(line.strip_suffix(b"\n").unwrap_or(line)));
+        data = &data[len..];
+    }
+
+    Ok(lines)
+}
+
+/// 判断是否是 40 位的十六进制提交哈希
+fn is_object_id(value: &str) -> bool {
+    value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// 请求 git 镜像的引用列表，并获取 `HEAD` 指向的提交
+pub fn probe_git(registry: &str, timeout: Duration) -> GitCheck {
+    let url = info_refs_url(registry).ok_or_else(|| Msg::GitUnsupportedUrl.to_string())?;
+    let start = Instant::now();
+    let data = download(&url, timeout)?;
+    let elapsed = start.elapsed();
+
+    // 第一行是服务声明，随后是一个 flush-pkt
+    let data = data
+        .strip_prefix(SERVICE_HEADER)
+        .ok_or_else(|| Msg::GitNotSmart.to_string())?;
+    let mut head = None;
+    let mut refs = 0;
+
+    for line in pkt_lines(data)?.into_iter().map_while(|v| v) {
+        // 第一个引用的后面会以 `\0` 分隔服务器支持的功能
+        let line = line.split(|v| *v == 0).next().unwrap_or_default();
+        let line = String::from_utf8_lossy(line);
+        let (id, name) = line
+            .split_once(' ')
+            .filter(|(id, _)| is_object_id(id))
+            .ok_or_else(|| Msg::GitInvalidPktLine.to_string())?;
+
+        match name {
+            "HEAD" => head = Some(id.to_lowercase()),
+            _ => refs += 1,
+        }
+    }
+
+    Ok(GitRefs {
+        head: head.ok_or_else(|| Msg::GitNoHead.to_string())?,
+        refs,
+        elapsed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_pkt_lines() {
+        let lines = pkt_lines(b"0008abc\n0007def0000").unwrap();
+
+        assert_eq!(lines, [Some(&b"abc"[..]), Some(&b"def"[..]), None]);
+        assert_eq!(pkt_lines(b"").unwrap(), Vec::<Option<&[u8]>>::new());
+    }
+
+    #[test]
+    fn rejects_malformed_pkt_lines() {
+        for data in [&b"00"[..], b"zzzz", b"0003", b"0010abc", b"0008abc\n00"] {
+            assert!(pkt_lines(data).is_err(), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn info_refs_urls() {
+        assert_eq!(
+            info_refs_url("https://m.cn/crates.io-index/").as_deref(),
+            Some("https://m.cn/crates.io-index/info/refs?service=git-upload-pack")
+        );
+        assert_eq!(info_refs_url("git://m.cn/crates.io-index"), None);
+    }
+}
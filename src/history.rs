@@ -0,0 +1,376 @@
+//! # 测试记录
+//!
+//! `crm test`、`crm best` 和 `crm bench` 的每一次测试结果都会追加到 `${HOME}/.crm/history.tsv` 文件中，
+//! 每一行是一个样本，依次为时间戳 (毫秒)、主机名、标签、命令、测试类型、镜像名和结果，以制表符分隔。
+//! 延迟的单位为毫秒，下载速度的单位为字节每秒，失败的样本记录的是失败的原因。
+//!
+//! 同一台电脑在不同的网络中 (例如公司和家里) 得到的结果差别很大，所以可以通过 `--label <name>` 参数为测试结果添加标签。
+//! `crm report` 会根据这些记录输出每个镜像的趋势、成功率和百分位数，`crm best --from-history` 会直接根据最近的记录选择镜像。
+//!
+//! 超过 `HISTORY_RETENTION_DAYS` 天的记录会在下一次写入时被删除。
+
+use std::{
+    env,
+    fs::{self, create_dir_all, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    constants::{HISTORY, HISTORY_RETENTION_DAYS},
+    error::CrmResult,
+    lock::FileLock,
+    stats::{Bandwidth, Stats},
+    utils::{crm_dir, Delay},
+};
+
+/// 一天的毫秒数
+pub const DAY_MILLIS: u128 = 24 * 60 * 60 * 1000;
+
+/// 测试的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// 网络连接延迟 (毫秒)
+    Connect,
+
+    /// 软件包下载延迟 (毫秒)
+    Download,
+
+    /// 下载速度 (字节每秒)
+    Throughput,
+}
+
+impl Kind {
+    /// 在记录文件和输出中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Connect => "connect",
+            Kind::Download => "download",
+            Kind::Throughput => "throughput",
+        }
+    }
+
+    /// 根据名称获取测试的类型
+    fn from_name(name: &str) -> Option<Kind> {
+        [Kind::Connect, Kind::Download, Kind::Throughput]
+            .into_iter()
+            .find(|v| v.as_str() == name)
+    }
+}
+
+/// 进行测试时所处的网络环境
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    /// 主机名
+    pub host: String,
+
+    /// 通过 `--label` 参数指定的标签
+    pub label: Option<String>,
+}
+
+impl Context {
+    /// 获取当前的网络环境
+    pub fn current(label: Option<String>) -> Self {
+        Context {
+            host: hostname(),
+            label,
+        }
+    }
+}
+
+/// 一条测试记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// 测试的时间戳 (毫秒)
+    pub time: u128,
+
+    /// 进行测试时所处的网络环境
+    pub context: Context,
+
+    /// 进行测试的命令
+    pub command: String,
+
+    /// 测试的类型
+    pub kind: Kind,
+
+    /// 镜像名
+    pub name: String,
+
+    /// 测试的结果
+    pub result: Delay,
+}
+
+impl Entry {
+    /// 将记录转换为一行文本
+    fn to_line(&self) -> String {
+        let (value, error) = match &self.result {
+            Ok(v) => (v.to_string(), String::new()),
+            Err(e) => (String::new(), e.to_string()),
+        };
+
+        [
+            self.time.to_string(),
+            self.context.host.to_string(),
+            self.context.label.clone().unwrap_or_default(),
+            self.command.to_string(),
+            self.kind.as_str().to_string(),
+            self.name.to_string(),
+            value,
+            error,
+        ]
+        .iter()
+        .map(|v| sanitize(v))
+        .collect::<Vec<String>>()
+        .join("\t")
+    }
+
+    /// 从一行文本中解析记录，格式不正确时返回 `None`
+    fn from_line(line: &str) -> Option<Entry> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [time, host, label, command, kind, name, value, error] = fields[..] else {
+            return None;
+        };
+
+        Some(Entry {
+            time: time.parse().ok()?,
+            context: Context {
+                host: host.to_string(),
+                label: Some(label.to_string()).filter(|v| !v.is_empty()),
+            },
+            command: command.to_string(),
+            kind: Kind::from_name(kind)?,
+            name: name.to_string(),
+            result: match value {
+                "" => Err(error.to_string().into()),
+                v => Ok(v.parse().ok()?),
+            },
+        })
+    }
+}
+
+/// 将制表符和换行符替换为空格，以免破坏记录的格式
+fn sanitize(value: &str) -> String {
+    value.replace(['\t', '\r', '\n'], " ")
+}
+
+/// 获取主机名，无法获取时返回 `-`
+fn hostname() -> String {
+    let host = ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|key| env::var(key).ok())
+        .or_else(|| {
+            ["/etc/hostname", "/proc/sys/kernel/hostname"]
+                .iter()
+                .find_map(|path| fs::read_to_string(path).ok())
+        })
+        .map(|v| sanitize(v.trim()))
+        .unwrap_or_default();
+
+    match host.is_empty() {
+        true => "-".to_string(),
+        false => host,
+    }
+}
+
+/// 将每个镜像的延迟测试结果展开为样本
+pub fn delay_samples(tested: &[(String, Stats)]) -> Vec<(String, Delay)> {
+    tested
+        .iter()
+        .flat_map(|(name, stats)| {
+            stats
+                .samples()
+                .iter()
+                .map(move |v| (name.to_string(), v.clone()))
+        })
+        .collect()
+}
+
+/// 将每个镜像的下载速度测试结果转换为样本，单位为字节每秒
+pub fn throughput_samples(tested: &[(String, Bandwidth)]) -> Vec<(String, Delay)> {
+    tested
+        .iter()
+        .map(|(name, bandwidth)| {
+            let sample = match bandwidth {
+                Ok(v) => Ok(v.bytes_per_sec() as u128),
+                Err(e) => Err(e.clone()),
+            };
+
+            (name.to_string(), sample)
+        })
+        .collect()
+}
+
+/// 比较前一半和后一半成功样本的中位数，返回变化的百分比，成功的样本少于两个时返回 `None`
+pub fn trend(stats: &Stats) -> Option<f64> {
+    let values: Vec<Delay> = stats
+        .samples()
+        .iter()
+        .filter(|v| v.is_ok())
+        .cloned()
+        .collect();
+
+    if values.len() < 2 {
+        return None;
+    }
+
+    let (older, newer) = values.split_at(values.len() / 2);
+    let older = Stats::new(older.to_vec()).median()? as f64;
+    let newer = Stats::new(newer.to_vec()).median()? as f64;
+
+    (older > 0.0).then(|| (newer - older) / older * 100.0)
+}
+
+/// 获取当前的时间戳 (毫秒)
+pub fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| v.as_millis())
+        .unwrap_or_default()
+}
+
+/// 获取记录文件的路径
+fn history_path() -> CrmResult<PathBuf> {
+    Ok(crm_dir()?.join(HISTORY))
+}
+
+/// 将一次测试的结果追加到记录文件中，`results` 是每个镜像的所有样本
+pub fn append(
+    context: &Context,
+    command: &str,
+    kind: Kind,
+    results: Vec<(String, Delay)>,
+) -> CrmResult<()> {
+    if results.is_empty() {
+        return Ok(());
+    }
+
+    let time = now();
+    let lines: String = results
+        .into_iter()
+        .map(|(name, result)| {
+            let entry = Entry {
+                time,
+                context: context.clone(),
+                command: command.to_string(),
+                kind,
+                name,
+                result,
+            };
+
+            format!("{}\n", entry.to_line())
+        })
+        .collect();
+
+    let path = history_path()?;
+    let _lock = FileLock::acquire(&path)?;
+    let cutoff = time.saturating_sub(DAY_MILLIS * HISTORY_RETENTION_DAYS as u128);
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let kept: Vec<&str> = content
+        .lines()
+        .filter(|line| Entry::from_line(line).is_some_and(|v| v.time >= cutoff))
+        .collect();
+
+    // 有过期的记录时重写整个文件，否则直接追加
+    if kept.len() != content.lines().count() {
+        let mut data: String = kept.iter().map(|v| format!("{}\n", v)).collect();
+        data.push_str(&lines);
+
+        return Ok(fs::write(&path, data)?);
+    }
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(lines.as_bytes())?;
+
+    Ok(())
+}
+
+/// 读取 `since` (毫秒) 之后的所有记录，按照时间从早到晚排序
+pub fn load(since: u128) -> CrmResult<Vec<Entry>> {
+    let path = history_path()?;
+
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    let _lock = FileLock::acquire(&path)?;
+    let mut entries: Vec<Entry> = fs::read_to_string(&path)?
+        .lines()
+        .filter_map(Entry::from_line)
+        .filter(|v| v.time >= since)
+        .collect();
+
+    entries.sort_by_key(|v| v.time);
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(label: Option<&str>, result: Delay) -> Entry {
+        Entry {
+            time: 1_700_000_000_000,
+            context: Context {
+                host: "host".to_string(),
+                label: label.map(str::to_string),
+            },
+            command: "test".to_string(),
+            kind: Kind::Download,
+            name: "tuna".to_string(),
+            result,
+        }
+    }
+
+    #[test]
+    fn round_trips_lines() {
+        for entry in [
+            entry(Some("office"), Ok(42)),
+            entry(None, Err("HTTP 404".to_string().into())),
+        ] {
+            assert_eq!(Entry::from_line(&entry.to_line()), Some(entry));
+        }
+    }
+
+    #[test]
+    fn sanitizes_fields() {
+        let line = entry(Some("a\tb"), Err("line1\nline2".to_string().into())).to_line();
+        let parsed = Entry::from_line(&line).unwrap();
+
+        assert_eq!(line.matches('\t').count(), 7);
+        assert_eq!(parsed.context.label.as_deref(), Some("a b"));
+        assert_eq!(parsed.result, Err("line1 line2".to_string().into()));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in [
+            "",
+            "1\thost\t\ttest\tconnect\ttuna\t42",
+            "1\thost\t\ttest\tconnect\ttuna\t42\t\textra",
+            "x\thost\t\ttest\tconnect\ttuna\t42\t",
+            "1\thost\t\ttest\tping\ttuna\t42\t",
+            "1\thost\t\ttest\tconnect\ttuna\t4.2\t",
+        ] {
+            assert_eq!(Entry::from_line(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn trends() {
+        let stats = |v: &[u128]| Stats::new(v.iter().map(|v| Ok(*v)).collect());
+
+        assert_eq!(trend(&stats(&[])), None);
+        assert_eq!(trend(&stats(&[100])), None);
+        assert_eq!(trend(&stats(&[100, 150])), Some(50.0));
+        assert_eq!(trend(&stats(&[0, 150])), None);
+    }
+}
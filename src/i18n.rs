@@ -0,0 +1,1064 @@
+//! # 本地化
+//!
+//! 所有面向用户的文本都以消息 ID 的形式保存在该模块的消息目录中，每条消息都有 `zh-CN` 和 `en` 两种翻译。
+//!
+//! 输出语言依次由 `CRM_LANG`、`LC_ALL`、`LC_MESSAGES` 和 `LANG` 环境变量决定，以 `zh` 开头的值使用中文，
+//! 其他的值 (例如 `en_US.UTF-8` 或 `C`) 使用英文。如果都没有设置，则默认使用中文。
+
+use std::{
+    env,
+    fmt::{self, Display},
+    sync::OnceLock,
+};
+
+use crate::constants::CRM_LANG;
+
+/// 输出语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    /// 简体中文
+    ZhCn,
+
+    /// 英文
+    En,
+}
+
+impl Lang {
+    /// 根据 `zh_CN.UTF-8`、`en` 或 `C` 这样的语言环境获取输出语言，值为空时返回 `None`
+    fn from_locale(locale: &str) -> Option<Lang> {
+        let locale = locale.trim().to_lowercase();
+
+        if locale.is_empty() {
+            return None;
+        }
+
+        Some(if locale.starts_with("zh") {
+            Lang::ZhCn
+        } else {
+            Lang::En
+        })
+    }
+}
+
+/// 获取当前的输出语言，在第一次调用时根据环境变量确定
+pub fn lang() -> Lang {
+    static LANG: OnceLock<Lang> = OnceLock::new();
+
+    *LANG.get_or_init(|| {
+        [CRM_LANG, "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|key| env::var(key).ok().and_then(|v| Lang::from_locale(&v)))
+            .unwrap_or(Lang::ZhCn)
+    })
+}
+
+/// 消息 ID
+///
+/// 消息中的 `{}` 是占位符，通过 `Msg::format` 按顺序替换为参数。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // 命令和选项的帮助信息
+    HelpLocal,
+    HelpPath,
+    HelpDryRun,
+    HelpFormat,
+    HelpTimeout,
+    HelpSamples,
+    HelpCrates,
+    HelpMaxBytes,
+    HelpMaxTime,
+    HelpBy,
+    HelpBench,
+    HelpBenchName,
+    HelpProbe,
+    HelpProbeName,
+    HelpCrate,
+    HelpFreshness,
+    HelpFreshnessName,
+    HelpIndexCrates,
+    HelpMaxLag,
+    HelpVerify,
+    HelpVerifyName,
+    HelpVerifyFlag,
+    HelpProxy,
+    HelpCaCert,
+    HelpJobs,
+    HelpDeadline,
+    HelpDetailed,
+    HelpLabel,
+    HelpVerbose,
+    HelpTag,
+    HelpFilter,
+    HelpFromHistory,
+    HelpReport,
+    HelpDisable,
+    HelpEnable,
+    HelpAll,
+    HelpRename,
+    HelpRenameOld,
+    HelpRenameNew,
+    HelpAlias,
+    HelpAliasAction,
+    HelpAliasName,
+    HelpAliasRegistry,
+    HelpAliasList,
+    HelpAliasSave,
+    HelpAliasRemove,
+    HelpProfile,
+    HelpProfileAction,
+    HelpProfileName,
+    HelpProfileRegistry,
+    HelpProfileUse,
+    HelpProfileList,
+    HelpProfileSave,
+    HelpProfileRemove,
+    HelpProfileReset,
+    HelpReportName,
+    HelpDays,
+    HelpBest,
+    HelpBestMode,
+    HelpBestGit,
+    HelpBestSparse,
+    HelpBestGitDownload,
+    HelpBestSparseDownload,
+    HelpCurrent,
+    HelpDefault,
+    HelpInstall,
+    HelpList,
+    HelpPublish,
+    HelpRemove,
+    HelpSave,
+    HelpName,
+    HelpAddr,
+    HelpDl,
+    HelpTest,
+    HelpTestName,
+    HelpUpdate,
+    HelpUse,
+    HelpVersion,
+    HelpCheckUpdate,
+    HelpHistory,
+    HelpRollback,
+    HelpSnapshotIndex,
+    HelpCompletions,
+    HelpShell,
+    HelpHelp,
+    HelpCommandName,
+    HelpComplete,
+
+    // 帮助信息的排版
+    HelpMore,
+    HelpUsage,
+    HelpArguments,
+    HelpOptions,
+    HelpPassthrough,
+
+    // 参数解析
+    Separator,
+    PositiveInteger,
+    NonNegativeInteger,
+    InvalidOptionValue,
+    ExtraArgument,
+    MissingPositional,
+    UnknownOption,
+    MissingOptionValue,
+    UnexpectedOptionValue,
+    InvalidUtf8,
+    InvalidFormat,
+    InvalidShell,
+    InvalidBestMode,
+    InvalidProfileAction,
+    UnknownProfile,
+    ProfileReservedKey,
+    InvalidFilter,
+    ConflictingProtocols,
+    InvalidBackupLimit,
+    InvalidRank,
+    InvalidCrate,
+    InvalidCrateName,
+    InvalidProxy,
+    IgnoredProxy,
+    IgnoredNoProxy,
+    InvalidCaInfo,
+    NoCertificates,
+
+    // 错误
+    MissingName,
+    MissingAddr,
+    MissingDl,
+    UnknownCommand,
+    SeeHelp,
+    ConfigParse,
+    FieldType,
+    FieldUnder,
+    MissingField,
+    ConfigConflict,
+    EmptyRegistryList,
+    NoProfiles,
+    NoAppliedProfile,
+    ProfileReset,
+    UnknownRegistry,
+    RemoveMissing,
+    RenameMissing,
+    TestMissing,
+    BuiltinRemoval,
+    BuiltinRename,
+    NameTaken,
+    DisableOfficial,
+    DisableCurrent,
+    UseDisabled,
+    InvalidAliasAction,
+    UnknownAlias,
+    NoAliases,
+    IoFailed,
+    NetworkFailed,
+    Table,
+    String,
+    StringArray,
+    Integer,
+    Boolean,
+    ProtocolIgnored,
+    PleaseTry,
+    NoHomeDir,
+    PathTooLong,
+    VersionParse,
+    UnsupportedTest,
+    NotSparse,
+    ConfigJsonInvalid,
+    ConfigJsonMissingDl,
+    DlMismatch,
+    IndexLineInvalid,
+    IndexEmpty,
+    NoSparseIndex,
+    UpstreamFailed,
+    VersionNotInIndex,
+    ChecksumMismatch,
+    GitUnsupportedUrl,
+    GitNotSmart,
+    GitInvalidPktLine,
+    GitNoHead,
+    TlsVerifyFailed,
+    DeadlineExceeded,
+    InvalidLabel,
+    TimingInvalidUrl,
+    TimingProxied,
+    TimingNoAddress,
+    TimingTimedOut,
+    TimingEmptyResponse,
+    TimingInvalidResponse,
+    DownloadTooLarge,
+    JsonUnexpectedChar,
+    JsonUnexpectedEnd,
+    JsonExpected,
+    JsonInvalidNumber,
+    JsonInvalidEscape,
+    JsonUnterminatedString,
+    JsonTooDeep,
+
+    // 普通的输出
+    ObsoleteConfig,
+    ScopeGlobal,
+    ScopeLocal,
+    Scope,
+    DiffCurrent,
+    DiffModified,
+    DiffSnapshot,
+    NoChanges,
+    NoRegistryAvailable,
+    WillSwitch,
+    Switched,
+    ConnectLatency,
+    LatencyStats,
+    DownloadSpeed,
+    SpeedStats,
+    IndexCheckTitle,
+    IndexOk,
+    FreshnessTitle,
+    FreshnessOk,
+    FreshnessLag,
+    VerifyTitle,
+    VerifyOk,
+    VerifyMismatch,
+    GitIndexTitle,
+    GitOk,
+    GitHeadDiffers,
+    TimingTitle,
+    ReportTitle,
+    DetailDisplayName,
+    DetailDescription,
+    DetailRegion,
+    DetailTags,
+    DetailHomepage,
+    DetailProtocol,
+    DetailProxiesDownloads,
+    DetailPriority,
+    Yes,
+    Disabled,
+    No,
+    ReportLatency,
+    ReportThroughput,
+    NoHistory,
+    BestFromHistory,
+    TimingStats,
+    DownloadLatency,
+    InvalidSnapshotIndex,
+    SnapshotNotFound,
+    SnapshotUnchanged,
+    NoSnapshots,
+    RolledBack,
+    NewVersion,
+    NoUpdate,
+}
+
+impl Msg {
+    /// 获取消息的 `zh-CN` 和 `en` 翻译
+    fn translations(self) -> (&'static str, &'static str) {
+        match self {
+            Msg::HelpLocal => (
+                "在距离当前目录最近的项目级 .cargo/config.toml 中修改",
+                "Modify the nearest project-level .cargo/config.toml from the current directory",
+            ),
+            Msg::HelpPath => (
+                "在距离 dir 目录最近的项目级 .cargo/config.toml 中修改",
+                "Modify the nearest project-level .cargo/config.toml from dir",
+            ),
+            Msg::HelpDryRun => (
+                "只输出配置文件的变化，而不写入到文件中",
+                "Print the changes to the config file without writing them",
+            ),
+            Msg::HelpFormat => (
+                "输出格式，可以是 text、json 或 tsv",
+                "Output format: text, json or tsv",
+            ),
+            Msg::HelpTimeout => (
+                "每次网络请求的超时时间 (秒)，默认为 5 秒",
+                "Timeout of each network request in seconds (default: 5)",
+            ),
+            Msg::HelpSamples => (
+                "对每个镜像进行测试的次数，默认为 1 次",
+                "Number of probes per registry (default: 1)",
+            ),
+            Msg::HelpCrates => (
+                "测试下载速度或校验软件包时下载的软件包，格式为 name@version，多个软件包之间用逗号分隔 (verify 默认使用体积较小的 cfg-if 和 itoa)",
+                "Crates to download when measuring throughput or verifying checksums, as comma-separated name@version (verify defaults to the small cfg-if and itoa crates)",
+            ),
+            Msg::HelpMaxBytes => (
+                "测试下载速度时每个镜像最多下载的字节数，默认为 32000000",
+                "Maximum bytes to download from each registry (default: 32000000)",
+            ),
+            Msg::HelpMaxTime => (
+                "测试下载速度时每个镜像最多花费的时间 (秒)，默认为 10 秒",
+                "Maximum seconds to spend on each registry (default: 10)",
+            ),
+            Msg::HelpBy => (
+                "排名的依据，可以是 latency (延迟，默认) 或 throughput (下载速度)",
+                "Rank by latency (default) or throughput",
+            ),
+            Msg::HelpBench => (
+                "下载较大的软件包以评估下载速度",
+                "Download larger crates to measure throughput",
+            ),
+            Msg::HelpBenchName => (
+                "镜像名，不指定时测试所有 git 镜像",
+                "Registry name, all git registries are tested if omitted",
+            ),
+            Msg::HelpProbe => (
+                "检查 sparse 镜像的 config.json 和索引文件是否正确",
+                "Check that a sparse registry serves a valid config.json and index files",
+            ),
+            Msg::HelpProbeName => (
+                "镜像名，不指定时检查所有 sparse 镜像",
+                "Registry name, all sparse registries are checked if omitted",
+            ),
+            Msg::HelpCrate => (
+                "用于检查索引文件的软件包，默认为 serde",
+                "Crate whose index file is checked (default: serde)",
+            ),
+            Msg::HelpFreshness => (
+                "将镜像的索引与官方索引进行比较，检查镜像落后了多少个版本",
+                "Compare registry indexes with the official index and report how far they lag behind",
+            ),
+            Msg::HelpFreshnessName => (
+                "镜像名，不指定时检查所有镜像",
+                "Registry name, all registries are checked if omitted",
+            ),
+            Msg::HelpIndexCrates => (
+                "用于比较索引的软件包，多个软件包之间用逗号分隔，默认为 aws-sdk-s3,syn,tokio",
+                "Crates whose index files are compared, comma-separated (default: aws-sdk-s3,syn,tokio)",
+            ),
+            Msg::HelpMaxLag => (
+                "排除索引落后于官方索引超过 n 个版本的镜像",
+                "Exclude registries whose index lags more than n versions behind the official index",
+            ),
+            Msg::HelpVerify => (
+                "下载软件包并将其 SHA-256 与镜像索引和官方索引中的校验和进行比较",
+                "Download crates and compare their SHA-256 with the checksums in the registry and official indexes",
+            ),
+            Msg::HelpVerifyName => (
+                "镜像名，不指定时校验所有镜像",
+                "Registry name, all registries are verified if omitted",
+            ),
+            Msg::HelpVerifyFlag => (
+                "排除软件包校验失败或校验和不一致的镜像",
+                "Exclude registries whose crates fail checksum verification",
+            ),
+            Msg::HelpProxy => (
+                "网络请求使用的 http 代理，默认使用 Cargo 的 http.proxy 或 HTTPS_PROXY 等环境变量",
+                "HTTP proxy for network requests (default: cargo's http.proxy or HTTPS_PROXY and friends)",
+            ),
+            Msg::HelpCaCert => (
+                "额外信任的 CA 证书文件 (PEM 格式)，默认使用 Cargo 的 http.cainfo",
+                "Extra CA certificates to trust, in PEM format (default: cargo's http.cainfo)",
+            ),
+            Msg::HelpJobs => (
+                "最多同时测试的镜像数量，默认为 16",
+                "Maximum number of registries probed at the same time (default: 16)",
+            ),
+            Msg::HelpDeadline => (
+                "所有测试的总时限 (秒)，超过时限仍未完成的镜像记为失败",
+                "Overall time limit in seconds, registries not finished by then count as failed",
+            ),
+            Msg::HelpDetailed => (
+                "分别输出 DNS 解析、TCP 连接、TLS 握手、首字节和响应体传输的耗时",
+                "Show DNS, TCP connect, TLS handshake, time to first byte and body transfer separately",
+            ),
+            Msg::HelpLabel => (
+                "为测试记录添加的标签，例如网络环境的名称；report 只统计带有该标签的记录",
+                "Label stored with the results, e.g. the network you are on; report only counts results with this label",
+            ),
+            Msg::HelpVerbose => (
+                "同时输出镜像的名称、描述、地区、标签、主页、协议等信息",
+                "Also show display name, description, region, tags, homepage, protocol and more",
+            ),
+            Msg::HelpTag => (
+                "只使用地区或标签与之相同的镜像，例如 edu",
+                "Only use registries whose region or tags include this value, e.g. edu",
+            ),
+            Msg::HelpFilter => (
+                "筛选参与评估的镜像，例如 \"protocol=sparse,tag=edu,downloads,custom,!tuna\"",
+                "Filter the registries to evaluate, e.g. \"protocol=sparse,tag=edu,downloads,custom,!tuna\"",
+            ),
+            Msg::HelpFromHistory => (
+                "根据最近 24 小时内在本机得到的测试记录选择镜像，没有记录时再进行测试",
+                "Pick a registry from results recorded on this host in the last 24 hours, probing only when there are none",
+            ),
+            Msg::HelpDisable => (
+                "禁用镜像，被禁用的镜像不会出现在镜像列表、测试和 best 中",
+                "Disable a registry so it is left out of list, tests and best",
+            ),
+            Msg::HelpEnable => ("重新启用被禁用的镜像", "Enable a disabled registry again"),
+            Msg::HelpAll => ("同时包含被禁用的镜像", "Include disabled registries"),
+            Msg::HelpRename => (
+                "重命名自定义的镜像",
+                "Rename a custom registry",
+            ),
+            Msg::HelpRenameOld => ("原来的镜像名", "Current registry name"),
+            Msg::HelpRenameNew => ("新的镜像名", "New registry name"),
+            Msg::HelpAlias => (
+                "管理镜像的别名，别名可以在 use、test、remove 等命令中代替镜像名",
+                "Manage registry aliases, which can stand in for registry names in use, test, remove and more",
+            ),
+            Msg::HelpAliasAction => ("要执行的操作", "Action to perform"),
+            Msg::HelpAliasName => ("别名", "Alias"),
+            Msg::HelpAliasRegistry => ("别名指向的镜像", "Registry the alias points to"),
+            Msg::HelpAliasList => ("列出所有的别名", "List all aliases"),
+            Msg::HelpAliasSave => ("添加/更新别名", "Add or update an alias"),
+            Msg::HelpAliasRemove => ("删除别名", "Remove an alias"),
+            Msg::HelpProfile => (
+                "管理将镜像和 Cargo 网络配置组合在一起的配置方案",
+                "Manage profiles that bundle a registry with Cargo network settings",
+            ),
+            Msg::HelpProfileAction => ("要执行的操作", "Action to perform"),
+            Msg::HelpProfileName => ("配置方案名", "Profile name"),
+            Msg::HelpProfileRegistry => (
+                "保存配置方案时使用的镜像，默认为当前正在使用的镜像",
+                "Registry to save in the profile, defaults to the one in use",
+            ),
+            Msg::HelpProfileUse => (
+                "撤销上一个配置方案，切换到配置方案中的镜像并写入其中的字段",
+                "Revert the previous profile, then switch to the profile's registry and apply its settings",
+            ),
+            Msg::HelpProfileList => ("列出所有的配置方案", "List all profiles"),
+            Msg::HelpProfileSave => (
+                "将当前的镜像和 [http]、[net] 配置保存为配置方案",
+                "Save the current registry and [http]/[net] settings as a profile",
+            ),
+            Msg::HelpProfileRemove => ("删除配置方案", "Remove a profile"),
+            Msg::HelpProfileReset => (
+                "撤销最后一次应用的配置方案写入的字段，镜像保持不变",
+                "Revert the settings written by the last applied profile, keeping the registry",
+            ),
+            Msg::HelpReport => (
+                "根据测试记录输出每个镜像的趋势、成功率和百分位数",
+                "Show trends, success rates and percentiles of recorded results",
+            ),
+            Msg::HelpReportName => ("只统计该镜像的测试记录", "Only report on this registry"),
+            Msg::HelpDays => (
+                "统计最近多少天的测试记录，默认为 7 天",
+                "Number of days of results to report on (default: 7)",
+            ),
+            Msg::HelpBest => (
+                "评估网络延迟并自动切换到最优的镜像",
+                "Measure network latency and switch to the best registry",
+            ),
+            Msg::HelpBestMode => ("评估模式", "Evaluation mode"),
+            Msg::HelpBestGit => ("仅评估 git 镜像源", "Only evaluate git registries"),
+            Msg::HelpBestSparse => (
+                "仅评估支持 sparse 协议的镜像源",
+                "Only evaluate registries that support the sparse protocol",
+            ),
+            Msg::HelpBestGitDownload => (
+                "仅评估能够快速下载软件包的 git 镜像源 (推荐使用)",
+                "Only evaluate git registries that speed up downloads (recommended)",
+            ),
+            Msg::HelpBestSparseDownload => (
+                "仅评估能够快速下载软件包且支持 sparse 协议的镜像源 (推荐使用)",
+                "Only evaluate sparse registries that speed up downloads (recommended)",
+            ),
+            Msg::HelpCurrent => (
+                "获取当前所使用的镜像",
+                "Show the registry currently in use",
+            ),
+            Msg::HelpDefault => (
+                "恢复为官方默认镜像",
+                "Restore the official default registry",
+            ),
+            Msg::HelpInstall => (
+                "使用官方镜像执行 \"cargo install\"",
+                "Run \"cargo install\" with the official registry",
+            ),
+            Msg::HelpList => (
+                "从镜像配置文件中获取镜像列表",
+                "List the registries in the registry config file",
+            ),
+            Msg::HelpPublish => (
+                "使用官方镜像执行 \"cargo publish\"",
+                "Run \"cargo publish\" with the official registry",
+            ),
+            Msg::HelpRemove => (
+                "在镜像配置文件中删除镜像",
+                "Remove a registry from the registry config file",
+            ),
+            Msg::HelpSave => (
+                "在镜像配置文件中添加/更新镜像",
+                "Add or update a registry in the registry config file",
+            ),
+            Msg::HelpName => ("镜像名", "Registry name"),
+            Msg::HelpAddr => ("镜像地址", "Registry address"),
+            Msg::HelpDl => (
+                "镜像的 dl，即镜像 config.json 文件中的 dl 字段",
+                "The dl field in the registry's config.json",
+            ),
+            Msg::HelpTest => (
+                "下载测试包以评估网络延迟",
+                "Download a test package to measure network latency",
+            ),
+            Msg::HelpTestName => (
+                "镜像名，不指定时测试所有镜像",
+                "Registry name, all registries are tested if omitted",
+            ),
+            Msg::HelpUpdate => (
+                "使用官方镜像执行 \"cargo update\"",
+                "Run \"cargo update\" with the official registry",
+            ),
+            Msg::HelpUse => ("切换为要使用的镜像", "Switch to a registry"),
+            Msg::HelpVersion => ("查看当前版本", "Show the current version"),
+            Msg::HelpCheckUpdate => ("检测版本更新", "Check for a newer version"),
+            Msg::HelpHistory => (
+                "查看配置文件的快照列表，或查看第 n 个快照与当前文件的差异",
+                "List config file snapshots, or diff snapshot n against the current file",
+            ),
+            Msg::HelpRollback => (
+                "将配置文件恢复为第 n 个快照 (默认为最新的快照)",
+                "Restore a config file from snapshot n (default: the latest)",
+            ),
+            Msg::HelpSnapshotIndex => (
+                "快照编号，1 表示最新的快照",
+                "Snapshot number, 1 is the latest",
+            ),
+            Msg::HelpCompletions => (
+                "输出 shell 的自动补全脚本",
+                "Print the shell completion script",
+            ),
+            Msg::HelpShell => ("bash、zsh、fish 或 elvish", "bash, zsh, fish or elvish"),
+            Msg::HelpHelp => ("查看帮助信息", "Show help"),
+            Msg::HelpCommandName => ("命令名", "Command name"),
+            Msg::HelpComplete => (
+                "输出命令行的补全候选项，供补全脚本使用",
+                "Print completion candidates for the completion scripts",
+            ),
+
+            Msg::HelpMore => (
+                "  运行 \"{} help <command>\" 查看命令的详细用法",
+                "  Run \"{} help <command>\" for more information on a command",
+            ),
+            Msg::HelpUsage => ("  用法: {}", "  Usage: {}"),
+            Msg::HelpArguments => ("  参数:", "  Arguments:"),
+            Msg::HelpOptions => ("  选项:", "  Options:"),
+            Msg::HelpPassthrough => (
+                "  [args] 会原样传递给 \"cargo {}\"",
+                "  [args] are passed to \"cargo {}\" unchanged",
+            ),
+
+            Msg::Separator => ("、", ", "),
+            Msg::PositiveInteger => ("正整数", "a positive integer"),
+            Msg::NonNegativeInteger => ("非负整数", "a non-negative integer"),
+            Msg::InvalidOptionValue => (
+                "--{} 的值 \"{}\" 无效，可选的值为: {}",
+                "Invalid value \"{1}\" for --{0}, expected: {2}",
+            ),
+            Msg::ExtraArgument => ("多余的参数 \"{}\"", "Unexpected argument \"{}\""),
+            Msg::MissingPositional => ("缺少参数 <{}>", "Missing argument <{}>"),
+            Msg::UnknownOption => ("不支持的选项 \"--{}\"", "Unsupported option \"--{}\""),
+            Msg::MissingOptionValue => ("--{} 缺少 <{}>", "--{} requires <{}>"),
+            Msg::UnexpectedOptionValue => (
+                "--{} 不需要指定值",
+                "--{} does not take a value",
+            ),
+            Msg::InvalidUtf8 => (
+                "参数 {} 不是有效的 UTF-8 字符串",
+                "Argument {} is not valid UTF-8",
+            ),
+            Msg::InvalidFormat => (
+                "参数错误，--format 只能是 \"text\"、\"json\" 或 \"tsv\"，而不是 \"{}\"",
+                "Invalid argument, --format must be \"text\", \"json\" or \"tsv\", not \"{}\"",
+            ),
+            Msg::InvalidShell => (
+                "参数错误，shell 只能是 {}，而不是 \"{}\"",
+                "Invalid argument, shell must be one of {}, not \"{}\"",
+            ),
+            Msg::InvalidBestMode => (
+                "参数错误，您不能使用除 \"sparse\"、\"git\"、\"git-download\" 或 \"sparse-download\" 之外的值",
+                "Invalid argument, the mode must be \"sparse\", \"git\", \"git-download\" or \"sparse-download\"",
+            ),
+            Msg::InvalidProfileAction => (
+                "参数错误，您不能使用除 \"use\"、\"reset\"、\"list\"、\"save\" 或 \"remove\" 之外的值",
+                "Invalid argument, the action must be \"use\", \"reset\", \"list\", \"save\" or \"remove\"",
+            ),
+            Msg::UnknownProfile => (
+                "配置方案 \"{}\" 不存在，可以通过 crm profile list 查看所有的配置方案",
+                "Profile \"{}\" does not exist, run crm profile list to see all profiles",
+            ),
+            Msg::ProfileReservedKey => (
+                "配置方案 \"{}\" 不能修改 {} 字段，镜像由配置方案的 registry 字段指定",
+                "Profile \"{}\" cannot set {}, the registry is chosen by the registry field of the profile",
+            ),
+            Msg::InvalidFilter => (
+                "参数错误，无效的筛选条件 \"{}\"，可以使用 protocol=<git|sparse>、tag=<tag>、downloads、custom 或 !<name>",
+                "Invalid argument, unknown filter \"{}\", use protocol=<git|sparse>, tag=<tag>, downloads, custom or !<name>",
+            ),
+            Msg::ConflictingProtocols => (
+                "参数错误，不能同时筛选 {} 和 {} 协议的镜像",
+                "Invalid argument, cannot filter by both the {} and {} protocols",
+            ),
+            Msg::InvalidBackupLimit => (
+                "环境变量 {} 的值 \"{}\" 不是一个有效的数字",
+                "The value \"{1}\" of the environment variable {0} is not a valid number",
+            ),
+
+            Msg::InvalidRank => (
+                "参数错误，--by 只能是 \"latency\" 或 \"throughput\"，而不是 \"{}\"",
+                "Invalid argument, --by must be \"latency\" or \"throughput\", not \"{}\"",
+            ),
+            Msg::InvalidCrate => (
+                "参数错误，\"{}\" 不是 name@version 格式的软件包",
+                "Invalid argument, \"{}\" is not a crate in name@version form",
+            ),
+
+            Msg::InvalidCrateName => (
+                "参数错误，\"{}\" 不是有效的软件包名",
+                "Invalid argument, \"{}\" is not a valid crate name",
+            ),
+
+            Msg::InvalidProxy => (
+                "代理地址 \"{}\" (来自 {}) 无效，只支持 http 代理",
+                "Invalid proxy \"{}\" (from {}), only http proxies are supported",
+            ),
+            Msg::IgnoredProxy => (
+                "已忽略 {1} 环境变量中的代理地址 \"{0}\"，只支持 http 代理",
+                "Ignoring proxy \"{0}\" from {1}, only http proxies are supported",
+            ),
+            Msg::IgnoredNoProxy => (
+                "已忽略 {1} 环境变量中无效的 \"{0}\"，只支持主机名、域名后缀、IP 地址、CIDR 和 *",
+                "Ignoring invalid entry \"{0}\" in {1}, only host names, domain suffixes, IP addresses, CIDR ranges and * are supported",
+            ),
+            Msg::InvalidCaInfo => (
+                "无法加载 CA 证书文件 \"{}\" (来自 {}): {}",
+                "Unable to load CA certificates from \"{}\" (from {}): {}",
+            ),
+            Msg::NoCertificates => ("文件中没有证书", "no certificates in the file"),
+
+            Msg::MissingName => ("请输入正确的镜像名", "Please enter a valid registry name"),
+            Msg::MissingAddr => (
+                "请输入正确的镜像地址",
+                "Please enter a valid registry address",
+            ),
+            Msg::MissingDl => (
+                "请输入正确的 dl。\n  每一个镜像源都是一个 Git 存储库，而在该存储库的根目录下有一个 config.json 文件，\n  其中，dl 属性是 config.json 文件中的一个字段。",
+                "Please enter a valid dl.\n  Every registry is a Git repository with a config.json file in its root,\n  and dl is a field in that config.json file.",
+            ),
+            Msg::UnknownCommand => (
+                "{} 命令无效。参考:\n{}\nHome：<https://github.com/wtklbm/crm>\n",
+                "Invalid command {}. Usage:\n{}\nHome: <https://github.com/wtklbm/crm>\n",
+            ),
+            Msg::SeeHelp => (
+                "{}\n  运行 \"{} help {}\" 查看帮助信息",
+                "{}\n  Run \"{} help {}\" for more information",
+            ),
+            Msg::ConfigParse => ("{} 文件解析失败，{}", "Failed to parse {}, {}"),
+            Msg::FieldType => (
+                "{} 文件中的 {} 字段不是一个{}，{}",
+                "The {1} field in {0} is not a {2}, {3}",
+            ),
+            Msg::FieldUnder => ("[{}] 下的 {}", "{1} under [{0}]"),
+            Msg::MissingField => (
+                "{} 文件中的 {} 里没有包含 {} 或 {} 字段，{}",
+                "{1} in {0} does not contain a {2} or {3} field, {4}",
+            ),
+            Msg::ConfigConflict => (
+                "检测到了两种形式的配置文件，为了避免歧义，请将 {} 文件 (不再被推荐使用) 中的内容手动合并到 {} 文件中",
+                "Found two forms of the config file. To avoid ambiguity, please merge {} (deprecated) into {} manually",
+            ),
+            Msg::NoProfiles => (
+                "  没有配置方案，可以通过 crm profile save <name> 将当前的镜像和 [http]、[net] 配置保存为配置方案",
+                "  No profiles yet, run crm profile save <name> to save the current registry and [http]/[net] settings",
+            ),
+            Msg::NoAppliedProfile => (
+                "要修改的配置文件没有应用过配置方案",
+                "No profile has been applied to the config file",
+            ),
+            Msg::ProfileReset => (
+                "已撤销配置方案 {} 写入的字段",
+                "Reverted the settings written by profile {}",
+            ),
+            Msg::EmptyRegistryList => (
+                "没有找到 {} 镜像，配置中的镜像列表为空，请用 \"crm save\" 添加镜像后重试",
+                "Registry {} not found, the registry list is empty. Please add one with \"crm save\" and try again",
+            ),
+            Msg::UnknownRegistry => (
+                "没有找到 {} 镜像，可选的镜像是:\n{}",
+                "Registry {} not found, available registries:\n{}",
+            ),
+            Msg::RemoveMissing => (
+                "删除失败，{} 镜像不存在",
+                "Failed to remove, registry {} does not exist",
+            ),
+            Msg::RenameMissing => (
+                "重命名失败，{} 镜像不存在",
+                "Failed to rename, registry {} does not exist",
+            ),
+            Msg::TestMissing => (
+                "测试失败，{} 镜像不存在",
+                "Failed to test, registry {} does not exist",
+            ),
+            Msg::BuiltinRemoval => (
+                "请不要删除内置镜像 {}",
+                "The built-in registry {} cannot be removed",
+            ),
+            Msg::BuiltinRename => (
+                "不能重命名内置镜像 {}，可以通过 crm alias save <alias> {0} 为它添加别名",
+                "The built-in registry {} cannot be renamed, run crm alias save <alias> {0} to give it an alias",
+            ),
+            Msg::NameTaken => (
+                "{} 已经是一个镜像名或别名",
+                "{} is already a registry name or alias",
+            ),
+            Msg::DisableOfficial => (
+                "不能禁用官方镜像 {}",
+                "The official registry {} cannot be disabled",
+            ),
+            Msg::DisableCurrent => (
+                "不能禁用正在使用的镜像 {}，请先切换到其他镜像",
+                "The registry {} is in use and cannot be disabled, switch to another registry first",
+            ),
+            Msg::UseDisabled => (
+                "镜像 {} 已被禁用，请先通过 crm enable {0} 重新启用",
+                "The registry {} is disabled, run crm enable {0} to enable it first",
+            ),
+            Msg::InvalidAliasAction => (
+                "参数错误，您不能使用除 \"list\"、\"save\" 或 \"remove\" 之外的值",
+                "Invalid argument, the action must be \"list\", \"save\" or \"remove\"",
+            ),
+            Msg::UnknownAlias => (
+                "别名 \"{}\" 不存在，可以通过 crm alias list 查看所有的别名",
+                "Alias \"{}\" does not exist, run crm alias list to see all aliases",
+            ),
+            Msg::NoAliases => (
+                "  没有别名，可以通过 crm alias save <alias> <name> 为镜像添加别名",
+                "  No aliases yet, run crm alias save <alias> <name> to add one",
+            ),
+            Msg::IoFailed => ("操作失败:\n  {}", "Operation failed:\n  {}"),
+            Msg::NetworkFailed => ("网络请求失败:\n  {}", "Network request failed:\n  {}"),
+            Msg::Table => ("表", "table"),
+            Msg::String => ("字符串", "string"),
+            Msg::StringArray => ("字符串数组", "array of strings"),
+            Msg::Boolean => ("布尔值", "boolean"),
+            Msg::Integer => ("非负整数", "non-negative integer"),
+            Msg::ProtocolIgnored => (
+                "{} 文件中的 {} 字段与镜像地址不一致，已忽略，协议由地址是否以 sparse+ 开头决定 (当前为 {})",
+                "The {1} field in {0} does not match the registry address and is ignored, the protocol is decided by the sparse+ prefix (currently {2})",
+            ),
+            Msg::PleaseTry => (
+                "请修改/删除后重试",
+                "please fix or remove it and try again",
+            ),
+            Msg::NoHomeDir => (
+                "无法获取当前用户的主目录",
+                "Unable to determine the home directory of the current user",
+            ),
+            Msg::PathTooLong => (
+                "当前路径超过了 Windows 普通路径的最大长度: {}",
+                "The path exceeds the maximum length of a regular Windows path: {}",
+            ),
+            Msg::VersionParse => (
+                "无法从 {} 中解析出最新的版本",
+                "Unable to parse the newest version from {}",
+            ),
+            Msg::UnsupportedTest => (
+                "不支持测试该镜像",
+                "Testing this registry is not supported",
+            ),
+
+            Msg::NotSparse => (
+                "不是 sparse 镜像",
+                "not a sparse registry",
+            ),
+            Msg::ConfigJsonInvalid => (
+                "config.json 解析失败: {}",
+                "failed to parse config.json: {}",
+            ),
+            Msg::ConfigJsonMissingDl => (
+                "config.json 中没有 dl 字段",
+                "config.json has no dl field",
+            ),
+            Msg::DlMismatch => (
+                "config.json 中的 dl 为 {}，与 .crmrc 中的 {} 不一致",
+                "dl in config.json is {}, which does not match {} in .crmrc",
+            ),
+            Msg::IndexLineInvalid => (
+                "索引文件 {} 的第 {} 行无效: {}",
+                "line {1} of index file {0} is invalid: {2}",
+            ),
+            Msg::IndexEmpty => ("索引文件 {} 是空的", "index file {} is empty"),
+            Msg::NoSparseIndex => (
+                "git 镜像无法按文件请求索引，不支持检查同步情况",
+                "not supported for git registries, which cannot serve single index files",
+            ),
+            Msg::UpstreamFailed => (
+                "无法获取官方索引: {}",
+                "unable to fetch the official index: {}",
+            ),
+            Msg::VersionNotInIndex => (
+                "索引文件 {2} 中没有 {0} {1} 的校验和",
+                "index file {2} has no checksum for {0} {1}",
+            ),
+            Msg::GitUnsupportedUrl => (
+                "只能检查通过 HTTP 访问的 git 镜像",
+                "only git registries served over HTTP can be checked",
+            ),
+            Msg::GitNotSmart => (
+                "不是 git smart HTTP 服务",
+                "not a git smart HTTP service",
+            ),
+            Msg::GitInvalidPktLine => (
+                "引用列表的格式无效",
+                "invalid ref advertisement",
+            ),
+            Msg::TlsVerifyFailed => (
+                "TLS 证书验证失败: {}，可以通过 --cacert 或 Cargo 的 http.cainfo 指定 CA 证书",
+                "TLS certificate verification failed: {}, use --cacert or cargo's http.cainfo to trust the CA",
+            ),
+            Msg::DeadlineExceeded => ("超过了总时限", "deadline exceeded"),
+            Msg::InvalidLabel => ("无效的标签 \"{}\"", "Invalid label \"{}\""),
+            Msg::TimingInvalidUrl => ("不是有效的 HTTP 地址: {}", "not a valid HTTP URL: {}"),
+            Msg::TimingProxied => (
+                "经过代理的请求无法分阶段计时",
+                "phase timings are not available through a proxy",
+            ),
+            Msg::TimingNoAddress => ("{} 没有解析出任何地址", "{} resolved to no addresses"),
+            Msg::TimingTimedOut => ("请求超时", "timed out"),
+            Msg::TimingEmptyResponse => (
+                "服务器没有返回任何数据就关闭了连接",
+                "connection closed without a response",
+            ),
+            Msg::TimingInvalidResponse => ("无效的 HTTP 响应", "invalid HTTP response"),
+            Msg::DownloadTooLarge => (
+                "{} 的内容超过了 {} 字节",
+                "{} is larger than {} bytes",
+            ),
+            Msg::JsonUnexpectedChar => ("意外的字符 '{}'", "unexpected character '{}'"),
+            Msg::JsonUnexpectedEnd => ("意外的输入结束", "unexpected end of input"),
+            Msg::JsonExpected => ("应为 '{}'，实际为 '{}'", "expected '{}', found '{}'"),
+            Msg::JsonInvalidNumber => ("无效的数字 \"{}\"", "invalid number \"{}\""),
+            Msg::JsonInvalidEscape => ("无效的转义序列", "invalid escape sequence"),
+            Msg::JsonUnterminatedString => ("字符串没有结束", "unterminated string"),
+            Msg::JsonTooDeep => ("嵌套超过了 {} 层", "nesting exceeds {} levels"),
+            Msg::GitNoHead => ("引用列表中没有 HEAD", "no HEAD in the ref advertisement"),
+            Msg::ChecksumMismatch => (
+                "{} 镜像提供的软件包与官方索引中的校验和不一致，镜像可能已被篡改或损坏，请不要使用",
+                "Crates served by {} do not match the checksums in the official index. The registry may be tampered with or corrupt, do not use it",
+            ),
+
+            Msg::ObsoleteConfig => (
+                "检测到了 {} 配置文件 (不再被推荐使用)，以后请使用 {} 配置文件",
+                "Found the deprecated config file {}, please use {} from now on",
+            ),
+            Msg::ScopeGlobal => ("全局", "global"),
+            Msg::ScopeLocal => ("项目", "project"),
+            Msg::Scope => ("{}\n 作用域: {} ({})", "{}\n Scope: {} ({})"),
+            Msg::DiffCurrent => ("{} (当前)", "{} (current)"),
+            Msg::DiffModified => ("{} (修改后)", "{} (modified)"),
+            Msg::DiffSnapshot => ("{} (快照 {})", "{} (snapshot {})"),
+            Msg::NoChanges => (
+                "配置文件不会发生变化",
+                "The config file would not change",
+            ),
+            Msg::NoRegistryAvailable => (
+                "没有可切换的镜像源",
+                "No registry is available to switch to",
+            ),
+            Msg::WillSwitch => ("将会切换到 {} 镜像源", "Would switch to registry {}"),
+            Msg::Switched => ("已切换到 {} 镜像源", "Switched to registry {}"),
+            Msg::ConnectLatency => ("网络连接延迟:", "Connection latency:"),
+            Msg::LatencyStats => (
+                "中位数 {} ms，最小 {} ms，p90 {} ms，抖动 {} ms，成功率 {}%",
+                "median {} ms, min {} ms, p90 {} ms, jitter {} ms, {}% success",
+            ),
+            Msg::DownloadSpeed => ("下载速度:", "Download throughput:"),
+            Msg::SpeedStats => (
+                "{} MB/s ({} MB，{} 秒)",
+                "{} MB/s ({} MB in {} s)",
+            ),
+            Msg::IndexCheckTitle => ("sparse 索引检查:", "Sparse index check:"),
+            Msg::IndexOk => ("正常，{} 共有 {} 个版本", "ok, {} has {} versions"),
+            Msg::FreshnessTitle => ("索引同步情况:", "Index freshness:"),
+            Msg::FreshnessOk => ("已同步", "up to date"),
+            Msg::FreshnessLag => (
+                "落后 {} 个版本 ({})",
+                "{} versions behind ({})",
+            ),
+            Msg::VerifyTitle => ("软件包校验:", "Crate verification:"),
+            Msg::VerifyOk => ("正常，已校验 {} 个软件包", "ok, {} crates verified"),
+            Msg::VerifyMismatch => (
+                "校验和不一致! {} {} ({}): 官方索引为 {}，镜像为 {}",
+                "CHECKSUM MISMATCH! {} {} ({}): official index has {}, registry has {}",
+            ),
+            Msg::GitIndexTitle => ("git 索引:", "Git index:"),
+            Msg::GitOk => ("{} ms，HEAD {}", "{} ms, HEAD {}"),
+            Msg::GitHeadDiffers => (
+                "，与 {} 的 HEAD {} 不同",
+                ", differs from HEAD {1} of {0}",
+            ),
+            Msg::DetailDisplayName => ("名称", "Name"),
+            Msg::DetailDescription => ("描述", "Description"),
+            Msg::DetailRegion => ("地区", "Region"),
+            Msg::DetailTags => ("标签", "Tags"),
+            Msg::DetailHomepage => ("主页", "Homepage"),
+            Msg::DetailProtocol => ("协议", "Protocol"),
+            Msg::DetailProxiesDownloads => ("代理下载", "Proxies downloads"),
+            Msg::DetailPriority => ("优先级", "Priority"),
+            Msg::Yes => ("是", "yes"),
+            Msg::No => ("否", "no"),
+            Msg::Disabled => ("已禁用", "disabled"),
+            Msg::ReportTitle => ("最近 {} 天的测试记录 (UTC):", "Results from the last {} days (UTC):"),
+            Msg::ReportLatency => (
+                "{0} 个样本，成功率 {1}%，中位数 {2}，p90 {3}，趋势 {4}，最近一次 {5}",
+                "{0} samples, {1}% success, median {2}, p90 {3}, trend {4}, last {5}",
+            ),
+            Msg::ReportThroughput => (
+                "{0} 个样本，成功率 {1}%，中位数 {2}，最低 {3}，趋势 {4}，最近一次 {5}",
+                "{0} samples, {1}% success, median {2}, min {3}, trend {4}, last {5}",
+            ),
+            Msg::NoHistory => (
+                "最近 {} 天没有测试记录，可以先运行 crm test 或 crm bench",
+                "No results in the last {} days, run crm test or crm bench first",
+            ),
+            Msg::BestFromHistory => (
+                "根据最近 {1} 小时内的 {0} 个样本选择镜像",
+                "Picked from {0} samples recorded in the last {1} hours",
+            ),
+            Msg::TimingTitle => ("分阶段耗时:", "Phase timings:"),
+            Msg::TimingStats => (
+                "DNS {0}，TCP {1}，TLS {2}，首字节 {3}，响应体 {4}，总计 {5} (HTTP {6})",
+                "DNS {0}, TCP {1}, TLS {2}, TTFB {3}, body {4}, total {5} (HTTP {6})",
+            ),
+            Msg::DownloadLatency => ("软件包下载延迟:", "Download latency:"),
+            Msg::InvalidSnapshotIndex => (
+                "{} 不是一个有效的快照编号",
+                "{} is not a valid snapshot number",
+            ),
+            Msg::SnapshotNotFound => (
+                "没有找到编号为 {} 的快照",
+                "Snapshot {} not found",
+            ),
+            Msg::SnapshotUnchanged => (
+                "  快照与当前文件的内容相同",
+                "  The snapshot is identical to the current file",
+            ),
+            Msg::NoSnapshots => ("  暂无快照", "  No snapshots yet"),
+            Msg::RolledBack => (
+                "已将 {} 恢复为 {} (UTC) 的快照",
+                "Restored {} from the snapshot taken at {} (UTC)",
+            ),
+            Msg::NewVersion => (
+                "  检测到新版本: {}，请切换到官方镜像源以执行更新",
+                "  New version available: {}, please switch to the official registry to update",
+            ),
+            Msg::NoUpdate => ("  暂无更新", "  Already up to date"),
+        }
+    }
+
+    /// 获取当前语言下的消息文本
+    pub fn text(self) -> &'static str {
+        let (zh, en) = self.translations();
+
+        match lang() {
+            Lang::ZhCn => zh,
+            Lang::En => en,
+        }
+    }
+
+    /// 使用参数替换消息中的占位符
+    ///
+    /// `{}` 会按顺序替换为下一个参数，`{0}`、`{1}` 这样的占位符会替换为对应位置的参数，
+    /// 以便不同的翻译能够调整参数的顺序。
+    pub fn format(self, args: &[&dyn Display]) -> String {
+        let text = self.text();
+        let mut out = String::with_capacity(text.len());
+        let mut next = 0;
+        let mut rest = text;
+
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+
+            let index = match &rest[1..end] {
+                "" => {
+                    next += 1;
+                    Some(next - 1)
+                }
+                v => v.parse::<usize>().ok(),
+            };
+
+            match index.and_then(|i| args.get(i)) {
+                Some(arg) => out.push_str(&arg.to_string()),
+                None => out.push_str(&rest[..=end]),
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        out.push_str(rest);
+        out
+    }
+}
+
+impl Display for Msg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
@@ -0,0 +1,315 @@
+//! # JSON 解析
+//!
+//! 镜像的 `config.json` 和 `sparse` 索引文件都是 `JSON` 格式的，为了保持最小依赖，
+//! 该模块实现了一个简单的 `JSON` 解析器，只用于读取这些文件中的字段。
+//!
+//! 这些文件来自于镜像，内容不可信，所以解析器限制了数组和对象的嵌套层数，以免深度嵌套的数据导致栈溢出。
+
+use std::{iter::Peekable, str::Chars};
+
+use crate::i18n::Msg;
+
+/// 数组和对象的最大嵌套层数，`config.json` 和索引文件的嵌套层数都不会超过 3 层
+pub const MAX_DEPTH: usize = 64;
+
+/// `JSON` 值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// 解析 `JSON` 文本，失败时返回失败的原因
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+
+        parser.skip_whitespace();
+
+        match parser.chars.next() {
+            None => Ok(value),
+            c => Err(unexpected(c)),
+        }
+    }
+
+    /// 获取对象中的字段
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// 获取字符串的值
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// 创建遇到了意外的字符或输入结束的错误
+fn unexpected(c: Option<char>) -> String {
+    match c {
+        Some(c) => Msg::JsonUnexpectedChar.format(&[&c]),
+        None => Msg::JsonUnexpectedEnd.to_string(),
+    }
+}
+
+/// 递归下降解析器
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+
+    /// 当前所在的数组和对象的嵌套层数
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(Msg::JsonExpected.format(&[&expected, &c])),
+            None => Err(unexpected(None)),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.nested(Parser::array),
+            Some('{') => self.nested(Parser::object),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            c => Err(unexpected(c.copied())),
+        }
+    }
+
+    /// 解析嵌套的数组或对象，超过最大嵌套层数时返回错误
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Msg::JsonTooDeep.format(&[&MAX_DEPTH]));
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(c);
+        }
+
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| Msg::JsonInvalidNumber.format(&[&text]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+
+        let mut out = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(out),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('/') => out.push('/'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('u') => out.push(self.unicode()?),
+                    _ => return Err(Msg::JsonInvalidEscape.to_string()),
+                },
+                Some(c) => out.push(c),
+                None => return Err(Msg::JsonUnterminatedString.to_string()),
+            }
+        }
+    }
+
+    /// 解析 `\uXXXX` 转义，包括代理对
+    fn unicode(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| Msg::JsonInvalidEscape.to_string());
+        }
+
+        self.expect('\\')?;
+        self.expect('u')?;
+
+        let low = self.hex4()?;
+
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(Msg::JsonInvalidEscape.to_string());
+        }
+
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+
+        char::from_u32(code).ok_or_else(|| Msg::JsonInvalidEscape.to_string())
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| Msg::JsonInvalidEscape.to_string())?;
+
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        let mut items = vec![];
+
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(items)),
+                c => return Err(unexpected(c)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        let mut fields = vec![];
+
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let key = self.string()?;
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                c => return Err(unexpected(c)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_index_line() {
+        let line = r#"{"name":"serde","vers":"1.0.0","deps":[{"name":"a","optional":false}],"cksum":"ab","yanked":null,"v":2}"#;
+        let value = Json::parse(line).unwrap();
+
+        assert_eq!(value.get("name").and_then(Json::as_str), Some("serde"));
+        assert_eq!(value.get("yanked"), Some(&Json::Null));
+        assert_eq!(value.get("v"), Some(&Json::Number(2.0)));
+        assert_eq!(value.get("missing"), None);
+        assert!(matches!(value.get("deps"), Some(Json::Array(v)) if v.len() == 1));
+    }
+
+    #[test]
+    fn parses_escapes() {
+        let value = Json::parse(r#""a\"\\\/\n\u00e9\ud83d\ude00""#).unwrap();
+
+        assert_eq!(value.as_str(), Some("a\"\\/\n\u{e9}\u{1f600}"));
+    }
+
+    #[test]
+    fn parses_empty_containers_and_whitespace() {
+        assert_eq!(Json::parse(" [ ] ").unwrap(), Json::Array(vec![]));
+        assert_eq!(Json::parse("{ }").unwrap(), Json::Object(vec![]));
+        assert_eq!(Json::parse("-1.5e2").unwrap(), Json::Number(-150.0));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for text in [
+            "",
+            "nul",
+            "[1,",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\":1,}",
+            "\"abc",
+            "\"\\x\"",
+            "\"\\ud83d\\u0041\"",
+            "1-2",
+            "{} x",
+        ] {
+            assert!(Json::parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |n: usize| format!("{}{}", "[".repeat(n), "]".repeat(n));
+
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(MAX_DEPTH + 1)),
+            Err(Msg::JsonTooDeep.format(&[&MAX_DEPTH]))
+        );
+        assert!(Json::parse(&"[".repeat(1_000_000)).is_err());
+    }
+}
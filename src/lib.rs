@@ -1,8 +1,26 @@
 pub mod args;
+pub mod backup;
 pub mod cargo;
+pub mod checksum;
+pub mod command;
+pub mod completion;
 pub mod constants;
 pub mod description;
+pub mod diff;
+pub mod error;
+pub mod filter;
+pub mod git;
+pub mod history;
+pub mod i18n;
+pub mod json;
+pub mod lock;
+pub mod net;
+pub mod output;
+pub mod profile;
 pub mod registry;
 pub mod runtime;
+pub mod sparse;
+pub mod stats;
+pub mod timing;
 pub mod toml;
 pub mod utils;
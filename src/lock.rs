@@ -0,0 +1,169 @@
+//! # 文件锁
+//!
+//! 为了避免多个 `crm` 进程同时修改同一个配置文件，修改配置文件的命令会在重新读取配置文件之前加上独占锁，
+//! 直到写入完成后才释放，这样整个 "读取-修改-写入" 的过程都会被串行化。只读取配置文件的命令不会加锁。
+//!
+//! 锁文件存放在 `${HOME}/.crm/locks` 目录中，而不是配置文件的旁边，以免在项目目录中留下多余的文件。
+//! 同一个进程中可能会为同一个配置文件创建多个配置对象，所以锁是可重入的，它们会共享同一个锁。
+
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, Weak},
+};
+
+use crate::{constants::LOCKS, error::CrmResult, utils::crm_dir};
+
+/// 锁文件名中保留的路径的最大长度，超出时只保留路径的末尾部分
+const MAX_NAME_LEN: usize = 200;
+
+/// 当前进程中已经持有的锁
+static HELD: OnceLock<Mutex<HashMap<PathBuf, Weak<FileLock>>>> = OnceLock::new();
+
+/// 文件锁，当最后一个引用被销毁时释放
+#[derive(Debug)]
+pub struct FileLock {
+    /// 锁文件
+    file: File,
+}
+
+impl FileLock {
+    /// 获取 `path` 文件的独占锁，如果锁已经被其他进程持有，则一直等待
+    pub fn acquire(path: &Path) -> CrmResult<Arc<FileLock>> {
+        FileLock::acquire_file(lock_path(path)?)
+    }
+
+    /// 对锁文件 `lock_path` 加锁，当前进程已经持有该锁时返回同一个锁
+    fn acquire_file(lock_path: PathBuf) -> CrmResult<Arc<FileLock>> {
+        let mut held = HELD
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        if let Some(lock) = held.get(&lock_path).and_then(Weak::upgrade) {
+            return Ok(lock);
+        }
+
+        if let Some(parent) = lock_path.parent() {
+            if !parent.is_dir() {
+                create_dir_all(parent)?;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+
+        file.lock()?;
+
+        let lock = Arc::new(FileLock { file });
+        held.insert(lock_path, Arc::downgrade(&lock));
+
+        Ok(lock)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// 获取 `path` 文件对应的锁文件的路径，例如 `/home/u/.cargo/config.toml` 对应
+/// `${HOME}/.crm/locks/home_u_.cargo_config.toml.lock`
+fn lock_path(path: &Path) -> CrmResult<PathBuf> {
+    Ok(crm_dir()?.join(LOCKS).join(lock_name(path)))
+}
+
+/// 根据 `path` 的完整路径生成锁文件名，路径中除了字母、数字、`.` 和 `-` 之外的字符都会被替换为 `_`
+///
+/// 文件名只取决于路径本身，所以不同版本的 `crm` 会使用同一个锁文件。不同的路径偶尔会得到相同的文件名，
+/// 这只会让它们的修改被不必要地串行化，而不会出错。
+fn lock_name(path: &Path) -> String {
+    let name: Vec<char> = path
+        .to_string_lossy()
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '.' || c == '-' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    let name: String = name[name.len().saturating_sub(MAX_NAME_LEN)..]
+        .iter()
+        .collect();
+
+    format!("{}.lock", name.trim_start_matches('_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, sync::mpsc, thread, time::Duration};
+
+    use super::*;
+
+    fn temp_lock(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("crm-{}-locks", process::id()))
+            .join(format!("{}.lock", name))
+    }
+
+    #[test]
+    fn lock_names_are_stable_and_distinct() {
+        assert_eq!(
+            lock_name(Path::new("/home/u/.cargo/config.toml")),
+            "home_u_.cargo_config.toml.lock"
+        );
+        assert_ne!(
+            lock_name(Path::new("/a/.cargo/config.toml")),
+            lock_name(Path::new("/b/.cargo/config.toml"))
+        );
+        assert!(lock_name(&Path::new("/").join("x".repeat(300))).len() <= MAX_NAME_LEN + 5);
+    }
+
+    #[test]
+    fn lock_is_reentrant() {
+        let path = temp_lock("reentrant");
+        let a = FileLock::acquire_file(path.clone()).unwrap();
+        let b = FileLock::acquire_file(path.clone()).unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+
+        drop(a);
+
+        // 还有引用时锁不会被释放
+        assert!(File::open(&path).unwrap().try_lock().is_err());
+
+        drop(b);
+
+        assert!(File::open(&path).unwrap().try_lock().is_ok());
+    }
+
+    #[test]
+    fn lock_blocks_other_holders() {
+        let path = temp_lock("contention");
+        let lock = FileLock::acquire_file(path.clone()).unwrap();
+
+        // 另一个文件句柄相当于另一个进程，在锁被释放之前无法加锁
+        let other = File::open(&path).unwrap();
+
+        assert!(other.try_lock().is_err());
+
+        let (sender, receiver) = mpsc::channel();
+        let waiter = thread::spawn(move || {
+            other.lock().unwrap();
+            sender.send(()).unwrap();
+        });
+
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+        drop(lock);
+
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+        waiter.join().unwrap();
+
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1,5 +1,13 @@
-use crm::args::{handle_command, parse_args};
+use std::process;
+
+use crm::{
+    args::{handle_command, parse_args},
+    utils::to_out,
+};
 
 fn main() {
-    handle_command(parse_args());
+    if let Err(e) = parse_args().and_then(handle_command) {
+        to_out(&e);
+        process::exit(e.exit_code());
+    }
 }
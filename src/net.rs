@@ -0,0 +1,671 @@
+//! # 网络代理和证书
+//!
+//! `crm` 发出的所有网络请求都会经过该模块，以便与 `Cargo` 使用相同的代理和 CA 证书。
+//!
+//! 代理的优先级依次为:
+//!   - `--proxy <url>` 参数
+//!   - `CARGO_HTTP_PROXY` 环境变量
+//!   - `Cargo` 配置文件中的 `http.proxy` 字段
+//!   - `HTTPS_PROXY` (用于 `https` 地址)、`HTTP_PROXY` (用于 `http` 地址) 或 `ALL_PROXY` 环境变量
+//!
+//! 主机与 `NO_PROXY` 环境变量中的某一项匹配时，不会使用代理，每一项可以是主机名 (可以带有端口)、
+//! 以 `.` 或 `*.` 开头的域名后缀、IP 地址、`CIDR` 格式的 IP 地址范围或者 `*`。显式指定的代理无效时会返回错误，
+//! 而环境变量中不支持的代理 (例如 `socks5://`) 只会输出警告并被忽略，因为这些环境变量通常是为其他程序设置的。
+//!
+//! 除了内置的根证书之外，还会信任 `--cacert <file>` 参数、`CARGO_HTTP_CAINFO` 环境变量或 `Cargo` 配置文件中的
+//! `http.cainfo` 字段指定的 CA 证书，以便测试使用私有 CA 签发的证书的内部镜像。
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    net::IpAddr,
+    sync::{Arc, OnceLock},
+};
+
+use ureq::{
+    rustls::{self, pki_types::pem::PemObject, pki_types::CertificateDer, RootCertStore},
+    Agent, AgentBuilder, Proxy, Request, Transport,
+};
+
+use crate::{
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+    utils::to_err,
+};
+
+/// 根据代理配置创建的请求对象
+struct Network {
+    /// 不使用代理的请求对象
+    direct: Agent,
+
+    /// 用于 `http` 地址的请求对象，不需要代理时为 `None`
+    http: Option<Agent>,
+
+    /// 用于 `https` 地址的请求对象，不需要代理时为 `None`
+    https: Option<Agent>,
+
+    /// 不使用代理的主机
+    no_proxy: Vec<NoProxy>,
+
+    /// 包含额外 CA 证书的 `TLS` 配置，没有指定 CA 证书时为 `None`
+    tls: Option<Arc<rustls::ClientConfig>>,
+}
+
+static NETWORK: OnceLock<Network> = OnceLock::new();
+
+/// 读取环境变量的函数，以便在测试中使用指定的环境变量
+type EnvFn<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// 读取进程的环境变量
+fn process_env(key: &str) -> Option<String> {
+    env::var(key).ok()
+}
+
+/// 获取第一个不为空的环境变量的名称和值
+fn env_var(env: EnvFn, keys: &[&str]) -> Option<(String, String)> {
+    keys.iter().find_map(|key| {
+        env(key)
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| (key.to_string(), v))
+    })
+}
+
+/// 获取 `Cargo` 的 `http.<key>` 设置及其来源，例如 `http.proxy`
+///
+/// 优先级依次为命令行参数 `arg`、`CARGO_HTTP_<KEY>` 环境变量以及 `config` 读取的配置文件中的字段，
+/// 只有前面的都没有设置时才会调用 `config`。
+pub fn http_setting<F>(
+    key: &str,
+    arg: Option<(String, String)>,
+    config: F,
+) -> CrmResult<Option<(String, String)>>
+where
+    F: FnOnce() -> CrmResult<Option<(String, String)>>,
+{
+    http_setting_with(&process_env, key, arg, config)
+}
+
+/// 使用 `env` 读取环境变量，获取 `Cargo` 的 `http.<key>` 设置及其来源
+fn http_setting_with<F>(
+    env: EnvFn,
+    key: &str,
+    arg: Option<(String, String)>,
+    config: F,
+) -> CrmResult<Option<(String, String)>>
+where
+    F: FnOnce() -> CrmResult<Option<(String, String)>>,
+{
+    if arg.is_some() {
+        return Ok(arg);
+    }
+
+    let var = format!("CARGO_HTTP_{}", key.to_uppercase().replace('-', "_"));
+
+    match env_var(env, &[&var]) {
+        Some((var, value)) => Ok(Some((value, var))),
+        None => config(),
+    }
+}
+
+/// `NO_PROXY` 中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NoProxy {
+    /// 所有的主机
+    All,
+
+    /// 主机名或 IP 地址，主机名也会匹配它的子域名，`port` 为 `None` 时匹配所有的端口
+    Host { host: String, port: Option<u16> },
+
+    /// `CIDR` 格式的 IP 地址范围，例如 `10.0.0.0/8`
+    Cidr { addr: IpAddr, prefix: u8 },
+}
+
+impl NoProxy {
+    /// 解析 `NO_PROXY` 中的一项，格式无效时返回 `None`
+    fn parse(entry: &str) -> Option<NoProxy> {
+        let entry = entry.trim().to_lowercase();
+
+        if entry == "*" {
+            return Some(NoProxy::All);
+        }
+
+        if let Some((addr, prefix)) = entry.split_once('/') {
+            let addr: IpAddr = addr
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok()?;
+            let prefix: u8 = prefix.parse().ok()?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+
+            return (prefix <= max).then_some(NoProxy::Cidr { addr, prefix });
+        }
+
+        let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+
+        // `IPv6` 地址需要放在方括号中才能指定端口，例如 `[::1]:8080`
+        let (host, port) = match entry.strip_prefix('[') {
+            Some(v) => {
+                let (host, rest) = v.split_once(']')?;
+
+                (host, rest.strip_prefix(':'))
+            }
+            None if entry.matches(':').count() == 1 => {
+                let (host, port) = entry.split_once(':')?;
+
+                (host, Some(port))
+            }
+            None => (entry, None),
+        };
+
+        if host.is_empty() || host.contains(['/', '@', '[', ']']) {
+            return None;
+        }
+
+        Some(NoProxy::Host {
+            host: host.to_string(),
+            port: port.map(str::parse).transpose().ok()?,
+        })
+    }
+
+    /// 判断请求 `host` 的 `port` 端口时是否匹配该项
+    fn matches(&self, host: &str, port: u16) -> bool {
+        match self {
+            NoProxy::All => true,
+            NoProxy::Host { host: v, port: p } => {
+                let is_host =
+                    host == v || (host.ends_with(v) && host[..host.len() - v.len()].ends_with('.'));
+
+                is_host && p.is_none_or(|p| p == port)
+            }
+            NoProxy::Cidr { addr, prefix } => match (addr, host.parse::<IpAddr>()) {
+                (IpAddr::V4(a), Ok(IpAddr::V4(b))) => {
+                    let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+
+                    u32::from(*a) & mask == u32::from(b) & mask
+                }
+                (IpAddr::V6(a), Ok(IpAddr::V6(b))) => {
+                    let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+
+                    u128::from(*a) & mask == u128::from(b) & mask
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+/// 解析 `NO_PROXY` 环境变量，无效的项会输出警告并被忽略
+fn no_proxy(env: EnvFn) -> Vec<NoProxy> {
+    let Some((key, value)) = env_var(env, &["NO_PROXY", "no_proxy"]) else {
+        return vec![];
+    };
+
+    value
+        .split(',')
+        .filter(|v| !v.trim().is_empty())
+        .filter_map(|v| {
+            let entry = NoProxy::parse(v);
+
+            if entry.is_none() {
+                to_err(Msg::IgnoredNoProxy.format(&[&v.trim(), &key]));
+            }
+
+            entry
+        })
+        .collect()
+}
+
+/// 根据 CA 证书文件创建 `TLS` 配置，`source` 是证书文件的来源，用于错误信息
+fn tls_config(path: &str, source: &str) -> CrmResult<Arc<rustls::ClientConfig>> {
+    let invalid = |e: &dyn ToString| {
+        CrmError::InvalidValue(Msg::InvalidCaInfo.format(&[&path, &source, &e.to_string()]))
+    };
+    let pem = fs::read(path).map_err(|e| invalid(&e))?;
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(&e))?;
+
+    if certs.is_empty() {
+        return Err(invalid(&Msg::NoCertificates));
+    }
+
+    let mut roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+
+    for cert in certs {
+        roots.add(cert).map_err(|e| invalid(&e))?;
+    }
+
+    client_config(roots).map_err(|e| invalid(&e))
+}
+
+/// 根据根证书创建 `TLS` 配置，与 `ureq` 默认的 `TLS` 配置保持一致
+fn client_config(roots: RootCertStore) -> Result<Arc<rustls::ClientConfig>, rustls::Error> {
+    let config = rustls::ClientConfig::builder_with_provider(
+        rustls::crypto::ring::default_provider().into(),
+    )
+    .with_protocol_versions(&[&rustls::version::TLS12, &rustls::version::TLS13])?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+/// 创建请求对象，`tls` 为 `None` 时使用 `ureq` 默认的 `TLS` 配置
+fn agent_builder(tls: &Option<Arc<rustls::ClientConfig>>) -> AgentBuilder {
+    match tls {
+        Some(tls) => AgentBuilder::new().tls_config(tls.clone()),
+        None => AgentBuilder::new(),
+    }
+}
+
+/// 根据代理地址创建请求对象，`source` 是代理地址的来源，用于错误信息
+fn proxy_agent(
+    url: &str,
+    source: &str,
+    tls: &Option<Arc<rustls::ClientConfig>>,
+) -> CrmResult<Agent> {
+    let invalid = || CrmError::InvalidValue(Msg::InvalidProxy.format(&[&url, &source]));
+    let url = url.trim();
+
+    // 没有启用 `ureq` 的 `socks-proxy` 功能，所以只支持 `http` 代理
+    if url.contains("://") && !url.to_lowercase().starts_with("http://") {
+        return Err(invalid());
+    }
+
+    let proxy = Proxy::new(url).map_err(|_| invalid())?;
+
+    Ok(agent_builder(tls).proxy(proxy).build())
+}
+
+impl Network {
+    /// 根据显式指定的代理、CA 证书和环境变量创建请求对象
+    ///
+    /// `explicit` 是通过 `--proxy` 参数、`CARGO_HTTP_PROXY` 环境变量或 `http.proxy` 字段指定的代理及其来源，
+    /// `cainfo` 是通过 `--cacert` 参数、`CARGO_HTTP_CAINFO` 环境变量或 `http.cainfo` 字段指定的 CA 证书文件及其来源。
+    fn new(
+        explicit: Option<(String, String)>,
+        cainfo: Option<(String, String)>,
+    ) -> CrmResult<Self> {
+        Network::with_env(&process_env, explicit, cainfo)
+    }
+
+    /// 使用 `env` 读取环境变量，创建请求对象
+    fn with_env(
+        env: EnvFn,
+        explicit: Option<(String, String)>,
+        cainfo: Option<(String, String)>,
+    ) -> CrmResult<Self> {
+        let tls = cainfo
+            .map(|(path, source)| tls_config(&path, &source))
+            .transpose()?;
+        let (http, https) = match explicit {
+            Some((url, source)) => {
+                let agent = proxy_agent(&url, &source, &tls)?;
+
+                (Some(agent.clone()), Some(agent))
+            }
+            None => {
+                // `ALL_PROXY` 可能同时用于 `http` 和 `https` 地址，同一个环境变量只警告一次
+                let mut ignored = vec![];
+                let mut from_env = |keys: &[&str]| {
+                    let (key, url) = env_var(env, keys)?;
+
+                    match proxy_agent(&url, &key, &tls) {
+                        Ok(agent) => Some(agent),
+                        Err(_) => {
+                            if !ignored.contains(&key) {
+                                to_err(Msg::IgnoredProxy.format(&[&url, &key]));
+                                ignored.push(key);
+                            }
+
+                            None
+                        }
+                    }
+                };
+
+                (
+                    from_env(&["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]),
+                    from_env(&["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]),
+                )
+            }
+        };
+
+        Ok(Network {
+            direct: agent_builder(&tls).build(),
+            http,
+            https,
+            no_proxy: no_proxy(env),
+            tls,
+        })
+    }
+
+    /// 判断是否应该直接访问 `url`
+    fn bypass(&self, url: &str) -> bool {
+        let (host, port) = host_port(url);
+
+        self.no_proxy.iter().any(|v| v.matches(&host, port))
+    }
+
+    /// 获取请求 `url` 时使用的代理请求对象，不需要代理时为 `None`
+    fn proxy(&self, url: &str) -> Option<&Agent> {
+        let proxied = match url.split_once("://") {
+            Some((scheme, _)) if scheme.eq_ignore_ascii_case("https") => self.https.as_ref(),
+            _ => self.http.as_ref(),
+        };
+
+        proxied.filter(|_| !self.bypass(url))
+    }
+
+    /// 获取请求 `url` 时使用的请求对象
+    fn agent(&self, url: &str) -> &Agent {
+        self.proxy(url).unwrap_or(&self.direct)
+    }
+}
+
+/// 获取 `url` 中的主机名和端口，不包括用户信息，没有端口时使用协议的默认端口
+fn host_port(url: &str) -> (String, u16) {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit_once('@').map_or(authority, |v| v.1);
+
+    // `IPv6` 地址的格式为 `[::1]:8080`
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(v) => v.split_once(']').unwrap_or((v, "")),
+        None => authority.split_once(':').unwrap_or((authority, "")),
+    };
+    let port = port.trim_start_matches(':').parse().unwrap_or(
+        match scheme.eq_ignore_ascii_case("https") {
+            true => 443,
+            false => 80,
+        },
+    );
+
+    (host.to_lowercase(), port)
+}
+
+/// 设置网络请求使用的代理和 CA 证书，只在第一次调用时生效
+///
+/// `proxy` 是显式指定的代理及其来源，为 `None` 时使用环境变量中的代理；
+/// `cainfo` 是额外的 CA 证书文件及其来源。CA 证书文件会在这里读取，所以只应该在需要访问网络的命令中调用，
+/// 其他命令不会受到无效的 `http.cainfo` 或 `CARGO_HTTP_CAINFO` 的影响。
+pub fn init(proxy: Option<(String, String)>, cainfo: Option<(String, String)>) -> CrmResult<()> {
+    if NETWORK.get().is_none() {
+        let _ = NETWORK.set(Network::new(proxy, cainfo)?);
+    }
+
+    Ok(())
+}
+
+/// 获取请求对象，如果没有调用过 `init`，则只使用环境变量中的代理
+fn network() -> &'static Network {
+    NETWORK.get_or_init(|| {
+        Network::new(None, None).unwrap_or_else(|_| Network {
+            direct: Agent::new(),
+            http: None,
+            https: None,
+            no_proxy: vec![],
+            tls: None,
+        })
+    })
+}
+
+/// 创建 `GET` 请求
+pub fn get(url: &str) -> Request {
+    network().agent(url).get(url)
+}
+
+/// 判断请求 `url` 时是否会使用代理
+pub fn is_proxied(url: &str) -> bool {
+    network().proxy(url).is_some()
+}
+
+/// 获取直接建立 `TLS` 连接时使用的配置，与请求对象信任相同的 CA 证书
+pub fn tls_client_config() -> Result<Arc<rustls::ClientConfig>, String> {
+    match &network().tls {
+        Some(tls) => Ok(tls.clone()),
+        None => client_config(RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        })
+        .map_err(|e| e.to_string()),
+    }
+}
+
+/// 在错误链中查找 `TLS` 证书验证失败的错误
+///
+/// `rustls` 的错误会被包装在 `io::Error` 中，而 `io::Error::source` 会跳过被包装的错误本身，所以需要单独处理。
+fn certificate_error<'a>(e: &'a (dyn Error + 'static)) -> Option<&'a rustls::Error> {
+    let mut current = Some(e);
+
+    while let Some(e) = current {
+        let tls = e.downcast_ref::<rustls::Error>().or_else(|| {
+            e.downcast_ref::<io::Error>()
+                .and_then(|v| v.get_ref())
+                .and_then(|v| v.downcast_ref::<rustls::Error>())
+        });
+
+        if let Some(tls @ rustls::Error::InvalidCertificate(_)) = tls {
+            return Some(tls);
+        }
+
+        current = e.source();
+    }
+
+    None
+}
+
+/// 网络请求失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetError {
+    /// `TLS` 证书验证失败，通常是缺少 CA 证书，而不是镜像不可用
+    Certificate(String),
+
+    /// 其他错误，例如连接失败或者返回的状态不是预期的
+    Other(String),
+}
+
+impl NetError {
+    /// 是否是 `TLS` 证书验证失败
+    pub fn is_certificate(&self) -> bool {
+        matches!(self, NetError::Certificate(_))
+    }
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Certificate(e) => write!(f, "{}", Msg::TlsVerifyFailed.format(&[e])),
+            NetError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for NetError {
+    fn from(e: String) -> Self {
+        NetError::Other(e)
+    }
+}
+
+/// 将传输错误转换为 `NetError`，`TLS` 证书验证失败时会给出单独的提示
+pub fn transport_error(e: Transport) -> NetError {
+    match certificate_error(&e) {
+        Some(tls) => NetError::Certificate(tls.to_string()),
+        None => NetError::Other(e.to_string()),
+    }
+}
+
+/// 将读写连接时的错误转换为 `NetError`，`TLS` 证书验证失败时会给出单独的提示
+pub fn io_error(e: &io::Error) -> NetError {
+    match certificate_error(e) {
+        Some(tls) => NetError::Certificate(tls.to_string()),
+        None => NetError::Other(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_certificate_errors() {
+        let tls = rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer);
+        let e = io_error(&io::Error::new(io::ErrorKind::InvalidData, tls.clone()));
+
+        assert_eq!(e, NetError::Certificate(tls.to_string()));
+        assert!(e.is_certificate());
+        assert_eq!(e.to_string(), Msg::TlsVerifyFailed.format(&[&tls]));
+    }
+
+    #[test]
+    fn other_errors_are_not_certificate_errors() {
+        let e = io_error(&io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+
+        assert_eq!(e, NetError::Other("refused".to_string()));
+        assert!(!e.is_certificate());
+
+        // 与证书错误的提示相同的文本也不会被当作证书错误
+        let text = NetError::Certificate("x".to_string()).to_string();
+
+        assert!(!NetError::from(text).is_certificate());
+    }
+
+    /// 根据 `vars` 创建读取环境变量的函数
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        move |key| vars.iter().find(|v| v.0 == key).map(|v| v.1.clone())
+    }
+
+    fn source(value: &str, from: &str) -> Option<(String, String)> {
+        Some((value.to_string(), from.to_string()))
+    }
+
+    #[test]
+    fn http_setting_precedence() {
+        let env = env_of(&[("CARGO_HTTP_PROXY", "http://var:1")]);
+        let config = || Ok(source("http://config:2", "http.proxy"));
+
+        assert_eq!(
+            http_setting_with(&env, "proxy", source("http://arg:3", "--proxy"), config).unwrap(),
+            source("http://arg:3", "--proxy")
+        );
+        assert_eq!(
+            http_setting_with(&env, "proxy", None, config).unwrap(),
+            source("http://var:1", "CARGO_HTTP_PROXY")
+        );
+        assert_eq!(
+            http_setting_with(&env_of(&[]), "proxy", None, config).unwrap(),
+            source("http://config:2", "http.proxy")
+        );
+        assert_eq!(
+            http_setting_with(&env_of(&[]), "proxy", None, || Ok(None)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn explicit_proxy_overrides_env() {
+        let env = env_of(&[("HTTPS_PROXY", "socks5://env:1080")]);
+        let network = Network::with_env(&env, source("http://explicit:3128", "--proxy"), None);
+
+        assert!(network.unwrap().proxy("https://example.com").is_some());
+
+        // 环境变量中不支持的代理会被忽略，而显式指定的代理无效时会返回错误
+        let network = Network::with_env(&env, None, None).unwrap();
+
+        assert!(network.proxy("https://example.com").is_none());
+        assert!(Network::with_env(&env_of(&[]), source("socks5://x:1", "--proxy"), None).is_err());
+
+        let env = env_of(&[("HTTP_PROXY", "http://env:3128")]);
+        let network = Network::with_env(&env, None, None).unwrap();
+
+        assert!(network.proxy("http://example.com").is_some());
+        assert!(network.proxy("https://example.com").is_none());
+    }
+
+    #[test]
+    fn no_proxy_matching() {
+        let env = env_of(&[
+            ("HTTPS_PROXY", "http://proxy:3128"),
+            (
+                "NO_PROXY",
+                ".example.com, *.test.org,internal:8443,10.0.0.0/8,fd00::/8,[::1],10.0.0.0/33",
+            ),
+        ]);
+        let network = Network::with_env(&env, None, None).unwrap();
+        let bypass = |url: &str| network.proxy(url).is_none();
+
+        assert!(bypass("https://example.com/x"));
+        assert!(bypass("https://api.EXAMPLE.com"));
+        assert!(!bypass("https://badexample.com"));
+        assert!(bypass("https://a.b.test.org"));
+        assert!(bypass("https://internal:8443"));
+        assert!(!bypass("https://internal"));
+        assert!(bypass("https://10.1.2.3"));
+        assert!(!bypass("https://11.1.2.3"));
+        assert!(bypass("https://[fd12::1]:8443"));
+        assert!(bypass("https://[::1]/"));
+        assert!(!bypass("https://[::2]/"));
+        assert_eq!(network.no_proxy.len(), 6);
+
+        let env = env_of(&[("HTTPS_PROXY", "http://proxy:3128"), ("no_proxy", "*")]);
+
+        assert!(Network::with_env(&env, None, None)
+            .unwrap()
+            .proxy("https://any.host")
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_no_proxy_entries() {
+        for entry in [
+            "10.0.0.0/33",
+            "::1/129",
+            "host:port",
+            "[::1",
+            "a/b",
+            "user@host",
+        ] {
+            assert_eq!(NoProxy::parse(entry), None, "{}", entry);
+        }
+
+        assert_eq!(
+            NoProxy::parse(" *.Example.com "),
+            Some(NoProxy::Host {
+                host: "example.com".to_string(),
+                port: None
+            })
+        );
+        assert_eq!(
+            NoProxy::parse("[::1]:8080"),
+            Some(NoProxy::Host {
+                host: "::1".to_string(),
+                port: Some(8080)
+            })
+        );
+    }
+
+    #[test]
+    fn extracts_host_and_port() {
+        assert_eq!(host_port("https://[::1]:8443/x"), ("::1".to_string(), 8443));
+        assert_eq!(
+            host_port("http://user:pw@[FE80::1%25eth0]/a?b"),
+            ("fe80::1%25eth0".to_string(), 80)
+        );
+        assert_eq!(
+            host_port("https://[2001:db8::1]"),
+            ("2001:db8::1".to_string(), 443)
+        );
+        assert_eq!(
+            host_port("https://Example.com:8080#x"),
+            ("example.com".to_string(), 8080)
+        );
+        assert_eq!(host_port("example.com/x"), ("example.com".to_string(), 80));
+    }
+}
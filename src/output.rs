@@ -0,0 +1,486 @@
+//! # 输出格式
+//!
+//! 默认情况下，`crm` 会输出便于阅读的文本。`list`、`current`、`test` 和 `best` 命令还可以通过
+//! `--format json` 或 `--format tsv` 参数输出结构化的数据，以便在脚本中使用。
+
+use std::{str::FromStr, time::Duration};
+
+use crate::{
+    checksum::IntegrityCheck,
+    error::CrmError,
+    git::GitCheck,
+    i18n::Msg,
+    sparse::{FreshnessCheck, IndexCheck},
+    stats::{Bandwidth, Stats},
+    timing::{Timing, TimingCheck},
+};
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// 便于阅读的文本
+    #[default]
+    Text,
+
+    /// `JSON`
+    Json,
+
+    /// 以制表符分隔的值，第一行为表头
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = CrmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(CrmError::InvalidValue(Msg::InvalidFormat.format(&[&s]))),
+        }
+    }
+}
+
+/// 字段的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// 空值
+    Null,
+
+    /// 布尔值
+    Bool(bool),
+
+    /// 数字
+    Number(u128),
+
+    /// 小数
+    Float(f64),
+
+    /// 字符串
+    String(String),
+}
+
+impl Value {
+    /// 转换为 `JSON` 值
+    fn to_json(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Bool(v) => v.to_string(),
+            Value::Number(v) => v.to_string(),
+            Value::Float(v) if v.is_finite() => v.to_string(),
+            Value::Float(_) => "null".to_string(),
+            Value::String(v) => json_string(v),
+        }
+    }
+
+    /// 转换为 `TSV` 中的单元格，制表符和换行符会被替换为空格
+    fn to_tsv(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Bool(v) => v.to_string(),
+            Value::Number(v) => v.to_string(),
+            Value::Float(v) => v.to_string(),
+            Value::String(v) => v.replace(['\t', '\r', '\n'], " "),
+        }
+    }
+}
+
+impl From<Option<String>> for Value {
+    fn from(v: Option<String>) -> Self {
+        v.map_or(Value::Null, Value::String)
+    }
+}
+
+impl From<Option<u128>> for Value {
+    fn from(v: Option<u128>) -> Self {
+        v.map_or(Value::Null, Value::Number)
+    }
+}
+
+/// 将字符串转换为 `JSON` 字符串
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// 由字段名和字段值组成的一条记录
+pub type Fields = Vec<(&'static str, Value)>;
+
+/// 镜像记录
+#[derive(Debug, Clone, Default)]
+pub struct Record {
+    /// 镜像名
+    pub name: String,
+
+    /// 镜像地址
+    pub registry: Option<String>,
+
+    /// 镜像 `dl`
+    pub dl: Option<String>,
+
+    /// 是否是内置镜像
+    pub builtin: bool,
+
+    /// 是否是当前正在使用的镜像
+    pub current: bool,
+
+    /// 是否被禁用
+    pub disabled: bool,
+
+    /// 显示名称
+    pub display_name: Option<String>,
+
+    /// 描述
+    pub description: Option<String>,
+
+    /// 地区
+    pub region: Option<String>,
+
+    /// 标签
+    pub tags: Vec<String>,
+
+    /// 主页
+    pub homepage: Option<String>,
+
+    /// 协议
+    pub protocol: Option<String>,
+
+    /// `dl` 是否由镜像代理下载
+    pub proxies_downloads: Option<bool>,
+
+    /// 优先级
+    pub priority: Option<u64>,
+
+    /// 网络连接测试的结果，没有测试时为 `None`
+    pub connect: Option<Stats>,
+
+    /// 软件包下载测试的结果，没有测试时为 `None`
+    pub download: Option<Stats>,
+
+    /// 下载速度测试的结果，没有测试时为 `None`
+    pub throughput: Option<Bandwidth>,
+
+    /// `sparse` 索引检查的结果，没有检查时为 `None`
+    pub index: Option<IndexCheck>,
+
+    /// 索引同步情况的检查结果，没有检查时为 `None`
+    pub freshness: Option<FreshnessCheck>,
+
+    /// 软件包校验的结果，没有校验时为 `None`
+    pub integrity: Option<IntegrityCheck>,
+
+    /// git 索引检查的结果，没有检查时为 `None`
+    pub git: Option<GitCheck>,
+
+    /// 分阶段计时的结果，没有计时时为 `None`
+    pub timing: Option<TimingCheck>,
+}
+
+impl Record {
+    /// 获取记录中的所有字段
+    pub fn fields(&self) -> Fields {
+        let stat = |v: &Option<Stats>, f: fn(&Stats) -> Option<u128>| v.as_ref().and_then(f).into();
+        let error = |v: &Option<Stats>| {
+            v.as_ref()
+                .and_then(|v| v.error())
+                .map(ToString::to_string)
+                .into()
+        };
+        let phase = |f: fn(&Timing) -> Option<Duration>| match &self.timing {
+            Some(Ok(v)) => f(v).map(|v| v.as_millis()).into(),
+            _ => Value::Null,
+        };
+        let rate = |v: &Option<Stats>| {
+            v.as_ref()
+                .map_or(Value::Null, |v| Value::Float(v.success_rate()))
+        };
+
+        vec![
+            ("name", Value::String(self.name.to_string())),
+            ("registry", self.registry.clone().into()),
+            ("dl", self.dl.clone().into()),
+            ("builtin", Value::Bool(self.builtin)),
+            ("current", Value::Bool(self.current)),
+            ("disabled", Value::Bool(self.disabled)),
+            ("display_name", self.display_name.clone().into()),
+            ("description", self.description.clone().into()),
+            ("region", self.region.clone().into()),
+            ("tags", Value::String(self.tags.join(", "))),
+            ("homepage", self.homepage.clone().into()),
+            ("protocol", self.protocol.clone().into()),
+            (
+                "proxies_downloads",
+                self.proxies_downloads.map_or(Value::Null, Value::Bool),
+            ),
+            ("priority", self.priority.map(u128::from).into()),
+            ("connect_ms", stat(&self.connect, Stats::median)),
+            ("connect_error", error(&self.connect)),
+            ("connect_min_ms", stat(&self.connect, Stats::min)),
+            ("connect_p90_ms", stat(&self.connect, Stats::p90)),
+            ("connect_jitter_ms", stat(&self.connect, Stats::jitter)),
+            ("connect_success_rate", rate(&self.connect)),
+            ("download_ms", stat(&self.download, Stats::median)),
+            ("download_error", error(&self.download)),
+            ("download_min_ms", stat(&self.download, Stats::min)),
+            ("download_p90_ms", stat(&self.download, Stats::p90)),
+            ("download_jitter_ms", stat(&self.download, Stats::jitter)),
+            ("download_success_rate", rate(&self.download)),
+            (
+                "throughput_mbps",
+                match &self.throughput {
+                    Some(Ok(v)) => Value::Float((v.mbps() * 100.0).round() / 100.0),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "throughput_bytes",
+                match &self.throughput {
+                    Some(Ok(v)) => Value::Number(v.bytes as u128),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "throughput_ms",
+                match &self.throughput {
+                    Some(Ok(v)) => Value::Number(v.elapsed.as_millis()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "throughput_error",
+                match &self.throughput {
+                    Some(Err(e)) => Value::String(e.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "index_api",
+                match &self.index {
+                    Some(Ok(v)) => v.api.clone().into(),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "index_versions",
+                match &self.index {
+                    Some(Ok(v)) => Value::Number(v.versions as u128),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "index_error",
+                match &self.index {
+                    Some(Err(e)) => Value::String(e.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "lag_versions",
+                match &self.freshness {
+                    Some(Ok(v)) => Value::Number(v.missing() as u128),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "freshness_error",
+                match &self.freshness {
+                    Some(Err(e)) => Value::String(e.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "verified_crates",
+                match &self.integrity {
+                    Some(Ok(v)) => Value::Number(v.checked as u128),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "checksum_mismatches",
+                match &self.integrity {
+                    Some(Ok(v)) => Value::String(
+                        v.mismatches
+                            .iter()
+                            .map(|v| format!("{}@{} ({})", v.krate, v.version, v.kind.as_str()))
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                    ),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "verify_error",
+                match &self.integrity {
+                    Some(Err(e)) => Value::String(e.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "git_head",
+                match &self.git {
+                    Some(Ok(v)) => Value::String(v.head.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "git_ms",
+                match &self.git {
+                    Some(Ok(v)) => Value::Number(v.elapsed.as_millis()),
+                    _ => Value::Null,
+                },
+            ),
+            (
+                "git_error",
+                match &self.git {
+                    Some(Err(e)) => Value::String(e.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+            ("dns_ms", phase(|v| Some(v.dns))),
+            ("tcp_ms", phase(|v| Some(v.tcp))),
+            ("tls_ms", phase(|v| v.tls)),
+            ("ttfb_ms", phase(|v| Some(v.ttfb))),
+            ("body_ms", phase(|v| Some(v.body))),
+            (
+                "timing_error",
+                match &self.timing {
+                    Some(Err(e)) => Value::String(e.to_string()),
+                    _ => Value::Null,
+                },
+            ),
+        ]
+    }
+}
+
+/// 将一条记录转换为 `JSON` 对象
+pub fn json_object(fields: &Fields) -> String {
+    let pairs: Vec<String> = fields
+        .iter()
+        .map(|(k, v)| format!("{}: {}", json_string(k), v.to_json()))
+        .collect();
+
+    format!("{{{}}}", pairs.join(", "))
+}
+
+/// 将多条记录转换为 `JSON` 数组，每条记录占一行
+pub fn json_array(records: &[Fields]) -> String {
+    if records.is_empty() {
+        return "[]".to_string();
+    }
+
+    let items: Vec<String> = records
+        .iter()
+        .map(|v| format!("  {}", json_object(v)))
+        .collect();
+
+    format!("[\n{}\n]", items.join(",\n"))
+}
+
+/// 将多条记录转换为 `TSV`，第一行为表头
+pub fn tsv(records: &[Fields]) -> String {
+    let header = match records.first() {
+        Some(first) => first.iter().map(|(k, _)| *k).collect::<Vec<&str>>(),
+        None => Record::default().fields().iter().map(|(k, _)| *k).collect(),
+    };
+    let rows = records.iter().map(|fields| {
+        fields
+            .iter()
+            .map(|(_, v)| v.to_tsv())
+            .collect::<Vec<String>>()
+            .join("\t")
+    });
+
+    std::iter::once(header.join("\t"))
+        .chain(rows)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// 按照输出格式将多条记录转换为字符串，`Format::Text` 需要由调用者自行处理
+pub fn render(format: Format, records: &[Fields]) -> String {
+    match format {
+        Format::Tsv => tsv(records),
+        _ => json_array(records),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Json;
+
+    fn string(value: &str) -> Fields {
+        vec![("name", Value::String(value.to_string()))]
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(json_string("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(json_string("\u{0}\u{1f}\u{7f}"), "\"\\u0000\\u001f\u{7f}\"");
+        assert_eq!(json_string("镜像 ☃ 😀"), "\"镜像 ☃ 😀\"");
+    }
+
+    #[test]
+    fn json_output_round_trips() {
+        let value = "\"quoted\" \\path\\ \n\t\u{1}镜像😀";
+        let json = json_array(&[string(value)]);
+
+        let Ok(Json::Array(items)) = Json::parse(&json) else {
+            panic!("invalid json: {}", json);
+        };
+
+        assert_eq!(items[0].get("name").and_then(Json::as_str), Some(value));
+    }
+
+    #[test]
+    fn writes_json_values() {
+        let fields = vec![
+            ("null", Value::Null),
+            ("bool", Value::Bool(true)),
+            ("number", Value::Number(42)),
+            ("float", Value::Float(1.5)),
+            ("nan", Value::Float(f64::NAN)),
+        ];
+
+        assert_eq!(
+            json_object(&fields),
+            r#"{"null": null, "bool": true, "number": 42, "float": 1.5, "nan": null}"#
+        );
+        assert_eq!(json_array(&[]), "[]");
+    }
+
+    #[test]
+    fn tsv_cells_stay_on_one_line() {
+        let records = [
+            vec![
+                ("name", Value::String("a\tb\r\nc".to_string())),
+                ("error", Value::Null),
+            ],
+            vec![
+                ("name", Value::String("镜像 \"x\" \\y".to_string())),
+                ("error", Value::String("line1\nline2".to_string())),
+            ],
+        ];
+
+        assert_eq!(
+            tsv(&records),
+            "name\terror\na b  c\t\n镜像 \"x\" \\y\tline1 line2"
+        );
+    }
+}
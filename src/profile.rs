@@ -0,0 +1,255 @@
+//! # 配置方案
+//!
+//! 在不同的网络环境中 (例如公司、家里和 CI)，除了镜像之外，往往还需要修改 `Cargo` 配置文件中的 `[http]`、`[net]` 等字段。
+//! 配置方案将一个镜像和一组 `Cargo` 配置字段组合在一起，定义在 `.crmrc` 文件中:
+//!
+//! ```toml
+//! [profile.office]
+//! registry = "company"
+//!
+//! [profile.office.config.http]
+//! proxy = "http://proxy.example.com:3128"
+//!
+//! [profile.office.config.net]
+//! git-fetch-with-cli = true
+//! ```
+//!
+//! `crm profile use <name>` 会先撤销上一次应用到同一个配置文件的配置方案，再切换镜像并写入新的字段，
+//! 所有的修改都会一次性写入到配置文件中。每个配置文件最后一次应用的配置方案，以及被覆盖的字段原来的值，
+//! 都记录在 `${HOME}/.crm/profiles.toml` 文件中，以便之后撤销。
+
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use toml_edit::{table, value, Item, Table, TableLike};
+
+use crate::{
+    constants::{HTTP, NET, PROFILES, REGISTRIES, SOURCE},
+    error::{CrmError, CrmResult},
+    i18n::Msg,
+    lock::FileLock,
+    toml::Toml,
+    utils::crm_dir,
+};
+
+/// `.crmrc` 文件中的 `[profile]` 属性
+pub const PROFILE: &str = "profile";
+
+/// `[profile.xxx]` 属性下面的 `config` 属性，结构与 `Cargo` 配置文件相同
+pub const CONFIG: &str = "config";
+
+/// 配置方案不能修改的 `Cargo` 配置字段，它们由切换镜像的逻辑管理
+pub const RESERVED_KEYS: &[&str] = &[SOURCE, REGISTRIES];
+
+/// `crm profile save` 保存的 `Cargo` 配置字段
+pub const SAVED_KEYS: &[&str] = &[HTTP, NET];
+
+/// 记录文件中的 `profile` 属性
+const APPLIED_PROFILE: &str = "profile";
+
+/// 记录文件中的 `previous` 属性
+const PREVIOUS: &str = "previous";
+
+/// 配置方案
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// 使用的镜像
+    pub registry: String,
+
+    /// 要写入 `Cargo` 配置文件的字段
+    pub config: Table,
+}
+
+impl Profile {
+    /// 创建一个配置方案
+    pub fn new(registry: String, config: Table) -> Self {
+        Profile { registry, config }
+    }
+
+    /// 获取所有要写入的字段的路径，例如 `http.proxy`
+    pub fn keys(&self) -> Vec<String> {
+        leaves(&self.config)
+            .into_iter()
+            .map(|(path, _)| path.join("."))
+            .collect()
+    }
+}
+
+/// 已经应用到某个 `Cargo` 配置文件中的配置方案
+#[derive(Debug, Clone, Default)]
+pub struct Applied {
+    /// 配置方案的名称
+    pub profile: String,
+
+    /// 写入的字段
+    pub config: Table,
+
+    /// 写入之前这些字段的值，原来不存在的字段不包含在内
+    pub previous: Table,
+}
+
+/// 获取表中所有不是表的字段及其路径
+pub fn leaves(table: &dyn TableLike) -> Vec<(Vec<String>, Item)> {
+    let mut ret = vec![];
+
+    for (key, item) in table.iter() {
+        match item.as_table_like() {
+            Some(child) => ret.extend(leaves(child).into_iter().map(|(mut path, item)| {
+                path.insert(0, key.to_string());
+                (path, item)
+            })),
+            None => ret.push((vec![key.to_string()], item.clone())),
+        }
+    }
+
+    ret
+}
+
+/// 根据表中所有不是表的字段重新创建一个表，以免保留原来的表在文件中的位置和格式
+pub fn rebuild(table: &Table) -> Table {
+    let mut root = Item::Table(Table::new());
+
+    for (path, item) in leaves(table) {
+        let _ = set_path(&mut root, &path, item);
+    }
+
+    let mut table = root.into_table().unwrap_or_default();
+    table.set_implicit(true);
+    table
+}
+
+/// 获取 `root` 中 `path` 对应的字段
+pub fn get_path<'a>(root: &'a Item, path: &[String]) -> Option<&'a Item> {
+    path.iter().try_fold(root, |item, key| item.get(key))
+}
+
+/// 将 `root` 中 `path` 对应的字段设置为 `item`，缺少的表会自动创建
+///
+/// 如果路径中的某一级已经存在但不是表，则返回这一级的路径。
+pub fn set_path(root: &mut Item, path: &[String], item: Item) -> Result<(), String> {
+    let Some((last, parents)) = path.split_last() else {
+        return Ok(());
+    };
+    let mut current = root;
+
+    for (i, key) in parents.iter().enumerate() {
+        if current.get(key).is_none() {
+            current[key.as_str()] = table();
+        }
+
+        current = &mut current[key.as_str()];
+
+        if !current.is_table_like() {
+            return Err(path[..=i].join("."));
+        }
+    }
+
+    current[last.as_str()] = item;
+
+    Ok(())
+}
+
+/// 删除 `root` 中 `path` 对应的字段
+pub fn remove_path(root: &mut Item, path: &[String]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let parent = parents
+        .iter()
+        .try_fold(root, |item, key| item.get_mut(key.as_str()));
+
+    if let Some(table) = parent.and_then(Item::as_table_like_mut) {
+        table.remove(last);
+    }
+}
+
+/// 判断两个字段的值是否相同，忽略空白和注释
+pub fn same_value(a: &Item, b: &Item) -> bool {
+    let normalize = |item: &Item| match item.as_value() {
+        Some(v) => {
+            let mut v = v.clone();
+            v.decor_mut().clear();
+            v.to_string()
+        }
+        None => item.to_string(),
+    };
+
+    normalize(a) == normalize(b)
+}
+
+/// 获取记录文件的路径
+fn state_path() -> CrmResult<PathBuf> {
+    Ok(crm_dir()?.join(PROFILES))
+}
+
+/// 读取记录文件，文件不存在时返回空文档
+///
+/// 记录文件无法解析时返回错误，而不是当作空文档覆盖它，否则会丢失上一次应用的配置方案，无法再撤销它的配置。
+fn read_state(path: &Path) -> CrmResult<Toml> {
+    Toml::parse(&read_to_string(path).unwrap_or_default())
+        .map_err(|_| CrmError::ConfigParse(path.to_path_buf()))
+}
+
+/// 获取最后一次应用到 `config_path` 的配置方案
+pub fn applied(config_path: &Path) -> CrmResult<Option<Applied>> {
+    let path = state_path()?;
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let _lock = FileLock::acquire(&path)?;
+    let state = read_state(&path)?;
+    let key = config_path.display().to_string();
+    let Some(entry) = state.table().get(&key).and_then(Item::as_table) else {
+        return Ok(None);
+    };
+    let config = |field: &str| {
+        entry
+            .get(field)
+            .and_then(Item::as_table)
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    Ok(entry
+        .get(APPLIED_PROFILE)
+        .and_then(Item::as_str)
+        .map(|profile| Applied {
+            profile: profile.to_string(),
+            config: config(CONFIG),
+            previous: config(PREVIOUS),
+        }))
+}
+
+/// 记录最后一次应用到 `config_path` 的配置方案，为 `None` 时删除记录
+pub fn set_applied(config_path: &Path, applied: Option<&Applied>) -> CrmResult<()> {
+    let path = state_path()?;
+    let _lock = FileLock::acquire(&path)?;
+    let mut state = read_state(&path)?;
+    let key = config_path.display().to_string();
+    let data = state.table_mut();
+
+    match applied {
+        Some(applied) => {
+            let mut entry = Table::new();
+
+            entry[APPLIED_PROFILE] = value(applied.profile.to_string());
+            entry[CONFIG] = Item::Table(rebuild(&applied.config));
+            entry[PREVIOUS] = Item::Table(rebuild(&applied.previous));
+            data[&key] = Item::Table(entry);
+        }
+        None => {
+            data.remove(&key);
+        }
+    }
+
+    state.write(&path)
+}
+
+/// 创建配置方案中的字段不能修改的错误
+pub fn reserved_error(name: &str, key: &str) -> CrmError {
+    CrmError::InvalidValue(Msg::ProfileReservedKey.format(&[&name, &key]))
+}
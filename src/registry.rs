@@ -2,210 +2,1649 @@
 //!
 //! 该模块用于操作镜像。包括简单的增删改查操作。
 
-use std::{collections::HashSet, process};
+use std::{
+    cmp::Reverse, collections::HashMap, env, fs::read_to_string, path::PathBuf, time::Duration,
+};
+
+use crate::{
+    backup::{restore, snapshots, Snapshot},
+    cargo::{CargoConfig, Scope},
+    checksum::{sha256, Integrity, IntegrityCheck, Mismatch, MismatchKind},
+    constants::{
+        APP_NAME, APP_VERSION, CARGO, DEFAULT_BENCH_CRATES, DEFAULT_BENCH_MAX_BYTES,
+        DEFAULT_BENCH_MAX_TIME, DEFAULT_FRESHNESS_CRATES, DEFAULT_JOBS, DEFAULT_PROBE_CRATE,
+        DEFAULT_REPORT_DAYS, DEFAULT_TIMEOUT, DEFAULT_VERIFY_CRATES, DL, HISTORY_MAX_AGE_HOURS,
+        HTTP, OFFICIAL_SPARSE_INDEX, RUST_LANG,
+    },
+    diff::unified_diff,
+    error::{CrmError, CrmResult, Operation},
+    filter::RegistryFilter,
+    git::{probe_git, GitCheck},
+    history::{self, delay_samples, now, throughput_samples, trend, Context, Kind, DAY_MILLIS},
+    i18n::Msg,
+    output::{json_object, render, Fields, Format, Record, Value},
+    profile::{self, Applied, Profile, SAVED_KEYS},
+    runtime::RuntimeConfig,
+    sparse::{
+        freshness, index_cksum, index_root, probe, upstream_versions, FreshnessCheck, IndexCheck,
+    },
+    stats::{Bandwidth, Rank, Stats, Throughput},
+    timing::{measure, TimingCheck},
+    utils::{
+        append_end_spaces, cargo_config_path, crate_prefix, download, exec_command,
+        format_timestamp, is_registry_addr, is_registry_dl, is_registry_name,
+        local_cargo_config_paths, network_delay, run_bounded, status_prefix, throughput, to_out,
+        Budget, Delay, ProbeLimits,
+    },
+};
+
+/// 镜像对象
+pub struct Registry {
+    /// 运行时配置
+    rc: RuntimeConfig,
+
+    /// 要修改的 `Cargo` 配置
+    cargo: CargoConfig,
+
+    /// 是否只输出配置文件的变化，而不写入到文件中
+    dry_run: bool,
+
+    /// 输出格式
+    format: Format,
+
+    /// 每次网络请求的超时时间
+    timeout: Duration,
+
+    /// 对每个镜像进行测试的次数
+    samples: usize,
+
+    /// 对镜像进行排名的依据
+    rank: Rank,
+
+    /// 测试下载速度时下载的软件包及其版本
+    bench_crates: Vec<(String, String)>,
+
+    /// 校验软件包时下载的软件包及其版本
+    verify_crates: Vec<(String, String)>,
+
+    /// 测试下载速度时每个镜像最多下载的字节数
+    max_bytes: u64,
+
+    /// 测试下载速度时每个镜像最多花费的时间
+    max_time: Duration,
+
+    /// 检查 `sparse` 索引时请求的软件包
+    probe_crate: String,
+
+    /// 检查索引的同步情况时比较的软件包
+    freshness_crates: Vec<String>,
+
+    /// `best` 允许镜像索引落后的最大版本数，为 `None` 时不检查
+    max_lag: Option<usize>,
+
+    /// `best` 是否排除软件包校验失败的镜像
+    verify: bool,
+
+    /// 同时测试的镜像的最大数量
+    jobs: usize,
+
+    /// 所有测试的总时限
+    deadline: Option<Duration>,
+
+    /// `test` 是否分阶段计时
+    detailed: bool,
+
+    /// 测试记录的标签
+    label: Option<String>,
+
+    /// `best` 是否根据最近的测试记录选择镜像
+    from_history: bool,
+
+    /// `report` 统计的天数
+    days: u64,
+
+    /// `list` 是否输出镜像的详细信息
+    verbose: bool,
+
+    /// 只使用地区或标签与之相同的镜像
+    tag: Option<String>,
+
+    /// `best` 使用的筛选条件
+    filter: RegistryFilter,
+}
+
+/// 将常量中的软件包列表转换为 `Vec`
+fn to_crate_list(crates: &[(&str, &str)]) -> Vec<(String, String)> {
+    crates
+        .iter()
+        .map(|(krate, version)| (krate.to_string(), version.to_string()))
+        .collect()
+}
+
+/// 解析 `name@version` 格式的软件包列表，多个软件包之间用逗号分隔
+fn parse_crate_list(crates: &str) -> CrmResult<Vec<(String, String)>> {
+    let list: Vec<(String, String)> = crates
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| match v.split_once('@') {
+            Some((krate, version)) if is_crate_name(krate) && !version.is_empty() => {
+                Ok((krate.to_string(), version.to_string()))
+            }
+            _ => Err(CrmError::InvalidValue(Msg::InvalidCrate.format(&[&v]))),
+        })
+        .collect::<CrmResult<_>>()?;
+
+    if list.is_empty() {
+        return Err(CrmError::InvalidValue(Msg::InvalidCrate.format(&[&crates])));
+    }
+
+    Ok(list)
+}
+
+/// 判断是否是有效的软件包名
+fn is_crate_name(krate: &str) -> bool {
+    !krate.is_empty()
+        && krate
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Registry {
+    /// 创建镜像对象
+    ///
+    /// `config_path` 是切换镜像时要修改的 `Cargo` 配置文件的路径。
+    pub fn new(config_path: PathBuf) -> CrmResult<Self> {
+        Ok(Registry {
+            rc: RuntimeConfig::new()?,
+            cargo: CargoConfig::new(config_path)?,
+            dry_run: false,
+            format: Format::Text,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            samples: 1,
+            rank: Rank::Latency,
+            bench_crates: to_crate_list(DEFAULT_BENCH_CRATES),
+            verify_crates: to_crate_list(DEFAULT_VERIFY_CRATES),
+            max_bytes: DEFAULT_BENCH_MAX_BYTES,
+            max_time: Duration::from_secs(DEFAULT_BENCH_MAX_TIME),
+            probe_crate: DEFAULT_PROBE_CRATE.to_string(),
+            freshness_crates: DEFAULT_FRESHNESS_CRATES
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+            max_lag: None,
+            verify: false,
+            jobs: DEFAULT_JOBS,
+            deadline: None,
+            detailed: false,
+            label: None,
+            from_history: false,
+            days: DEFAULT_REPORT_DAYS,
+            verbose: false,
+            tag: None,
+            filter: RegistryFilter::default(),
+        })
+    }
+
+    /// 设置每次网络请求的超时时间
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// 设置对每个镜像进行测试的次数
+    pub fn set_samples(&mut self, samples: usize) {
+        self.samples = samples.max(1);
+    }
+
+    /// 设置对镜像进行排名的依据
+    pub fn set_rank(&mut self, rank: Rank) {
+        self.rank = rank;
+    }
+
+    /// 设置测试下载速度时下载的软件包，格式为 `name@version`，多个软件包之间用逗号分隔
+    pub fn set_bench_crates(&mut self, crates: &str) -> CrmResult<()> {
+        self.bench_crates = parse_crate_list(crates)?;
+
+        Ok(())
+    }
+
+    /// 设置校验软件包时下载的软件包，格式与 `set_bench_crates` 相同
+    pub fn set_verify_crates(&mut self, crates: &str) -> CrmResult<()> {
+        self.verify_crates = parse_crate_list(crates)?;
+
+        Ok(())
+    }
+
+    /// 设置测试下载速度时每个镜像最多下载的字节数和最多花费的时间
+    pub fn set_bench_limits(&mut self, max_bytes: Option<u64>, max_time: Option<Duration>) {
+        self.max_bytes = max_bytes.unwrap_or(self.max_bytes);
+        self.max_time = max_time.unwrap_or(self.max_time);
+    }
+
+    /// 设置检查 `sparse` 索引时请求的软件包
+    pub fn set_probe_crate(&mut self, krate: &str) -> CrmResult<()> {
+        if !is_crate_name(krate) {
+            return Err(CrmError::InvalidValue(
+                Msg::InvalidCrateName.format(&[&krate]),
+            ));
+        }
+
+        self.probe_crate = krate.to_string();
+
+        Ok(())
+    }
+
+    /// 设置检查索引的同步情况时比较的软件包，多个软件包之间用逗号分隔
+    pub fn set_freshness_crates(&mut self, crates: &str) -> CrmResult<()> {
+        self.freshness_crates = crates
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| match is_crate_name(v) {
+                true => Ok(v.to_string()),
+                false => Err(CrmError::InvalidValue(Msg::InvalidCrateName.format(&[&v]))),
+            })
+            .collect::<CrmResult<_>>()?;
+
+        if self.freshness_crates.is_empty() {
+            return Err(CrmError::InvalidValue(
+                Msg::InvalidCrateName.format(&[&crates]),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 设置 `best` 允许镜像索引落后的最大版本数
+    pub fn set_max_lag(&mut self, max_lag: usize) {
+        self.max_lag = Some(max_lag);
+    }
+
+    /// 设置 `best` 是否排除软件包校验失败的镜像
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// 设置同时测试的镜像的最大数量
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs.max(1);
+    }
+
+    /// 设置所有测试的总时限
+    pub fn set_deadline(&mut self, deadline: Duration) {
+        self.deadline = Some(deadline);
+    }
+
+    /// 设置 `test` 是否分阶段计时
+    pub fn set_detailed(&mut self, detailed: bool) {
+        self.detailed = detailed;
+    }
+
+    /// 设置测试记录的标签
+    pub fn set_label(&mut self, label: &str) -> CrmResult<()> {
+        let label = label.trim();
+
+        if label.is_empty() || label.contains(['\t', '\r', '\n']) {
+            return Err(CrmError::InvalidValue(Msg::InvalidLabel.format(&[&label])));
+        }
+
+        self.label = Some(label.to_string());
+
+        Ok(())
+    }
+
+    /// 设置 `best` 是否根据最近的测试记录选择镜像
+    pub fn set_from_history(&mut self, from_history: bool) {
+        self.from_history = from_history;
+    }
+
+    /// 设置 `report` 统计的天数
+    pub fn set_days(&mut self, days: u64) {
+        self.days = days;
+    }
+
+    /// 设置 `list` 是否输出镜像的详细信息
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// 设置只使用地区或标签与之相同的镜像
+    pub fn set_tag(&mut self, tag: &str) {
+        self.tag = Some(tag.trim().to_string());
+    }
+
+    /// 添加 `best` 使用的筛选条件，条件无效时返回错误
+    pub fn add_filter(&mut self, expr: &str) -> CrmResult<()> {
+        self.filter.add_expr(expr)
+    }
+
+    /// 将测试结果追加到测试记录中，只输出变化而不写入文件时不会记录
+    fn remember(&self, command: &str, kind: Kind, results: Vec<(String, Delay)>) -> CrmResult<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
+        history::append(
+            &Context::current(self.label.clone()),
+            command,
+            kind,
+            results,
+        )
+    }
+
+    /// 获取网络测试的限制
+    fn probe_limits(&self) -> ProbeLimits {
+        ProbeLimits {
+            timeout: self.timeout,
+            samples: self.samples,
+            jobs: self.jobs,
+            deadline: self.deadline,
+        }
+    }
+
+    /// 在有界的线程池中同时检查 `names` 中的每个镜像，没有在总时限内完成检查的镜像记为失败
+    fn check_all<N, T, F>(&self, names: &[N], check: F) -> Vec<(String, Result<T, String>)>
+    where
+        N: AsRef<str> + Sync,
+        T: Send,
+        F: Fn(&str, &Budget) -> Result<T, String> + Sync,
+    {
+        let checked = run_bounded(
+            names,
+            self.probe_limits(),
+            |name, budget| check(name.as_ref(), budget),
+            |_| false,
+        );
+
+        names
+            .iter()
+            .zip(checked)
+            .map(|(name, check)| {
+                let check = check.unwrap_or_else(|| Err(Msg::DeadlineExceeded.to_string()));
+
+                (name.as_ref().to_string(), check)
+            })
+            .collect()
+    }
+
+    /// 获取 `best` 需要等待的测试结果的数量
+    ///
+    /// 只测试一次时，最先返回的结果就是最快的镜像；测试多次时，需要等待所有的结果，然后根据分数进行排名。
+    fn best_sender_size(&self) -> Option<usize> {
+        (self.samples == 1).then_some(1)
+    }
+
+    /// 设置输出格式
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
+
+    /// 设置是否只输出配置文件的变化，而不写入到文件中
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// 输出配置文件的变化
+    fn print_diff(diff: String) {
+        if diff.is_empty() {
+            to_out(Msg::NoChanges);
+        } else {
+            println!("{}", diff.trim_end());
+        }
+    }
+
+    /// 修改 `Cargo` 配置之前加锁，并重新读取配置文件，只输出配置文件的变化时不需要加锁
+    fn lock_cargo(&mut self) -> CrmResult<()> {
+        match self.dry_run {
+            true => Ok(()),
+            false => self.cargo.lock(),
+        }
+    }
+
+    /// 修改运行时配置之前加锁，并重新读取运行时配置，只输出配置文件的变化时不需要加锁
+    fn lock_rc(&mut self) -> CrmResult<()> {
+        match self.dry_run {
+            true => Ok(()),
+            false => self.rc.lock(),
+        }
+    }
+
+    /// 将 `Cargo` 配置写入到文件中
+    fn make_cargo(&mut self) -> CrmResult<()> {
+        if self.dry_run {
+            Registry::print_diff(self.cargo.diff()?);
+            return Ok(());
+        }
+
+        self.cargo.make()
+    }
+
+    /// 将运行时配置写入到文件中
+    fn write_rc(&mut self) -> CrmResult<()> {
+        if self.dry_run {
+            Registry::print_diff(self.rc.diff());
+            return Ok(());
+        }
+
+        self.rc.write()
+    }
+
+    /// 切换镜像，`name` 也可以是镜像的别名
+    pub fn select(&mut self, name: Option<&String>) -> CrmResult<()> {
+        self.lock_cargo()?;
+
+        let name = &self.rc.resolve(is_registry_name(name)?.trim());
+
+        // 收集需要添加 `[registries.xxx]` 属性的镜像元祖数组
+        let remaining_registries = self.rc.to_tuples(None);
+
+        let registry_description = self.rc.get(name).ok_or_else(|| CrmError::UnknownRegistry {
+            name: name.to_string(),
+            operation: Operation::Use,
+            candidates: self.rc.registry_names(),
+        })?;
+
+        if self.rc.is_disabled(name) {
+            return Err(CrmError::InvalidValue(Msg::UseDisabled.format(&[&name])));
+        }
+
+        self.cargo
+            .use_registry(name, registry_description, remaining_registries)?;
+        self.cargo.remove_registries(&self.rc.disabled_names());
+        self.make_cargo()
+    }
+
+    /// 删除镜像，`name` 也可以是镜像的别名
+    pub fn remove(&mut self, name: Option<&String>) -> CrmResult<()> {
+        self.lock_rc()?;
+
+        let name = &self.rc.resolve(is_registry_name(name)?.trim());
+
+        if self.rc.get_default(name).is_some() {
+            return Err(CrmError::BuiltinRemoval(name.to_string()));
+        }
+
+        if self.rc.get_extend(name).is_none() {
+            return Err(CrmError::UnknownRegistry {
+                name: name.to_string(),
+                operation: Operation::Remove,
+                candidates: vec![],
+            });
+        }
+
+        self.rc.remove(name);
+        self.write_rc()
+    }
+
+    /// 重命名用户自定义的镜像
+    ///
+    /// 指向该镜像的别名和配置方案会同时更新，如果要修改的配置文件正在使用该镜像，也会更新其中的 `replace-with` 属性。
+    pub fn rename(&mut self, old: Option<&String>, new: Option<&String>) -> CrmResult<()> {
+        self.lock_rc()?;
+        self.lock_cargo()?;
+
+        let old = self.rc.resolve(is_registry_name(old)?.trim());
+        let new = is_registry_name(new)?.trim();
+
+        self.rc.rename(&old, new)?;
+
+        let addr = self
+            .rc
+            .get(new)
+            .map(|v| v.registry.to_string())
+            .unwrap_or_default();
+
+        let changed = self.cargo.rename_registry(&old, new, &addr)?;
+
+        self.write_rc()?;
+
+        match changed {
+            true => self.make_cargo(),
+            false => Ok(()),
+        }
+    }
+
+    /// 管理镜像的别名，`action` 为 `list`、`save` 或 `remove`
+    pub fn alias(
+        &mut self,
+        action: Option<&String>,
+        alias: Option<&String>,
+        name: Option<&String>,
+    ) -> CrmResult<()> {
+        match action.map(|v| v.to_lowercase()).as_deref() {
+            Some("list") => {
+                println!("{}", self.list_aliases());
+                Ok(())
+            }
+            Some("save") => {
+                self.lock_rc()?;
+
+                let alias = is_registry_name(alias)?.trim();
+                let name = is_registry_name(name)?.trim();
+
+                self.rc.save_alias(alias, name)?;
+                self.write_rc()
+            }
+            Some("remove") => {
+                self.lock_rc()?;
+
+                let alias = is_registry_name(alias)?.trim();
+
+                if !self.rc.aliases().contains_key(alias) {
+                    return Err(CrmError::InvalidValue(Msg::UnknownAlias.format(&[&alias])));
+                }
+
+                self.rc.remove_alias(alias);
+                self.write_rc()
+            }
+            _ => Err(CrmError::InvalidValue(Msg::InvalidAliasAction.to_string())),
+        }
+    }
+
+    /// 获取别名列表
+    fn list_aliases(&self) -> String {
+        let aliases = self.rc.aliases();
+
+        if self.format != Format::Text {
+            let records: Vec<Fields> = aliases
+                .iter()
+                .map(|(alias, name)| {
+                    vec![
+                        ("alias", Value::String(alias.to_string())),
+                        ("name", Value::String(name.to_string())),
+                    ]
+                })
+                .collect();
+
+            return render(self.format, &records);
+        }
+
+        if aliases.is_empty() {
+            return Msg::NoAliases.to_string();
+        }
+
+        aliases
+            .iter()
+            .map(|(alias, name)| format!("    {}-> {}", append_end_spaces(alias, None), name))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// 禁用或启用镜像，被禁用的镜像不会出现在镜像列表、测试和 `best` 中，也不会被写入 `[registries.xxx]` 属性
+    pub fn set_registry_disabled(
+        &mut self,
+        name: Option<&String>,
+        disabled: bool,
+    ) -> CrmResult<()> {
+        self.lock_rc()?;
+
+        let name = &self.rc.resolve(is_registry_name(name)?.trim());
+
+        // 被禁用的镜像也可以重新启用，所以候选项中包含被禁用的镜像
+        if self.rc.get(name).is_none() {
+            return Err(CrmError::UnknownRegistry {
+                name: name.to_string(),
+                operation: Operation::Use,
+                candidates: self.rc.all_registry_names(),
+            });
+        }
+
+        if disabled && name == RUST_LANG {
+            return Err(CrmError::InvalidValue(
+                Msg::DisableOfficial.format(&[&name]),
+            ));
+        }
+
+        // 检查的是这个命令要修改的配置文件，而不是当前目录中生效的配置文件
+        if disabled && self.cargo.current()?.0 == *name {
+            return Err(CrmError::InvalidValue(Msg::DisableCurrent.format(&[&name])));
+        }
+
+        self.rc.set_disabled(name, disabled);
+        self.write_rc()
+    }
+
+    /// 设置是否包含被禁用的镜像
+    pub fn set_all(&mut self, all: bool) {
+        self.rc.set_include_disabled(all);
+    }
+
+    /// 添加/更新镜像
+    pub fn save(
+        &mut self,
+        name: Option<&String>,
+        addr: Option<&String>,
+        dl: Option<&String>,
+    ) -> CrmResult<()> {
+        let name = is_registry_name(name)?.trim();
+        let addr = is_registry_addr(addr)?.trim();
+        let dl = is_registry_dl(dl)?.trim();
+
+        self.lock_rc()?;
+
+        self.rc.save(name, addr, dl);
+        self.write_rc()
+    }
+
+    /// 将别名转换为镜像名，不是别名时原样返回
+    pub fn resolve(&self, name: &str) -> String {
+        self.rc.resolve(name.trim())
+    }
+
+    /// 管理配置方案，`action` 为 `use`、`reset`、`list`、`save` 或 `remove`
+    pub fn profile(
+        &mut self,
+        action: Option<&String>,
+        name: Option<&String>,
+        registry: Option<&String>,
+    ) -> CrmResult<()> {
+        match action.map(|v| v.to_lowercase()).as_deref() {
+            Some("list") => {
+                println!("{}", self.list_profiles()?);
+                Ok(())
+            }
+            Some("use") => self.use_profile(is_registry_name(name)?.trim()),
+            Some("reset") => self.reset_profile(),
+            Some("save") => self.save_profile(is_registry_name(name)?.trim(), registry),
+            Some("remove") => self.remove_profile(is_registry_name(name)?.trim()),
+            _ => Err(CrmError::InvalidValue(
+                Msg::InvalidProfileAction.to_string(),
+            )),
+        }
+    }
+
+    /// 获取配置方案列表，并标记最后一次应用到要修改的配置文件的配置方案
+    fn list_profiles(&self) -> CrmResult<String> {
+        let active = profile::applied(self.cargo.path())?
+            .map(|v| v.profile)
+            .unwrap_or_default();
+        let names = self.rc.profile_names();
+
+        if self.format != Format::Text {
+            let records: Vec<Fields> = names
+                .iter()
+                .filter_map(|name| {
+                    let profile = self.rc.get_profile(name)?;
+
+                    Some(vec![
+                        ("name", Value::String(name.to_string())),
+                        ("registry", Value::String(profile.registry.to_string())),
+                        ("config", Value::String(profile.keys().join(", "))),
+                        ("active", Value::Bool(*name == active)),
+                    ])
+                })
+                .collect();
+
+            return Ok(render(self.format, &records));
+        }
+
+        if names.is_empty() {
+            return Ok(Msg::NoProfiles.to_string());
+        }
+
+        let lines: Vec<String> = names
+            .iter()
+            .filter_map(|name| {
+                let profile = self.rc.get_profile(name)?;
+                let keys = profile.keys();
+                let keys = match keys.is_empty() {
+                    true => String::new(),
+                    false => format!(" ({})", keys.join(", ")),
+                };
+
+                Some(format!(
+                    "{}{}- {}{}",
+                    status_prefix(name, &active),
+                    append_end_spaces(name, None),
+                    profile.registry,
+                    keys
+                ))
+            })
+            .collect();
+
+        Ok(lines.join("\n"))
+    }
+
+    /// 应用配置方案
+    ///
+    /// 先撤销上一次应用到同一个配置文件的配置方案，再切换镜像并写入配置方案中的字段，所有的修改一次性写入到配置文件中。
+    fn use_profile(&mut self, name: &str) -> CrmResult<()> {
+        self.lock_cargo()?;
+
+        let profile = self
+            .rc
+            .get_profile(name)
+            .cloned()
+            .ok_or_else(|| CrmError::InvalidValue(Msg::UnknownProfile.format(&[&name])))?;
+        let registry = self.rc.resolve(&profile.registry);
+        let registry_description =
+            self.rc
+                .get(&registry)
+                .ok_or_else(|| CrmError::UnknownRegistry {
+                    name: registry.to_string(),
+                    operation: Operation::Use,
+                    candidates: self.rc.registry_names(),
+                })?;
+
+        if self.rc.is_disabled(&registry) {
+            return Err(CrmError::InvalidValue(
+                Msg::UseDisabled.format(&[&registry]),
+            ));
+        }
+
+        if let Some(applied) = profile::applied(self.cargo.path())? {
+            self.cargo
+                .revert_settings(&applied.config, &applied.previous);
+        }
+
+        self.cargo
+            .use_registry(&registry, registry_description, self.rc.to_tuples(None))?;
+        self.cargo.remove_registries(&self.rc.disabled_names());
+
+        let previous = self.cargo.apply_settings(name, &profile.config)?;
+
+        if self.dry_run {
+            return self.make_cargo();
+        }
+
+        // 记录写入失败时恢复配置文件，以免写入的字段之后无法撤销
+        let path = self.cargo.path().to_path_buf();
+        let applied = Applied {
+            profile: name.to_string(),
+            config: profile.config,
+            previous,
+        };
+
+        self.cargo
+            .make_with(|| profile::set_applied(&path, Some(&applied)))
+    }
+
+    /// 撤销最后一次应用到要修改的配置文件的配置方案写入的字段，镜像保持不变
+    fn reset_profile(&mut self) -> CrmResult<()> {
+        self.lock_cargo()?;
+
+        let Some(applied) = profile::applied(self.cargo.path())? else {
+            to_out(Msg::NoAppliedProfile);
+            return Ok(());
+        };
+
+        self.cargo
+            .revert_settings(&applied.config, &applied.previous);
+
+        if self.dry_run {
+            return self.make_cargo();
+        }
+
+        let path = self.cargo.path().to_path_buf();
+
+        self.cargo.make_with(|| profile::set_applied(&path, None))?;
+        to_out(Msg::ProfileReset.format(&[&applied.profile]));
+
+        Ok(())
+    }
+
+    /// 将要修改的配置文件中正在使用的镜像和 `[http]`、`[net]` 字段保存为配置方案，`registry` 用于指定其他的镜像
+    fn save_profile(&mut self, name: &str, registry: Option<&String>) -> CrmResult<()> {
+        self.lock_rc()?;
+
+        let registry = match registry {
+            Some(registry) => {
+                let registry = self.rc.resolve(registry.trim());
+
+                if self.rc.get(&registry).is_none() {
+                    return Err(CrmError::UnknownRegistry {
+                        name: registry.to_string(),
+                        operation: Operation::Use,
+                        candidates: self.rc.registry_names(),
+                    });
+                }
+
+                registry
+            }
+            None => self.cargo.current()?.0,
+        };
+
+        self.rc.save_profile(
+            name,
+            Profile::new(registry, self.cargo.settings(SAVED_KEYS)),
+        );
+        self.write_rc()
+    }
+
+    /// 删除配置方案
+    fn remove_profile(&mut self, name: &str) -> CrmResult<()> {
+        self.lock_rc()?;
+
+        if self.rc.get_profile(name).is_none() {
+            return Err(CrmError::InvalidValue(Msg::UnknownProfile.format(&[&name])));
+        }
+
+        self.rc.remove_profile(name);
+        self.write_rc()
+    }
+
+    /// 获取镜像的记录
+    fn record(&self, name: &str, current: &str) -> Record {
+        let description = self.rc.get(name);
+
+        Record {
+            name: name.to_string(),
+            registry: description.map(|v| v.registry.to_string()),
+            dl: description.map(|v| v.dl.to_string()),
+            display_name: description.and_then(|v| v.display_name.clone()),
+            description: description.and_then(|v| v.description.clone()),
+            region: description.and_then(|v| v.region.clone()),
+            tags: description.map(|v| v.tags.clone()).unwrap_or_default(),
+            homepage: description.and_then(|v| v.homepage.clone()),
+            protocol: description.map(|v| v.protocol().to_string()),
+            proxies_downloads: description.and_then(|v| v.proxies_downloads),
+            priority: description.and_then(|v| v.priority),
+            builtin: self.rc.get_extend(name).is_none() && self.rc.get_default(name).is_some(),
+            current: name.eq(current),
+            disabled: self.rc.is_disabled(name),
+            ..Default::default()
+        }
+    }
+
+    /// 获取镜像列表
+    pub fn list(&self, current: &String) -> String {
+        let names: Vec<String> = self
+            .rc
+            .registry_names()
+            .into_iter()
+            .filter(|name| self.matches_tag(name))
+            .collect();
+
+        if self.format != Format::Text {
+            let records: Vec<Fields> = names
+                .iter()
+                .map(|name| self.record(name, current).fields())
+                .collect();
+
+            return render(self.format, &records);
+        }
+
+        let mut lines = vec![];
+
+        for name in &names {
+            let Some(rd) = self.rc.get(name) else {
+                continue;
+            };
+
+            let disabled = match self.rc.is_disabled(name) {
+                true => format!(" ({})", Msg::Disabled),
+                false => String::new(),
+            };
+
+            lines.push(format!(
+                "{}{}- {}{}",
+                status_prefix(name, current),
+                append_end_spaces(name, None),
+                rd.registry,
+                disabled
+            ));
+
+            if !self.verbose {
+                continue;
+            }
+
+            let yes_no = |v: bool| if v { Msg::Yes } else { Msg::No }.to_string();
+            let details = [
+                (Msg::DetailDisplayName.to_string(), rd.display_name.clone()),
+                (DL.to_string(), Some(rd.dl.to_string())),
+                (
+                    Msg::DetailProtocol.to_string(),
+                    Some(rd.protocol().to_string()),
+                ),
+                (
+                    Msg::DetailProxiesDownloads.to_string(),
+                    rd.proxies_downloads.map(yes_no),
+                ),
+                (Msg::DetailRegion.to_string(), rd.region.clone()),
+                (
+                    Msg::DetailTags.to_string(),
+                    Some(rd.tags.join(", ")).filter(|v| !v.is_empty()),
+                ),
+                (Msg::DetailHomepage.to_string(), rd.homepage.clone()),
+                (Msg::DetailDescription.to_string(), rd.description.clone()),
+                (
+                    Msg::DetailPriority.to_string(),
+                    rd.priority.map(|v| v.to_string()),
+                ),
+            ];
+
+            for (label, value) in details {
+                if let Some(value) = value {
+                    lines.push(format!("        {}: {}", label, value));
+                }
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// 判断镜像是否满足筛选条件
+    fn matches_filter(&self, name: &str, filter: &RegistryFilter) -> bool {
+        self.rc
+            .get(name)
+            .is_some_and(|v| filter.matches(name, v, self.rc.get_extend(name).is_some()))
+    }
+
+    /// 判断镜像是否与 `--tag` 参数指定的地区或标签匹配，没有指定时总是匹配
+    fn matches_tag(&self, name: &str) -> bool {
+        self.tag
+            .as_ref()
+            .is_none_or(|tag| self.rc.get(name).is_some_and(|v| v.has_tag(tag)))
+    }
+
+    /// 获取当前生效的 `Cargo` 配置
+    ///
+    /// 与 `Cargo` 的查找规则一致，距离当前目录最近的、替换了 `crates-io` 的项目级配置优先生效，
+    /// 如果都没有，则使用全局配置。
+    fn effective_config(&self) -> CrmResult<CargoConfig> {
+        for path in local_cargo_config_paths(&env::current_dir()?)? {
+            let config = CargoConfig::new(path)?;
+
+            if config.is_replaced() {
+                return Ok(config);
+            }
+        }
+
+        CargoConfig::new(cargo_config_path()?)
+    }
+
+    /// 获取 `Cargo` 配置文件中的 `http.<key>` 字段及其来源
+    ///
+    /// 与 `Cargo` 一样，距离当前目录最近的项目级配置文件优先，最后是全局配置文件。
+    /// `CARGO_HTTP_<KEY>` 环境变量的优先级更高，由 `net::http_setting` 处理。
+    pub fn cargo_http(&self, key: &str) -> CrmResult<Option<(String, String)>> {
+        let mut paths = local_cargo_config_paths(&env::current_dir()?)?;
+
+        paths.push(cargo_config_path()?);
+
+        for path in paths {
+            if let Some(value) = CargoConfig::new(path.clone())?.http(key) {
+                return Ok(Some((
+                    value,
+                    format!("{} ({}.{})", path.display(), HTTP, key),
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// 获取当前正在使用的镜像所在的配置文件及其作用域
+    pub fn current_scope(&self) -> CrmResult<(Scope, PathBuf)> {
+        let config = self.effective_config()?;
+
+        Ok((config.scope()?, config.path().to_path_buf()))
+    }
+
+    /// 获取当前正在使用的镜像
+    pub fn current(&self) -> CrmResult<(String, Option<String>)> {
+        let (name, addr) = self.effective_config()?.current()?;
+        let addr = match addr {
+            Some(addr) => Some(addr),
+            None => self.rc.get(&name).map(|addr| addr.registry.clone()),
+        };
+
+        Ok((name, addr))
+    }
+
+    /// 获取当前正在使用的镜像及其所在的配置文件的描述
+    pub fn show_current(&self) -> CrmResult<String> {
+        let (name, addr) = self.current()?;
+        let (scope, path) = self.current_scope()?;
+
+        if self.format == Format::Text {
+            let current = match addr {
+                Some(addr) => format!(" {}: {}", name, addr),
+                None => format!(" {}", name),
+            };
+
+            return Ok(Msg::Scope.format(&[&current, &scope, &path.display()]));
+        }
+
+        let mut record = self.record(&name, &name);
+        record.registry = addr;
+
+        let mut fields = record.fields();
+        let scope = match scope {
+            Scope::Global => "global",
+            Scope::Local => "local",
+        };
+        fields.push(("scope", Value::String(scope.to_string())));
+        fields.push(("config", Value::String(path.display().to_string())));
+
+        Ok(match self.format {
+            Format::Json => json_object(&fields),
+            _ => render(self.format, &[fields]),
+        })
+    }
+
+    /// 恢复为默认镜像
+    pub fn default(&mut self) -> CrmResult<()> {
+        self.select(Some(&RUST_LANG.to_string()))
+    }
+
+    /// 评估网络延迟并自动切换到最优的镜像
+    pub fn best(&mut self, mode: Option<&String>) -> CrmResult<()> {
+        let names = self.rc.registry_names();
+        let mut filter = self.filter.clone();
+
+        // 评估模式会被转换为筛选条件，例如 `git-download` 相当于 `protocol=git,downloads`
+        if let Some(mode) = mode {
+            filter.add_mode(&mode.to_lowercase())?;
+        }
+
+        // 没有指定协议时只评估 git 镜像
+        let protocol = filter
+            .protocol
+            .get_or_insert_with(|| "git".to_string())
+            .clone();
+
+        // 只使用满足筛选条件，并且地区或标签匹配的镜像
+        let mut candidates: Vec<&String> = names
+            .iter()
+            .filter(|name| self.matches_filter(name, &filter) && self.matches_tag(name))
+            .collect();
+
+        // 排除索引落后太多的镜像，无法检查同步情况的镜像也会被排除
+        let mut lags = HashMap::new();
+
+        if let Some(max_lag) = self.max_lag {
+            lags = self.freshness_of(&candidates).into_iter().collect();
+            candidates.retain(|name| {
+                lags.get(*name).is_some_and(|v: &FreshnessCheck| {
+                    v.as_ref().is_ok_and(|v| v.missing() <= max_lag)
+                })
+            });
+        }
+
+        // 排除软件包校验失败或校验和不一致的镜像
+        let mut integrities = HashMap::new();
+
+        if self.verify {
+            integrities = self.integrity_of(&candidates).into_iter().collect();
+            candidates.retain(|name| {
+                integrities
+                    .get(*name)
+                    .is_some_and(|v: &IntegrityCheck| v.as_ref().is_ok_and(Integrity::is_ok))
+            });
+        }
+
+        // git 镜像和代理了下载的镜像测试的是软件包下载，其他的 `sparse` 镜像只测试网络连接
+        let is_download = protocol == "git" || filter.downloads;
+
+        let kind = match self.rank {
+            Rank::Throughput => Kind::Throughput,
+            Rank::Latency if is_download => Kind::Download,
+            Rank::Latency => Kind::Connect,
+        };
+
+        // 优先根据最近的测试记录选择镜像，没有可用的记录时再进行测试
+        let remembered = match self.from_history {
+            true => self.best_from_history(&candidates, kind)?,
+            false => None,
+        };
+
+        let found = match self.rank {
+            _ if remembered.is_some() => remembered,
+
+            Rank::Throughput => {
+                let tested = self.bench_names(&candidates);
+
+                self.remember("best", kind, throughput_samples(&tested))?;
+
+                // 下载速度相同时，优先级高的镜像优先
+                tested
+                    .into_iter()
+                    .filter_map(|(name, bandwidth)| {
+                        let speed = bandwidth.as_ref().ok()?.bytes_per_sec();
+
+                        Some((
+                            (Reverse(speed), Reverse(self.priority(&name))),
+                            name,
+                            bandwidth,
+                        ))
+                    })
+                    .min_by_key(|v| v.0)
+                    .map(|(_, name, bandwidth)| {
+                        let mut record = self.record(&name, &name);
+                        record.throughput = Some(bandwidth);
+                        record
+                    })
+            }
+
+            Rank::Latency => {
+                let urls = candidates
+                    .iter()
+                    .map(|name| match is_download {
+                        true => (name.to_string(), self.to_download_url(name)),
+                        false => (name.to_string(), self.to_connected_url(name)),
+                    })
+                    .collect();
+
+                // 测试网络连接时，只要能连上主机就算成功
+                let tested = network_delay(
+                    urls,
+                    self.best_sender_size(),
+                    !is_download,
+                    self.probe_limits(),
+                );
+
+                // 提前拿到最快的镜像时，其他镜像没有测试完，不完整的一轮测试不会被记录
+                if tested.len() == candidates.len() {
+                    self.remember("best", kind, delay_samples(&tested))?;
+                }
+
+                // 分数相同时，优先级高的镜像优先
+                tested
+                    .into_iter()
+                    .filter_map(|(name, stats)| {
+                        let score = stats.score(self.timeout)?;
+
+                        Some(((score, Reverse(self.priority(&name))), name, stats))
+                    })
+                    .min_by_key(|v| v.0)
+                    .map(|(_, name, stats)| {
+                        let mut record = self.record(&name, &name);
+
+                        if is_download {
+                            record.download = Some(stats);
+                        } else {
+                            record.connect = Some(stats);
+                        }
+
+                        record
+                    })
+            }
+        };
+
+        let Some(mut record) = found else {
+            match self.format {
+                Format::Text => to_out(Msg::NoRegistryAvailable),
+                Format::Json => println!("null"),
+                Format::Tsv => println!("{}", render(self.format, &[])),
+            }
+
+            return Ok(());
+        };
+
+        record.freshness = lags.remove(&record.name);
+        record.integrity = integrities.remove(&record.name);
+        self.select(Some(&record.name))?;
+
+        match self.format {
+            Format::Text if self.dry_run => to_out(Msg::WillSwitch.format(&[&record.name])),
+            Format::Text => to_out(Msg::Switched.format(&[&record.name])),
+            Format::Json => println!("{}", json_object(&record.fields())),
+            Format::Tsv => println!("{}", render(self.format, &[record.fields()])),
+        }
+
+        Ok(())
+    }
+
+    /// 根据最近的测试记录选择最优的镜像，只使用在相同的主机上并且使用相同的标签得到的记录
+    ///
+    /// 没有可用的记录时返回 `None`。
+    fn best_from_history(&self, candidates: &[&String], kind: Kind) -> CrmResult<Option<Record>> {
+        let context = Context::current(self.label.clone());
+        let since = now().saturating_sub(DAY_MILLIS * HISTORY_MAX_AGE_HOURS as u128 / 24);
+        let mut samples: HashMap<String, Vec<Delay>> = HashMap::new();
+
+        for entry in history::load(since)? {
+            if entry.kind == kind
+                && entry.context == context
+                && candidates.iter().any(|v| **v == entry.name)
+            {
+                samples.entry(entry.name).or_default().push(entry.result);
+            }
+        }
+
+        let count: usize = samples.values().map(Vec::len).sum();
+
+        // 延迟根据分数从小到大排名，下载速度根据中位数从大到小排名，相同时优先级高的镜像优先
+        let found = samples
+            .into_iter()
+            .filter_map(|(name, samples)| {
+                let stats = Stats::new(samples);
+                let key = match kind {
+                    Kind::Throughput => u128::MAX - stats.median()?,
+                    _ => stats.score(self.timeout)?,
+                };
+
+                Some((key, name, stats))
+            })
+            .min_by(|a, b| {
+                a.0.cmp(&b.0)
+                    .then_with(|| self.priority(&b.1).cmp(&self.priority(&a.1)))
+                    .then_with(|| a.1.cmp(&b.1))
+            });
 
-use crate::{
-    cargo::CargoConfig,
-    constants::{APP_NAME, APP_VERSION, CARGO, RUST_LANG},
-    runtime::RuntimeConfig,
-    utils::{
-        append_end_spaces, exec_command, is_registry_addr, is_registry_dl, is_registry_name,
-        network_delay, status_prefix, to_out,
-    },
-};
+        let Some((_, name, stats)) = found else {
+            return Ok(None);
+        };
 
-/// 镜像对象
-pub struct Registry {
-    /// 运行时配置
-    rc: RuntimeConfig,
+        if self.format == Format::Text {
+            to_out(Msg::BestFromHistory.format(&[&count, &HISTORY_MAX_AGE_HOURS]));
+        }
 
-    /// `Cargo` 配置
-    cargo: CargoConfig,
-}
+        let mut record = self.record(&name, &name);
 
-impl Registry {
-    /// 创建镜像对象
-    pub fn new() -> Self {
-        Registry {
-            rc: RuntimeConfig::new(),
-            cargo: CargoConfig::new(),
+        match kind {
+            Kind::Connect => record.connect = Some(stats),
+            Kind::Download => record.download = Some(stats),
+            Kind::Throughput => {}
         }
+
+        Ok(Some(record))
     }
 
-    /// 切换镜像
-    pub fn select(&mut self, name: Option<&String>) {
-        let name = is_registry_name(name).trim();
+    /// 获取镜像的优先级，没有指定时为 `0`
+    fn priority(&self, name: &str) -> u64 {
+        self.rc.get(name).and_then(|v| v.priority).unwrap_or(0)
+    }
 
-        // 收集需要添加 `[registries.xxx]` 属性的镜像元祖数组
-        let remaining_registries = self.rc.to_tuples(None);
+    /// 将 `dl` 转换为 `url`
+    fn to_download_url(&self, name: &str) -> Option<String> {
+        self.crate_download_url(name, APP_NAME, APP_VERSION)
+    }
 
-        if let Err(name) = self
-            .cargo
-            .use_registry(name, self.rc.get(name), remaining_registries)
-        {
-            let keys = self.rc.to_key_string();
+    /// 获取从镜像中下载某个版本的软件包的 `url`
+    ///
+    /// 与 `Cargo` 的规则一致，如果 `dl` 中没有包含 `{crate}` 等标记，则在末尾追加 `/{crate}/{version}/download`。
+    fn crate_download_url(&self, name: &str, krate: &str, version: &str) -> Option<String> {
+        const MARKERS: [&str; 4] = ["{crate}", "{version}", "{prefix}", "{lowerprefix}"];
 
-            if keys.is_empty() {
-                return to_out(format!(
-                    "没有找到 {} 镜像，配置中的镜像列表为空，请用 \"crm save\" 添加镜像后重试",
-                    name,
-                ));
-            }
+        let dl = &self.rc.get(name)?.dl;
 
-            to_out(format!("没有找到 {} 镜像，可选的镜像是:\n{}", name, keys));
-        };
+        if !MARKERS.iter().any(|v| dl.contains(v)) {
+            return Some(format!("{}/{}/{}/download", dl, krate, version));
+        }
+
+        let prefix = crate_prefix(krate);
 
-        self.cargo.make();
+        Some(
+            dl.replace("{crate}", krate)
+                .replace("{version}", version)
+                .replace("{prefix}", &prefix)
+                .replace("{lowerprefix}", &prefix.to_lowercase()),
+        )
     }
 
-    /// 删除镜像
-    pub fn remove(&mut self, name: Option<&String>) {
-        let name = is_registry_name(name).trim();
+    /// 依次测试 `names` 中每个镜像的下载速度，并按照下载速度从快到慢排序，失败的镜像排在最后面
+    ///
+    /// 为了避免多个镜像争抢带宽，每个镜像都是单独测试的。
+    fn bench_names(&self, names: &[&String]) -> Vec<(String, Bandwidth)> {
+        let mut ret: Vec<(String, Bandwidth)> = names
+            .iter()
+            .map(|name| {
+                let urls: Vec<Option<String>> = self
+                    .bench_crates
+                    .iter()
+                    .map(|(krate, version)| self.crate_download_url(name, krate, version))
+                    .collect();
+                let bandwidth = throughput(&urls, self.timeout, self.max_bytes, self.max_time);
 
-        if self.rc.get_default(name).is_some() {
-            to_out("请不要删除内置镜像");
-            process::exit(7);
-        }
+                (name.to_string(), bandwidth)
+            })
+            .collect();
 
-        if self.rc.get_extend(name).is_none() {
-            to_out(format!("删除失败，{} 镜像不存在", name));
-            process::exit(8);
+        ret.sort_by_key(|v| Reverse(v.1.as_ref().ok().map(Throughput::bytes_per_sec)));
+
+        ret
+    }
+
+    /// 检查 `sparse` 镜像的 `config.json` 和索引文件
+    pub fn probe(&self, current: &String, name: Option<&String>) -> CrmResult<()> {
+        let names = match name {
+            Some(name) => {
+                self.verify_exists(name)?;
+
+                vec![name.to_string()]
+            }
+            None => self
+                .rc
+                .registry_names()
+                .into_iter()
+                .filter(|name| {
+                    self.rc
+                        .get(name)
+                        .is_some_and(|v| index_root(&v.registry).is_some())
+                })
+                .collect(),
+        };
+
+        // 同时检查所有的镜像
+        let tested: Vec<(String, IndexCheck)> = self.check_all(&names, |name, budget| {
+            let rd = self
+                .rc
+                .get(name)
+                .ok_or_else(|| Msg::UnsupportedTest.to_string())?;
+
+            probe(&rd.registry, &rd.dl, &self.probe_crate, budget.timeout()?)
+        });
+
+        if self.format != Format::Text {
+            let records: Vec<Fields> = tested
+                .into_iter()
+                .map(|(name, check)| {
+                    let mut record = self.record(&name, current);
+                    record.index = Some(check);
+                    record.fields()
+                })
+                .collect();
+
+            println!("{}", render(self.format, &records));
+
+            return Ok(());
         }
 
-        self.rc.remove(name);
-        self.rc.write();
+        let lines: Vec<String> = tested
+            .iter()
+            .map(|(name, check)| {
+                let status = match check {
+                    Ok(v) => Msg::IndexOk.format(&[&self.probe_crate, &v.versions]),
+                    Err(e) => format!("failed: {}", e),
+                };
+
+                format!(
+                    "{}{} -- {}",
+                    status_prefix(name, current),
+                    append_end_spaces(name, None),
+                    status
+                )
+            })
+            .collect();
+
+        println!("{}\n{}", Msg::IndexCheckTitle, lines.join("\n"));
+
+        Ok(())
     }
 
-    /// 添加/更新镜像
-    pub fn save(&mut self, name: Option<&String>, addr: Option<&String>, dl: Option<&String>) {
-        let name = is_registry_name(name).trim();
-        let addr = is_registry_addr(addr).trim();
-        let dl = is_registry_dl(dl).trim();
+    /// 获取用于检查镜像同步情况的 `sparse` 索引地址
+    ///
+    /// git 镜像无法按文件请求索引，所以返回 `None`，官方镜像则使用官方的 `sparse` 索引。
+    /// 同名的 `-sparse` 镜像可能来自于不同的同步任务，所以不能代替 git 镜像的索引。
+    fn sparse_root(&self, name: &str) -> Option<String> {
+        if name == RUST_LANG {
+            return Some(OFFICIAL_SPARSE_INDEX.to_string());
+        }
 
-        self.rc.save(name, addr, dl);
-        self.rc.write();
+        index_root(&self.rc.get(name)?.registry)
     }
 
-    /// 获取镜像列表
-    pub fn list(&self, current: &String) -> String {
-        self.rc.to_string(current, Some("- "))
+    /// 检查 `names` 中每个镜像的索引与官方索引的同步情况
+    fn freshness_of(&self, names: &[&String]) -> Vec<(String, FreshnessCheck)> {
+        // 官方索引只需要请求一次
+        let upstream = upstream_versions(&self.freshness_crates, self.timeout);
+
+        self.check_all(names, |name, budget| {
+            match (&upstream, self.sparse_root(name)) {
+                (Err(e), _) => Err(e.to_string()),
+                (_, None) => Err(Msg::NoSparseIndex.to_string()),
+                (Ok(upstream), Some(root)) => freshness(&root, upstream, budget.timeout()?),
+            }
+        })
     }
 
-    /// 获取当前正在使用的镜像
-    pub fn current(&self) -> (String, Option<String>) {
-        let (name, addr) = CargoConfig::new().current();
-        let addr = match addr {
-            Some(addr) => Some(addr),
-            None => self.rc.get(&name).map(|addr| addr.registry.clone()),
+    /// 检查镜像的索引与官方索引的同步情况
+    pub fn freshness(&self, current: &String, name: Option<&String>) -> CrmResult<()> {
+        let names = match name {
+            Some(name) => {
+                self.verify_exists(name)?;
+
+                vec![name.to_string()]
+            }
+            None => self.rc.registry_names(),
         };
+        let mut tested = self.freshness_of(&names.iter().collect::<Vec<&String>>());
 
-        (name, addr)
-    }
+        // 落后的版本越少越靠前，检查失败的镜像排在最后面
+        tested.sort_by_key(|v| v.1.as_ref().map_or(usize::MAX, |v| v.missing()));
 
-    /// 恢复为默认镜像
-    pub fn default(&mut self) {
-        self.select(Some(&RUST_LANG.to_string()));
-    }
+        if self.format != Format::Text {
+            let records: Vec<Fields> = tested
+                .into_iter()
+                .map(|(name, check)| {
+                    let mut record = self.record(&name, current);
+                    record.freshness = Some(check);
+                    record.fields()
+                })
+                .collect();
 
-    fn delay_tester<'a, I, P, F>(
-        &self,
-        iter: I,
-        predicate: P,
-        conversion_url: F,
-    ) -> Vec<(String, Option<u128>)>
-    where
-        I: Iterator<Item = &'a String>,
-        P: FnMut(&&'a String) -> bool,
-        F: FnMut(&'a String) -> (String, Option<String>),
-    {
-        let urls: Vec<(String, Option<String>)> =
-            iter.filter(predicate).map(conversion_url).collect();
+            println!("{}", render(self.format, &records));
+
+            return Ok(());
+        }
+
+        let lines: Vec<String> = tested
+            .iter()
+            .map(|(name, check)| {
+                let status = match check {
+                    Ok(v) if v.missing() == 0 => Msg::FreshnessOk.to_string(),
+                    Ok(v) => {
+                        let behind: Vec<String> = v
+                            .lags
+                            .iter()
+                            .filter(|v| v.missing > 0)
+                            .map(|v| format!("{} {} < {}", v.krate, v.newest, v.upstream))
+                            .collect();
+
+                        Msg::FreshnessLag.format(&[&v.missing(), &behind.join(", ")])
+                    }
+                    Err(e) => format!("failed: {}", e),
+                };
+
+                format!(
+                    "{}{} -- {}",
+                    status_prefix(name, current),
+                    append_end_spaces(name, None),
+                    status
+                )
+            })
+            .collect();
 
-        network_delay(urls, Some(1), true)
+        println!("{}\n{}", Msg::FreshnessTitle, lines.join("\n"));
+
+        Ok(())
     }
 
-    /// 评估网络延迟并自动切换到最优的镜像
-    pub fn best(&mut self, mode: Option<&String>) {
-        let names = self.rc.registry_names();
-        let iter = names.iter();
-        let dld = ["sjtu", "ustc", "rsproxy"].iter().map(ToString::to_string);
-        const SPARSE: &str = "-sparse";
-
-        let tested = match mode {
-            Some(mode) => match mode.to_lowercase().as_str() {
-                // 测试所有带有 `sparse` 后缀的镜像源
-                "sparse" => self.delay_tester(
-                    iter,
-                    |name| name.ends_with(SPARSE),
-                    |name| (name.to_string(), self.to_connected_url(name)),
-                ),
+    /// 校验镜像 `name` 提供的软件包
+    ///
+    /// `upstream` 是官方索引中每个软件包的 `cksum`，与 `verify_crates` 一一对应。
+    fn integrity(&self, name: &str, upstream: &[String], budget: &Budget) -> IntegrityCheck {
+        // 官方镜像的索引就是官方索引，不需要重复比较
+        let root = self
+            .sparse_root(name)
+            .filter(|v| v != OFFICIAL_SPARSE_INDEX);
+        let mut integrity = Integrity::default();
 
-                // 测试所有 git 镜像源
-                "git" => self.delay_tester(
-                    iter,
-                    |name| !name.ends_with(SPARSE),
-                    |name| (name.to_string(), self.to_download_url(name)),
-                ),
+        for ((krate, version), expected) in self.verify_crates.iter().zip(upstream) {
+            let url = self
+                .crate_download_url(name, krate, version)
+                .ok_or_else(|| Msg::UnsupportedTest.to_string())?;
+            let mut actual = vec![(
+                MismatchKind::File,
+                sha256(&download(&url, budget.timeout()?)?),
+            )];
 
-                // 仅测试能够快速下载包的 git 镜像源
-                "git-download" => {
-                    let s = dld.collect::<HashSet<String>>();
+            if let Some(root) = &root {
+                actual.push((
+                    MismatchKind::Index,
+                    index_cksum(root, krate, version, budget.timeout()?)?,
+                ));
+            }
 
-                    self.delay_tester(
-                        iter,
-                        |name| s.contains(&**name),
-                        |name| (name.to_string(), self.to_download_url(name)),
-                    )
-                }
+            integrity.mismatches.extend(
+                actual
+                    .into_iter()
+                    .filter(|(_, actual)| actual != expected)
+                    .map(|(kind, actual)| Mismatch {
+                        krate: krate.to_string(),
+                        version: version.to_string(),
+                        kind,
+                        expected: expected.to_string(),
+                        actual,
+                    }),
+            );
+            integrity.checked += 1;
+        }
 
-                // 仅测试所有能够快速下载包的，并且带有 `sparse` 后缀的镜像源
-                "sparse-download" => {
-                    let s = dld.map(|v| v + SPARSE).collect::<HashSet<String>>();
+        Ok(integrity)
+    }
 
-                    self.delay_tester(
-                        iter,
-                        |name| s.contains(&**name),
-                        |name| (name.to_string(), self.to_connected_url(name)),
-                    )
-                }
+    /// 同时校验 `names` 中每个镜像提供的软件包
+    fn integrity_of(&self, names: &[&String]) -> Vec<(String, IntegrityCheck)> {
+        // 官方索引只需要请求一次
+        let upstream: Result<Vec<String>, String> = self
+            .verify_crates
+            .iter()
+            .map(|(krate, version)| {
+                index_cksum(OFFICIAL_SPARSE_INDEX, krate, version, self.timeout)
+                    .map_err(|e| Msg::UpstreamFailed.format(&[&e]))
+            })
+            .collect();
 
-                _ => {
-                    to_out("参数错误，您不能使用除 \"sparse\"、\"git\"、\"git-download\" 或 \"sparse-download\" 之外的值");
-                    process::exit(9)
-                }
-            },
+        self.check_all(names, |name, budget| match &upstream {
+            Ok(upstream) => self.integrity(name, upstream, budget),
+            Err(e) => Err(e.to_string()),
+        })
+    }
+
+    /// 校验镜像提供的软件包，只要有一个镜像的校验和不一致就会返回错误
+    pub fn verify(&self, current: &String, name: Option<&String>) -> CrmResult<()> {
+        let names = match name {
+            Some(name) => {
+                self.verify_exists(name)?;
 
-            None => self.test_download_status(None, Some(1)),
+                vec![name.to_string()]
+            }
+            None => self.rc.registry_names(),
         };
+        let tested = self.integrity_of(&names.iter().collect::<Vec<&String>>());
+        let tampered: Vec<String> = tested
+            .iter()
+            .filter(|(_, check)| check.as_ref().is_ok_and(|v| !v.is_ok()))
+            .map(|(name, _)| name.to_string())
+            .collect();
+
+        if self.format != Format::Text {
+            let records: Vec<Fields> = tested
+                .into_iter()
+                .map(|(name, check)| {
+                    let mut record = self.record(&name, current);
+                    record.integrity = Some(check);
+                    record.fields()
+                })
+                .collect();
 
-        let found = tested.iter().find(|v| v.1.is_some());
+            println!("{}", render(self.format, &records));
+        } else {
+            let lines: Vec<String> = tested
+                .iter()
+                .map(|(name, check)| {
+                    let status = match check {
+                        Ok(v) if v.is_ok() => Msg::VerifyOk.format(&[&v.checked]),
+                        Ok(v) => v
+                            .mismatches
+                            .iter()
+                            .map(|v| {
+                                Msg::VerifyMismatch.format(&[
+                                    &v.krate,
+                                    &v.version,
+                                    &v.kind.as_str(),
+                                    &v.expected,
+                                    &v.actual,
+                                ])
+                            })
+                            .collect::<Vec<String>>()
+                            .join("\n      "),
+                        Err(e) => format!("failed: {}", e),
+                    };
+
+                    format!(
+                        "{}{} -- {}",
+                        status_prefix(name, current),
+                        append_end_spaces(name, None),
+                        status
+                    )
+                })
+                .collect();
 
-        if found.is_none() {
-            return to_out("没有可切换的镜像源");
+            println!("{}\n{}", Msg::VerifyTitle, lines.join("\n"));
         }
 
-        let registry_name = &found.unwrap().0;
+        if !tampered.is_empty() {
+            return Err(CrmError::ChecksumMismatch(tampered));
+        }
 
-        self.select(Some(registry_name));
-        to_out(format!("已切换到 {} 镜像源", registry_name));
+        Ok(())
     }
 
-    /// 将 `dl` 转换为 `url`
-    fn to_download_url(&self, name: &str) -> Option<String> {
-        match self.rc.get(name) {
-            Some(rd) => {
-                let dl = rd.dl.clone();
-                let url = if !dl.ends_with("/api/v1/crates") {
-                    dl.replace("{crate}", APP_NAME)
-                        .replace("{version}", APP_VERSION)
-                } else {
-                    format!("{}/{}/{}/download", dl, APP_NAME, APP_VERSION)
-                };
+    /// 测试镜像源的下载速度
+    pub fn bench(&self, current: &String, name: Option<&String>) -> CrmResult<()> {
+        let names = match name {
+            Some(name) => {
+                self.verify_exists(name)?;
 
-                Some(url)
+                vec![name.to_string()]
             }
-            None => None,
+
+            // `sparse` 镜像与对应的 git 镜像使用的是同一个 `dl`，所以不需要重复测试
+            None => self
+                .rc
+                .registry_names()
+                .into_iter()
+                .filter(|name| !self.is_sparse(name))
+                .collect(),
+        };
+        let tested = self.bench_names(&names.iter().collect::<Vec<&String>>());
+
+        self.remember("bench", Kind::Throughput, throughput_samples(&tested))?;
+
+        if self.format != Format::Text {
+            let records: Vec<Fields> = tested
+                .into_iter()
+                .map(|(name, bandwidth)| {
+                    let mut record = self.record(&name, current);
+                    record.throughput = Some(bandwidth);
+                    record.fields()
+                })
+                .collect();
+
+            println!("{}", render(self.format, &records));
+
+            return Ok(());
         }
+
+        let lines: Vec<String> = tested
+            .iter()
+            .map(|(name, bandwidth)| {
+                let status = match bandwidth {
+                    Ok(v) => Msg::SpeedStats.format(&[
+                        &format!("{:.2}", v.mbps()),
+                        &format!("{:.1}", v.bytes as f64 / 1_000_000.0),
+                        &format!("{:.1}", v.elapsed.as_secs_f64()),
+                    ]),
+                    Err(e) if e.is_certificate() => format!("failed: {}", e),
+                    Err(_) => "failed".to_string(),
+                };
+
+                format!(
+                    "{}{} -- {}",
+                    status_prefix(name, current),
+                    append_end_spaces(name, None),
+                    status
+                )
+            })
+            .collect();
+
+        println!("{}\n{}", Msg::DownloadSpeed, lines.join("\n"));
+
+        Ok(())
     }
 
     /// 测试镜像源状态
@@ -214,13 +1653,10 @@ impl Registry {
         &self,
         name: Option<&String>,
         sender_size: Option<usize>,
-    ) -> Vec<(String, Option<u128>)> {
+    ) -> CrmResult<Vec<(String, Stats)>> {
         let urls = match name {
             Some(name) => {
-                if self.rc.get(name).is_none() {
-                    to_out(format!("下载测试失败，{} 镜像不存在", name));
-                    process::exit(10);
-                }
+                self.verify_exists(name)?;
 
                 vec![(name.to_string(), self.to_download_url(name))]
             }
@@ -228,12 +1664,17 @@ impl Registry {
                 .rc
                 .registry_names()
                 .iter()
-                .filter(|name| !name.ends_with("-sparse"))
+                .filter(|name| !self.is_sparse(name))
                 .map(|name| (name.to_string(), self.to_download_url(name)))
                 .collect(),
         };
 
-        network_delay(urls, sender_size, false)
+        Ok(network_delay(urls, sender_size, false, self.probe_limits()))
+    }
+
+    /// 判断镜像是否使用 `sparse` 协议
+    fn is_sparse(&self, name: &str) -> bool {
+        self.rc.get(name).is_some_and(|v| v.protocol() == "sparse")
     }
 
     /// 将 `dl` 转换为 `url`
@@ -256,13 +1697,10 @@ impl Registry {
         &self,
         name: Option<&String>,
         sender_size: Option<usize>,
-    ) -> Vec<(String, Option<u128>)> {
+    ) -> CrmResult<Vec<(String, Stats)>> {
         let urls = match name {
             Some(name) => {
-                if self.rc.get(name).is_none() {
-                    to_out(format!("连接测试失败，{} 镜像不存在", name));
-                    process::exit(11);
-                }
+                self.verify_exists(name)?;
 
                 vec![(name.to_string(), self.to_connected_url(name))]
             }
@@ -274,85 +1712,485 @@ impl Registry {
                 .collect(),
         };
 
-        network_delay(urls, sender_size, true)
+        Ok(network_delay(urls, sender_size, true, self.probe_limits()))
+    }
+
+    /// 同时检查 git 镜像的引用列表，并按照耗时从小到大排序，失败的镜像排在最后面
+    fn test_git_status(&self, name: Option<&String>) -> CrmResult<Vec<(String, GitCheck)>> {
+        let names = match name {
+            Some(name) => {
+                self.verify_exists(name)?;
+
+                vec![name.to_string()]
+            }
+            None => self.rc.registry_names(),
+        };
+
+        let names: Vec<&String> = names
+            .iter()
+            .filter(|name| {
+                self.rc
+                    .get(name)
+                    .is_some_and(|rd| index_root(&rd.registry).is_none())
+            })
+            .collect();
+
+        let mut tested: Vec<(String, GitCheck)> = self.check_all(&names, |name, budget| {
+            let rd = self
+                .rc
+                .get(name)
+                .ok_or_else(|| Msg::UnsupportedTest.to_string())?;
+
+            probe_git(&rd.registry, budget.timeout()?)
+        });
+
+        tested.sort_by_key(|v| v.1.as_ref().map_or(Duration::MAX, |v| v.elapsed));
+
+        Ok(tested)
+    }
+
+    /// 同时对镜像进行分阶段计时，并按照总耗时从小到大排序，失败的镜像排在最后面
+    fn test_timing_status(&self, name: Option<&String>) -> CrmResult<Vec<(String, TimingCheck)>> {
+        let names = match name {
+            Some(name) => {
+                self.verify_exists(name)?;
+
+                vec![name.to_string()]
+            }
+            None => self.rc.registry_names(),
+        };
+
+        let mut tested: Vec<(String, TimingCheck)> =
+            self.check_all(&names, |name, budget| match self.to_connected_url(name) {
+                Some(url) => measure(&url, budget.timeout()?),
+                None => Err(Msg::UnsupportedTest.to_string()),
+            });
+
+        tested.sort_by_key(|v| v.1.as_ref().map_or(Duration::MAX, |v| v.total()));
+
+        Ok(tested)
+    }
+
+    /// 获取用于比较的 `HEAD`，优先使用官方镜像的，否则使用最多的镜像所指向的提交
+    fn reference_head(tested: &[(String, GitCheck)]) -> Option<(String, String)> {
+        let heads: Vec<(&String, &str)> = tested
+            .iter()
+            .filter_map(|(name, check)| Some((name, check.as_ref().ok()?.head.as_str())))
+            .collect();
+
+        heads
+            .iter()
+            .find(|(name, _)| name.as_str() == RUST_LANG)
+            .or_else(|| {
+                heads
+                    .iter()
+                    .max_by_key(|(_, head)| heads.iter().filter(|v| v.1 == *head).count())
+            })
+            .map(|(name, head)| (name.to_string(), head.to_string()))
+    }
+
+    /// 验证要测试的镜像是否存在
+    fn verify_exists(&self, name: &str) -> CrmResult<()> {
+        match self.rc.get(name) {
+            Some(_) => Ok(()),
+            None => Err(CrmError::UnknownRegistry {
+                name: name.to_string(),
+                operation: Operation::Test,
+                candidates: vec![],
+            }),
+        }
     }
 
     /// 测试镜像源延迟
-    pub fn test(&self, current: &String, name: Option<&String>) {
-        let connected_status: Vec<String> = self
-            .test_connected_status(name, None)
+    pub fn test(&self, current: &String, name: Option<&String>) -> CrmResult<()> {
+        let connected = self.test_connected_status(name, None)?;
+        let downloaded = self.test_download_status(name, None)?;
+        let git = self.test_git_status(name)?;
+
+        self.remember("test", Kind::Connect, delay_samples(&connected))?;
+        self.remember("test", Kind::Download, delay_samples(&downloaded))?;
+
+        let timings = match self.detailed {
+            true => self.test_timing_status(name)?,
+            false => vec![],
+        };
+
+        if self.format != Format::Text {
+            let names = match name {
+                Some(name) => vec![name.to_string()],
+                None => self.rc.registry_names(),
+            };
+            let find = |tested: &Vec<(String, Stats)>, name: &str| {
+                tested.iter().find(|v| v.0.eq(name)).map(|v| v.1.clone())
+            };
+            let records: Vec<Fields> = names
+                .iter()
+                .map(|name| {
+                    let mut record = self.record(name, current);
+                    record.connect = find(&connected, name);
+                    record.download = find(&downloaded, name);
+                    record.git = git.iter().find(|v| v.0.eq(name)).map(|v| v.1.clone());
+                    record.timing = timings.iter().find(|v| v.0.eq(name)).map(|v| v.1.clone());
+                    record.fields()
+                })
+                .collect();
+
+            println!("{}", render(self.format, &records));
+
+            return Ok(());
+        }
+
+        let to_status = |tested: Vec<(String, Stats)>| {
+            tested
+                .iter()
+                .map(|(name, stats)| {
+                    let prefix = status_prefix(name, current);
+                    let name = append_end_spaces(name, None);
+                    let status = match stats.median() {
+                        // `TLS` 证书验证失败通常是缺少 CA 证书，而不是镜像不可用，所以单独输出原因
+                        None => match stats.error() {
+                            Some(e) if e.is_certificate() => format!("failed: {}", e),
+                            _ => "failed".to_string(),
+                        },
+                        Some(median) if stats.count() == 1 => format!("{} ms", median),
+                        Some(median) => Msg::LatencyStats.format(&[
+                            &median,
+                            &stats.min().unwrap_or_default(),
+                            &stats.p90().unwrap_or_default(),
+                            &stats.jitter().unwrap_or_default(),
+                            &format!("{:.0}", stats.success_rate() * 100.0),
+                        ]),
+                    };
+
+                    format!("{}{} -- {}", prefix, name, status)
+                })
+                .collect::<Vec<String>>()
+        };
+
+        println!(
+            "{}\n{}\n",
+            Msg::ConnectLatency,
+            to_status(connected).join("\n")
+        );
+        println!(
+            "{}\n{}",
+            Msg::DownloadLatency,
+            to_status(downloaded).join("\n")
+        );
+
+        if !timings.is_empty() {
+            let ms = |v: Duration| format!("{} ms", v.as_millis());
+            let lines: Vec<String> = timings
+                .iter()
+                .map(|(name, timing)| {
+                    let status = match timing {
+                        Ok(v) => Msg::TimingStats.format(&[
+                            &ms(v.dns),
+                            &ms(v.tcp),
+                            &v.tls.map_or("-".to_string(), ms),
+                            &ms(v.ttfb),
+                            &ms(v.body),
+                            &ms(v.total()),
+                            &v.status,
+                        ]),
+                        Err(e) => format!("failed: {}", e),
+                    };
+
+                    format!(
+                        "{}{} -- {}",
+                        status_prefix(name, current),
+                        append_end_spaces(name, None),
+                        status
+                    )
+                })
+                .collect();
+
+            println!("\n{}\n{}", Msg::TimingTitle, lines.join("\n"));
+        }
+
+        if git.is_empty() {
+            return Ok(());
+        }
+
+        // 比较不同的 git 镜像是否同步到了同一个提交
+        let reference = Self::reference_head(&git);
+        let lines: Vec<String> = git
             .iter()
-            .map(|(name, status)| {
-                let prefix = status_prefix(name, current);
-                let name = append_end_spaces(name, None);
-                let status = match status {
-                    Some(s) => format!("{} ms", s),
-                    None => "failed".to_string(),
+            .map(|(name, check)| {
+                let status = match check {
+                    Ok(v) => {
+                        let mut status =
+                            Msg::GitOk.format(&[&v.elapsed.as_millis(), &v.short_head()]);
+
+                        if let Some((other, head)) = &reference {
+                            if head != &v.head {
+                                status
+                                    .push_str(&Msg::GitHeadDiffers.format(&[other, &&head[..12]]));
+                            }
+                        }
+
+                        status
+                    }
+                    Err(e) => format!("failed: {}", e),
                 };
 
-                format!("{}{} -- {}", prefix, name, status)
+                format!(
+                    "{}{} -- {}",
+                    status_prefix(name, current),
+                    append_end_spaces(name, None),
+                    status
+                )
             })
             .collect();
 
-        println!("网络连接延迟:\n{}\n", connected_status.join("\n"));
+        println!("\n{}\n{}", Msg::GitIndexTitle, lines.join("\n"));
 
-        let download_status: Vec<String> = self
-            .test_download_status(name, None)
-            .iter()
-            .map(|(name, status)| {
-                let prefix = status_prefix(name, current);
-                let name = append_end_spaces(name, None);
-                let status = match status {
-                    Some(s) => format!("{} ms", s),
-                    None => "failed".to_string(),
+        Ok(())
+    }
+
+    /// 根据最近 `days` 天的测试记录，输出每个镜像的趋势、成功率和百分位数
+    pub fn report(&self, name: Option<&String>) -> CrmResult<()> {
+        let since = now().saturating_sub(DAY_MILLIS * self.days as u128);
+        let entries: Vec<_> = history::load(since)?
+            .into_iter()
+            .filter(|v| name.is_none_or(|name| name == &v.name))
+            .filter(|v| self.label.is_none() || v.context.label == self.label)
+            .collect();
+        let mut records: Vec<Fields> = vec![];
+        let mut sections: Vec<String> = vec![];
+
+        for (kind, title) in [
+            (Kind::Connect, Msg::ConnectLatency),
+            (Kind::Download, Msg::DownloadLatency),
+            (Kind::Throughput, Msg::DownloadSpeed),
+        ] {
+            let mut names: Vec<&String> = entries
+                .iter()
+                .filter(|v| v.kind == kind)
+                .map(|v| &v.name)
+                .collect();
+
+            names.sort();
+            names.dedup();
+
+            let mut rows: Vec<(&String, Stats, u128)> = names
+                .into_iter()
+                .map(|name| {
+                    let matched: Vec<_> = entries
+                        .iter()
+                        .filter(|v| v.kind == kind && &v.name == name)
+                        .collect();
+                    let last = matched.last().map_or(0, |v| v.time);
+                    let stats = Stats::new(matched.into_iter().map(|v| v.result.clone()).collect());
+
+                    (name, stats, last)
+                })
+                .collect();
+
+            // 延迟从小到大排序，下载速度从大到小排序，没有成功的样本的镜像排在最后面
+            rows.sort_by_key(|(_, stats, _)| match (kind, stats.median()) {
+                (_, None) => u128::MAX,
+                (Kind::Throughput, Some(v)) => u128::MAX - 1 - v,
+                (_, Some(v)) => v,
+            });
+
+            if rows.is_empty() {
+                continue;
+            }
+
+            let percent = |v: Option<f64>| match v {
+                Some(v) => format!("{:+.1}%", v),
+                None => "-".to_string(),
+            };
+            let mbps = |v: Option<u128>| match v {
+                Some(v) => format!("{:.2} MB/s", v as f64 / 1_000_000.0),
+                None => "-".to_string(),
+            };
+            let ms = |v: Option<u128>| v.map_or("-".to_string(), |v| format!("{} ms", v));
+            let mut lines = vec![];
+
+            for (name, stats, last) in rows {
+                let rate = format!("{:.0}", stats.success_rate() * 100.0);
+                let status = match kind {
+                    Kind::Throughput => Msg::ReportThroughput.format(&[
+                        &stats.count(),
+                        &rate,
+                        &mbps(stats.median()),
+                        &mbps(stats.min()),
+                        &percent(trend(&stats)),
+                        &format_timestamp(last),
+                    ]),
+                    _ => Msg::ReportLatency.format(&[
+                        &stats.count(),
+                        &rate,
+                        &ms(stats.median()),
+                        &ms(stats.p90()),
+                        &percent(trend(&stats)),
+                        &format_timestamp(last),
+                    ]),
                 };
 
-                format!("{}{} -- {}", prefix, name, status)
+                lines.push(format!(
+                    "    {} -- {}",
+                    append_end_spaces(name, None),
+                    status
+                ));
+                records.push(vec![
+                    ("name", Value::String(name.to_string())),
+                    ("kind", Value::String(kind.as_str().to_string())),
+                    (
+                        "unit",
+                        Value::String(match kind {
+                            Kind::Throughput => "B/s".to_string(),
+                            _ => "ms".to_string(),
+                        }),
+                    ),
+                    ("samples", Value::Number(stats.count() as u128)),
+                    ("success_rate", Value::Float(stats.success_rate())),
+                    ("min", stats.min().into()),
+                    ("median", stats.median().into()),
+                    ("p90", stats.p90().into()),
+                    (
+                        "trend_percent",
+                        trend(&stats)
+                            .map_or(Value::Null, |v| Value::Float((v * 10.0).round() / 10.0)),
+                    ),
+                    ("last_seen", Value::String(format_timestamp(last))),
+                ]);
+            }
+
+            sections.push(format!("{}\n{}", title, lines.join("\n")));
+        }
+
+        if self.format != Format::Text {
+            println!("{}", render(self.format, &records));
+
+            return Ok(());
+        }
+
+        if sections.is_empty() {
+            to_out(Msg::NoHistory.format(&[&self.days]));
+
+            return Ok(());
+        }
+
+        println!("{}\n", Msg::ReportTitle.format(&[&self.days]));
+        println!("{}", sections.join("\n\n"));
+
+        Ok(())
+    }
+
+    /// 根据编号查找快照，编号从 1 开始，1 表示最新的快照
+    fn find_snapshot(index: Option<&String>) -> CrmResult<Snapshot> {
+        let index = match index {
+            Some(index) => index
+                .parse::<usize>()
+                .ok()
+                .filter(|v| *v > 0)
+                .ok_or_else(|| {
+                    CrmError::InvalidValue(Msg::InvalidSnapshotIndex.format(&[index]))
+                })?,
+            None => 1,
+        };
+
+        snapshots()?
+            .into_iter()
+            .nth(index - 1)
+            .ok_or_else(|| CrmError::InvalidValue(Msg::SnapshotNotFound.format(&[&index])))
+    }
+
+    /// 获取配置文件的快照列表，如果指定了快照编号，则获取该快照与原文件当前内容之间的差异
+    pub fn history(&self, index: Option<&String>) -> CrmResult<String> {
+        if let Some(n) = index {
+            let snapshot = Registry::find_snapshot(index)?;
+            let current = read_to_string(&snapshot.origin).unwrap_or_default();
+            let diff = unified_diff(
+                &current,
+                &snapshot.content()?,
+                &Msg::DiffCurrent.format(&[&snapshot.origin.display()]),
+                &Msg::DiffSnapshot.format(&[&snapshot.origin.display(), n]),
+            );
+
+            if diff.is_empty() {
+                return Ok(Msg::SnapshotUnchanged.to_string());
+            }
+
+            return Ok(diff.trim_end().to_string());
+        }
+
+        let list: Vec<String> = snapshots()?
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                format!(
+                    "  {}{}  {}",
+                    append_end_spaces(&(i + 1).to_string(), Some(4)),
+                    format_timestamp(v.time),
+                    v.origin.display()
+                )
             })
             .collect();
 
-        println!("软件包下载延迟:\n{}", download_status.join("\n"));
+        if list.is_empty() {
+            return Ok(Msg::NoSnapshots.to_string());
+        }
+
+        Ok(list.join("\n"))
+    }
+
+    /// 将配置文件恢复为指定的快照，如果没有指定快照编号，则恢复为最新的快照
+    pub fn rollback(&mut self, index: Option<&String>) -> CrmResult<()> {
+        let snapshot = Registry::find_snapshot(index)?;
+
+        restore(&snapshot)?;
+        to_out(
+            Msg::RolledBack.format(&[&snapshot.origin.display(), &format_timestamp(snapshot.time)]),
+        );
+
+        Ok(())
     }
 
     /// 使用官方镜像源执行命令
-    fn exec(&mut self, command: &str) {
-        let (registry_name, _) = self.current();
+    fn exec(&mut self, args: Vec<String>) -> CrmResult<()> {
+        // 在当前生效的配置文件中切换镜像，否则项目级配置会覆盖掉对全局配置的修改
+        self.cargo = self.effective_config()?;
+
+        let (registry_name, _) = self.current()?;
         let is_default_registry = registry_name.eq(RUST_LANG);
 
         if !is_default_registry {
-            self.default();
+            self.default()?;
         }
 
-        if let Err(e) = exec_command(command, None) {
-            to_out(e);
-        };
+        // 无论命令是否执行成功，都要恢复为原来的镜像
+        let result = exec_command(CARGO, &args, None);
 
         if !is_default_registry {
-            self.select(Some(&registry_name));
+            self.select(Some(&registry_name))?;
         }
+
+        result?;
+
+        Ok(())
     }
 
     /// 使用官方镜像执行 `cargo publish`
-    pub fn publish(&mut self, args: String) {
-        self.exec(&format!("{} publish {}", CARGO, args.trim()));
+    pub fn publish(&mut self, args: Vec<String>) -> CrmResult<()> {
+        self.exec([vec!["publish".to_string()], args].concat())
     }
 
     /// 使用官方镜像执行 `cargo update`
-    pub fn update(&mut self, args: String) {
-        self.exec(&format!("{} update {}", CARGO, args.trim()));
+    pub fn update(&mut self, args: Vec<String>) -> CrmResult<()> {
+        self.exec([vec!["update".to_string()], args].concat())
     }
 
     /// 使用官方镜像执行 `cargo install`
-    pub fn install(&mut self, args: String) {
-        let args = args.trim();
-        let args = if args.is_empty() { "--help" } else { args };
-
-        self.exec(&format!("{} install {}", CARGO, args));
-    }
-}
+    pub fn install(&mut self, args: Vec<String>) -> CrmResult<()> {
+        let args = match args.is_empty() {
+            true => vec!["--help".to_string()],
+            false => args,
+        };
 
-impl Default for Registry {
-    fn default() -> Self {
-        Self::new()
+        self.exec([vec!["install".to_string()], args].concat())
     }
 }
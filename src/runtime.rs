@@ -4,22 +4,29 @@
 //! 而 `.crmrc` 文件里面存储的是关于 `Cargo` 配置的相关信息。
 
 use std::{
-    collections::{btree_map::Iter, BTreeMap},
+    collections::{btree_map::Iter, BTreeMap, BTreeSet},
     fs::read_to_string,
     iter::Chain,
-    path::PathBuf,
-    process,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use toml_edit::{table, value, Item, Table};
+use toml_edit::{table, value, Array, Item, Table};
 
 use crate::{
-    constants::{
-        CRMRC, CRMRC_FILE, CRMRC_PATH, DL, PLEASE_TRY, REGISTRY, RUST_LANG, SOURCE, TABLE,
+    backup::snapshot,
+    constants::{ALIAS, CRMRC, CRMRC_FILE, DISABLED, DL, REGISTRY, RUST_LANG, SOURCE},
+    description::{
+        RegistryDescription, DESCRIPTION, DISPLAY_NAME, HOMEPAGE, PRIORITY, PROTOCOL,
+        PROXIES_DOWNLOADS, REGION, TAGS,
     },
-    description::RegistryDescription,
+    diff::unified_diff,
+    error::{CrmError, CrmResult, Operation},
+    i18n::Msg,
+    lock::FileLock,
+    profile::{rebuild, Profile, CONFIG, PROFILE},
     toml::Toml,
-    utils::{append_end_spaces, home_dir, status_prefix, to_out},
+    utils::{append_end_spaces, home_dir, status_prefix, to_err},
 };
 
 /// 运行时配置
@@ -28,6 +35,9 @@ pub struct RuntimeConfig {
     /// 运行时配置的存放路径
     path: PathBuf,
 
+    /// 运行时配置的锁，只在修改运行时配置时持有，写入之后释放
+    lock: Option<Arc<FileLock>>,
+
     /// 用户自定义的配置
     config: Toml,
 
@@ -36,33 +46,78 @@ pub struct RuntimeConfig {
 
     /// 默认镜像的映射表
     default: BTreeMap<String, RegistryDescription>,
+
+    /// 配置方案的映射表
+    profiles: BTreeMap<String, Profile>,
+
+    /// 别名到镜像名的映射表
+    aliases: BTreeMap<String, String>,
+
+    /// 被禁用的镜像
+    disabled: BTreeSet<String>,
+
+    /// 获取镜像列表时是否包含被禁用的镜像
+    include_disabled: bool,
 }
 
 impl RuntimeConfig {
     /// 创建运行时配置对象
-    pub fn new() -> Self {
+    ///
+    /// 创建配置对象时不会加锁，修改运行时配置之前需要调用 `lock` 方法。
+    pub fn new() -> CrmResult<Self> {
         // 获取运行时配置的保存路径
-        let rc_path = home_dir().join(CRMRC);
+        let rc_path = home_dir()?.join(CRMRC);
 
         // 获取用户自定义镜像配置
-        let data = match read_to_string(&rc_path) {
-            Ok(content) => content,
-            Err(_) => String::new(),
-        };
+        let data = read_to_string(&rc_path).unwrap_or_default();
 
-        let extend = RuntimeConfig::parse(&data);
-        let default = RuntimeConfig::parse(CRMRC_FILE);
+        RuntimeConfig::from_content(rc_path, &data)
+    }
 
-        RuntimeConfig {
-            extend: RuntimeConfig::extract_to_map(&extend),
-            default: RuntimeConfig::extract_to_map(&default),
+    /// 根据运行时配置的路径和内容创建运行时配置对象
+    fn from_content(rc_path: PathBuf, data: &str) -> CrmResult<Self> {
+        let extend = RuntimeConfig::parse(&rc_path, data)?;
+        let default = RuntimeConfig::parse(&rc_path, CRMRC_FILE)?;
+
+        Ok(RuntimeConfig {
+            extend: RuntimeConfig::extract_to_map(&rc_path, &extend)?,
+            default: RuntimeConfig::extract_to_map(&rc_path, &default)?,
+            profiles: RuntimeConfig::extract_profiles(&rc_path, &extend)?,
+            aliases: RuntimeConfig::extract_aliases(&rc_path, &extend)?,
+            disabled: RuntimeConfig::extract_disabled(&rc_path, &extend)?,
+            include_disabled: false,
             path: rc_path,
+            lock: None,
             config: extend,
+        })
+    }
+
+    /// 对运行时配置加锁，并重新读取运行时配置，以免覆盖其他进程在此之前写入的内容
+    ///
+    /// 已经加锁时什么也不做，锁会在 `write` 写入运行时配置之后释放。
+    pub fn lock(&mut self) -> CrmResult<()> {
+        if self.lock.is_none() {
+            let lock = FileLock::acquire(&self.path)?;
+            let include_disabled = self.include_disabled;
+
+            *self = RuntimeConfig::new()?;
+            self.include_disabled = include_disabled;
+            self.lock = Some(lock);
         }
+
+        Ok(())
     }
 
-    /// 获取所有的镜像名 `Vec`
+    /// 获取所有的镜像名 `Vec`，默认不包含被禁用的镜像
     pub fn registry_names(&self) -> Vec<String> {
+        self.all_registry_names()
+            .into_iter()
+            .filter(|k| self.include_disabled || !self.is_disabled(k))
+            .collect()
+    }
+
+    /// 获取包含被禁用的镜像在内的所有镜像名 `Vec`
+    pub fn all_registry_names(&self) -> Vec<String> {
         self.default
             .iter()
             .chain(self.extend.iter())
@@ -70,6 +125,29 @@ impl RuntimeConfig {
             .collect()
     }
 
+    /// 设置获取镜像列表时是否包含被禁用的镜像
+    pub fn set_include_disabled(&mut self, include_disabled: bool) {
+        self.include_disabled = include_disabled;
+    }
+
+    /// 判断镜像是否被禁用
+    pub fn is_disabled(&self, registry_name: &str) -> bool {
+        self.disabled.contains(registry_name)
+    }
+
+    /// 获取所有被禁用的镜像名
+    pub fn disabled_names(&self) -> Vec<String> {
+        self.disabled.iter().cloned().collect()
+    }
+
+    /// 禁用或启用镜像
+    pub fn set_disabled(&mut self, registry_name: &str, disabled: bool) {
+        match disabled {
+            true => self.disabled.insert(registry_name.to_string()),
+            false => self.disabled.remove(registry_name),
+        };
+    }
+
     /// 将运行时配置中的镜像列表转换为字符串
     pub fn to_string(&self, current: &String, sep: Option<&str>) -> String {
         let sep = if sep.is_none() {
@@ -89,29 +167,131 @@ impl RuntimeConfig {
             .to_string()
     }
 
-    /// 将运行时配置中的镜像列表名转换为字符串
+    /// 将运行时配置中的镜像列表名转换为字符串，默认不包含被禁用的镜像
     pub fn to_key_string(&self) -> String {
-        let f = |key| format!("  - {}", key);
-        let v1 = self.default.keys().map(f);
-        let v2 = self.extend.keys().map(f);
+        self.registry_names()
+            .iter()
+            .map(|key| format!("  - {}", key))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 
-        v1.chain(v2).collect::<Vec<String>>().join("\n")
+    /// 获取将运行时配置写入到文件后，文件内容的变化
+    pub fn diff(&mut self) -> String {
+        self.convert_from_map();
+
+        unified_diff(
+            &read_to_string(&self.path).unwrap_or_default(),
+            &self.config.toml_string(),
+            &Msg::DiffCurrent.format(&[&self.path.display()]),
+            &Msg::DiffModified.format(&[&self.path.display()]),
+        )
     }
 
-    /// 将运行时配置写入到文件中
-    pub fn write(&mut self) {
+    /// 将运行时配置写入到文件中，写入之后释放锁
+    pub fn write(&mut self) -> CrmResult<()> {
         self.convert_from_map();
-        self.config.write(&self.path);
+        snapshot(&self.path)?;
+        self.config.write(&self.path)?;
+        self.lock = None;
+
+        Ok(())
     }
 
-    /// 获取运行时配置中的某一个属性
+    /// 获取运行时配置中的某一个属性，`registry_name` 也可以是镜像的别名
     pub fn get(&self, registry_name: &str) -> Option<&RegistryDescription> {
-        match self.get_extend(registry_name) {
-            None => self.get_default(registry_name),
+        let registry_name = self.resolve(registry_name);
+
+        match self.get_extend(&registry_name) {
+            None => self.get_default(&registry_name),
             v => v,
         }
     }
 
+    /// 将别名转换为镜像名，镜像名优先于同名的别名，不是别名时原样返回
+    pub fn resolve(&self, registry_name: &str) -> String {
+        let is_registry =
+            self.extend.contains_key(registry_name) || self.default.contains_key(registry_name);
+
+        match self.aliases.get(registry_name) {
+            Some(target) if !is_registry => target.to_string(),
+            _ => registry_name.to_string(),
+        }
+    }
+
+    /// 获取所有的别名及其指向的镜像
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// 添加/更新别名，别名不能与镜像同名，`registry_name` 也可以是另一个别名
+    pub fn save_alias(&mut self, alias: &str, registry_name: &str) -> CrmResult<()> {
+        let registry_name = self.resolve(registry_name);
+
+        if self.extend.contains_key(alias) || self.default.contains_key(alias) {
+            return Err(CrmError::InvalidValue(Msg::NameTaken.format(&[&alias])));
+        }
+
+        if self.get(&registry_name).is_none() {
+            return Err(CrmError::UnknownRegistry {
+                name: registry_name,
+                operation: Operation::Use,
+                candidates: self.registry_names(),
+            });
+        }
+
+        self.aliases.insert(alias.to_string(), registry_name);
+
+        Ok(())
+    }
+
+    /// 删除别名
+    pub fn remove_alias(&mut self, alias: &str) {
+        self.aliases.remove(alias);
+    }
+
+    /// 重命名用户自定义的镜像，同时更新指向该镜像的别名和配置方案，
+    /// 内置镜像不能重命名，新名称不能与已有的镜像或别名重复
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> CrmResult<()> {
+        if !self.extend.contains_key(old_name) {
+            return match self.default.contains_key(old_name) {
+                true => Err(CrmError::BuiltinRename(old_name.to_string())),
+                false => Err(CrmError::UnknownRegistry {
+                    name: old_name.to_string(),
+                    operation: Operation::Rename,
+                    candidates: vec![],
+                }),
+            };
+        }
+
+        if self.get(new_name).is_some() || self.aliases.contains_key(new_name) {
+            return Err(CrmError::InvalidValue(Msg::NameTaken.format(&[&new_name])));
+        }
+
+        let description = self.extend.remove(old_name).unwrap();
+
+        self.extend.insert(new_name.to_string(), description);
+
+        // 同名的内置镜像不受影响
+        if !self.default.contains_key(old_name) && self.disabled.remove(old_name) {
+            self.disabled.insert(new_name.to_string());
+        }
+
+        for target in self.aliases.values_mut() {
+            if target == old_name {
+                *target = new_name.to_string();
+            }
+        }
+
+        for profile in self.profiles.values_mut() {
+            if profile.registry == old_name {
+                profile.registry = new_name.to_string();
+            }
+        }
+
+        Ok(())
+    }
+
     /// 获取用户自定义运行时配置
     pub fn get_extend(&self, registry_name: &str) -> Option<&RegistryDescription> {
         self.extend.get(registry_name)
@@ -122,26 +302,54 @@ impl RuntimeConfig {
         self.default.get(registry_name)
     }
 
-    /// 添加/更新运行时配置中的属性
+    /// 添加/更新运行时配置中的属性，更新时会保留镜像的其他字段
     pub fn save(&mut self, registry_name: &str, registry_addr: &str, registry_dl: &str) {
-        self.extend.insert(
-            registry_name.to_string(),
-            RegistryDescription {
-                registry: registry_addr.to_string(),
-                dl: registry_dl.to_string(),
-            },
-        );
+        let description = self.extend.entry(registry_name.to_string()).or_default();
+
+        description.registry = registry_addr.to_string();
+        description.dl = registry_dl.to_string();
     }
 
-    /// 删除运行时配置中的属性
+    /// 删除运行时配置中的属性，同时删除指向该镜像的别名
     pub fn remove(&mut self, registry_name: &str) {
         self.extend.remove(registry_name);
+
+        // 同名的内置镜像仍然存在时，别名依然有效
+        if self.default.contains_key(registry_name) {
+            return;
+        }
+
+        self.aliases.retain(|_, target| target != registry_name);
+        self.disabled.remove(registry_name);
+    }
+
+    /// 获取所有的配置方案名
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// 获取配置方案
+    pub fn get_profile(&self, profile_name: &str) -> Option<&Profile> {
+        self.profiles.get(profile_name)
     }
 
-    /// 将镜像名称和镜像地址收集到元祖中，并返回一个元祖数组
+    /// 添加/更新配置方案
+    pub fn save_profile(&mut self, profile_name: &str, profile: Profile) {
+        self.profiles.insert(profile_name.to_string(), profile);
+    }
+
+    /// 删除配置方案
+    pub fn remove_profile(&mut self, profile_name: &str) {
+        self.profiles.remove(profile_name);
+    }
+
+    /// 将镜像名称和镜像地址收集到元祖中，并返回一个元祖数组，被禁用的镜像不包含在内
     pub fn to_tuples(&self, exclude_name: Option<&str>) -> Vec<(&str, &str)> {
         self.iter().fold(vec![], |mut memo, (k, v)| {
-            if k.eq(RUST_LANG) || (exclude_name.is_some() && k.eq(exclude_name.unwrap())) {
+            if k.eq(RUST_LANG)
+                || self.is_disabled(k)
+                || (exclude_name.is_some() && k.eq(exclude_name.unwrap()))
+            {
                 return memo;
             }
 
@@ -151,73 +359,217 @@ impl RuntimeConfig {
     }
 
     /// 创建迭代器
-    fn iter(&self) -> Chain<Iter<String, RegistryDescription>, Iter<String, RegistryDescription>> {
+    fn iter(
+        &self,
+    ) -> Chain<Iter<'_, String, RegistryDescription>, Iter<'_, String, RegistryDescription>> {
         self.default.iter().chain(self.extend.iter())
     }
 
     /// 将字符串解析为 `Toml` 对象
-    fn parse(data: &str) -> Toml {
-        let config = Toml::parse(data);
-
-        if config.is_err() {
-            to_out(format!("解析 {} 文件失败，{}", CRMRC_PATH, PLEASE_TRY));
-            process::exit(12);
-        }
-
-        let mut config = config.unwrap();
+    fn parse(path: &Path, data: &str) -> CrmResult<Toml> {
+        let mut config =
+            Toml::parse(data).map_err(|_| CrmError::ConfigParse(path.to_path_buf()))?;
         let data: &mut Table = config.table_mut();
+
         if data.contains_key(SOURCE) {
             let source: &Item = &data[SOURCE];
             if !source.is_table() {
-                to_out(format!(
-                    "{} 文件中的 {} 字段不是一个{}，{}",
-                    CRMRC_PATH, SOURCE, TABLE, PLEASE_TRY
-                ));
-                process::exit(12);
+                return Err(CrmError::FieldType {
+                    path: path.to_path_buf(),
+                    field: SOURCE.to_string(),
+                    expected: Msg::Table,
+                });
             }
         } else {
             data[SOURCE] = table();
         };
 
-        config
+        Ok(config)
     }
 
     /// 从配置转换为 `BTreeMap`
-    fn extract_to_map(config: &Toml) -> BTreeMap<String, RegistryDescription> {
+    fn extract_to_map(
+        path: &Path,
+        config: &Toml,
+    ) -> CrmResult<BTreeMap<String, RegistryDescription>> {
         let data = config.table();
         let source = data[SOURCE].as_table().unwrap();
         let mut map = BTreeMap::new();
 
-        source
-            .iter()
-            .for_each(|(key, value)| match value.as_table() {
-                Some(v) => {
-                    let r = v[REGISTRY].as_str();
-                    let d = v[DL].as_str();
-
-                    if r.is_none() || d.is_none() {
-                        to_out(format!(
-                            "{} 文件中的 [{}.{}] 里没有包含 {} 或 {} 字段, {}",
-                            CRMRC_PATH, SOURCE, key, REGISTRY, DL, PLEASE_TRY
-                        ));
-                        process::exit(12);
-                    }
-
-                    let registry = r.unwrap().to_string();
-                    let dl = d.unwrap().to_string();
-
-                    map.insert(key.to_string(), RegistryDescription::new(registry, dl));
+        for (key, value) in source.iter() {
+            let v = value.as_table().ok_or_else(|| CrmError::FieldType {
+                path: path.to_path_buf(),
+                field: format!("[{}.{}]", SOURCE, key),
+                expected: Msg::Table,
+            })?;
+
+            let (r, d) = match (v.get(REGISTRY), v.get(DL)) {
+                (Some(r), Some(d)) => (r.as_str(), d.as_str()),
+                _ => (None, None),
+            };
+
+            match (r, d) {
+                (Some(registry), Some(dl)) => {
+                    let mut description =
+                        RegistryDescription::new(registry.to_string(), dl.to_string());
+
+                    RuntimeConfig::extract_metadata(path, key, v, &mut description)?;
+                    map.insert(key.to_string(), description);
                 }
-                None => {
-                    to_out(format!(
-                        "{} 文件中的 {} 字段不是一个 {}, {}",
-                        CRMRC_PATH, key, TABLE, PLEASE_TRY
-                    ));
-                    process::exit(12);
+                _ => {
+                    return Err(CrmError::MissingField {
+                        path: path.to_path_buf(),
+                        field: format!("[{}.{}]", SOURCE, key),
+                    })
                 }
-            });
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// 从配置中读取所有的配置方案
+    fn extract_profiles(path: &Path, config: &Toml) -> CrmResult<BTreeMap<String, Profile>> {
+        let invalid = |field: String, expected: Msg| CrmError::FieldType {
+            path: path.to_path_buf(),
+            field,
+            expected,
+        };
+        let mut map = BTreeMap::new();
+        let Some(profiles) = config.table().get(PROFILE) else {
+            return Ok(map);
+        };
+        let profiles = profiles
+            .as_table()
+            .ok_or_else(|| invalid(PROFILE.to_string(), Msg::Table))?;
+
+        for (key, value) in profiles.iter() {
+            let field = format!("[{}.{}]", PROFILE, key);
+            let v = value
+                .as_table()
+                .ok_or_else(|| invalid(field.clone(), Msg::Table))?;
+            let registry = v
+                .get(REGISTRY)
+                .and_then(Item::as_str)
+                .ok_or_else(|| invalid(format!("{}.{}", field, REGISTRY), Msg::String))?;
+            let settings = match v.get(CONFIG) {
+                Some(settings) => settings
+                    .as_table()
+                    .cloned()
+                    .ok_or_else(|| invalid(format!("{}.{}", field, CONFIG), Msg::Table))?,
+                None => Table::new(),
+            };
+
+            map.insert(
+                key.to_string(),
+                Profile::new(registry.to_string(), settings),
+            );
+        }
+
+        Ok(map)
+    }
+
+    /// 从配置中读取所有的别名
+    fn extract_aliases(path: &Path, config: &Toml) -> CrmResult<BTreeMap<String, String>> {
+        let invalid = |field: String, expected: Msg| CrmError::FieldType {
+            path: path.to_path_buf(),
+            field,
+            expected,
+        };
+        let Some(aliases) = config.table().get(ALIAS) else {
+            return Ok(BTreeMap::new());
+        };
+
+        aliases
+            .as_table()
+            .ok_or_else(|| invalid(ALIAS.to_string(), Msg::Table))?
+            .iter()
+            .map(|(key, value)| match value.as_str() {
+                Some(target) => Ok((key.to_string(), target.to_string())),
+                None => Err(invalid(format!("[{}].{}", ALIAS, key), Msg::String)),
+            })
+            .collect()
+    }
 
-        map
+    /// 从配置中读取被禁用的镜像
+    fn extract_disabled(path: &Path, config: &Toml) -> CrmResult<BTreeSet<String>> {
+        let Some(disabled) = config.table().get(DISABLED) else {
+            return Ok(BTreeSet::new());
+        };
+
+        disabled
+            .as_array()
+            .and_then(|v| v.iter().map(|v| v.as_str().map(str::to_string)).collect())
+            .ok_or_else(|| CrmError::FieldType {
+                path: path.to_path_buf(),
+                field: DISABLED.to_string(),
+                expected: Msg::StringArray,
+            })
+    }
+
+    /// 读取镜像的可选字段，字段的类型不正确时返回错误
+    fn extract_metadata(
+        path: &Path,
+        key: &str,
+        table: &Table,
+        description: &mut RegistryDescription,
+    ) -> CrmResult<()> {
+        let invalid = |field: &str, expected: Msg| CrmError::FieldType {
+            path: path.to_path_buf(),
+            field: format!("[{}.{}].{}", SOURCE, key, field),
+            expected,
+        };
+        let string = |field: &str| -> CrmResult<Option<String>> {
+            table
+                .get(field)
+                .map(|v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| invalid(field, Msg::String))
+                })
+                .transpose()
+        };
+
+        description.display_name = string(DISPLAY_NAME)?;
+        description.description = string(DESCRIPTION)?;
+        description.region = string(REGION)?;
+        description.homepage = string(HOMEPAGE)?;
+
+        // 协议由地址的 `sparse+` 前缀决定，所以 `protocol` 字段不会被保存，与地址不一致时输出警告
+        if let Some(protocol) = table.get(PROTOCOL) {
+            if protocol.as_str() != Some(description.protocol()) {
+                to_err(Msg::ProtocolIgnored.format(&[
+                    &path.display(),
+                    &format!("[{}.{}].{}", SOURCE, key, PROTOCOL),
+                    &description.protocol(),
+                ]));
+            }
+        }
+
+        if let Some(tags) = table.get(TAGS) {
+            description.tags = tags
+                .as_array()
+                .and_then(|v| v.iter().map(|v| v.as_str().map(str::to_string)).collect())
+                .ok_or_else(|| invalid(TAGS, Msg::StringArray))?;
+        }
+
+        description.proxies_downloads = table
+            .get(PROXIES_DOWNLOADS)
+            .map(|v| {
+                v.as_bool()
+                    .ok_or_else(|| invalid(PROXIES_DOWNLOADS, Msg::Boolean))
+            })
+            .transpose()?;
+        description.priority = table
+            .get(PRIORITY)
+            .map(|v| {
+                v.as_integer()
+                    .and_then(|v| u64::try_from(v).ok())
+                    .ok_or_else(|| invalid(PRIORITY, Msg::Integer))
+            })
+            .transpose()?;
+
+        Ok(())
     }
 
     /// 从 `BTreeMap` 转换为配置
@@ -227,17 +579,216 @@ impl RuntimeConfig {
         let source = config[SOURCE].as_table_mut().unwrap();
 
         self.extend.iter().for_each(|(k, v)| {
-            let RegistryDescription { registry, dl } = v;
-
             source[k] = table();
-            source[k][REGISTRY] = value(registry.to_string());
-            source[k][DL] = value(dl.to_string());
+            source[k][REGISTRY] = value(v.registry.to_string());
+            source[k][DL] = value(v.dl.to_string());
+
+            let strings = [
+                (DISPLAY_NAME, &v.display_name),
+                (DESCRIPTION, &v.description),
+                (REGION, &v.region),
+                (HOMEPAGE, &v.homepage),
+            ];
+
+            for (field, text) in strings {
+                if let Some(text) = text {
+                    source[k][field] = value(text.to_string());
+                }
+            }
+
+            if !v.tags.is_empty() {
+                source[k][TAGS] = value(v.tags.iter().collect::<Array>());
+            }
+
+            if let Some(proxies_downloads) = v.proxies_downloads {
+                source[k][PROXIES_DOWNLOADS] = value(proxies_downloads);
+            }
+
+            if let Some(priority) = v.priority {
+                source[k][PRIORITY] = value(priority as i64);
+            }
+        });
+
+        if self.disabled.is_empty() {
+            config.remove(DISABLED);
+        } else {
+            config[DISABLED] = value(self.disabled.iter().collect::<Array>());
+        }
+
+        if self.aliases.is_empty() {
+            config.remove(ALIAS);
+        } else {
+            config[ALIAS] = table();
+
+            for (alias, target) in &self.aliases {
+                config[ALIAS][alias] = value(target.to_string());
+            }
+        }
+
+        if self.profiles.is_empty() {
+            config.remove(PROFILE);
+            return;
+        }
+
+        let mut profiles = Table::new();
+        profiles.set_implicit(true);
+
+        self.profiles.iter().for_each(|(k, v)| {
+            profiles[k] = table();
+            profiles[k][REGISTRY] = value(v.registry.to_string());
+            profiles[k][CONFIG] = Item::Table(rebuild(&v.config));
         });
+
+        config[PROFILE] = Item::Table(profiles);
     }
 }
 
-impl Default for RuntimeConfig {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(content: &str) -> RuntimeConfig {
+        RuntimeConfig::from_content(PathBuf::from("/nonexistent/.crmrc"), content).unwrap()
+    }
+
+    #[test]
+    fn disabled_registries_are_hidden() {
+        let mut rc = config("disabled = [\"tuna\"]\n");
+
+        assert!(rc.is_disabled("tuna"));
+        assert!(rc.get("tuna").is_some());
+        assert!(!rc.registry_names().contains(&"tuna".to_string()));
+        assert!(rc.all_registry_names().contains(&"tuna".to_string()));
+        assert!(!rc.to_key_string().contains("  - tuna\n"));
+        assert!(rc.to_tuples(None).iter().all(|(name, _)| *name != "tuna"));
+
+        rc.set_include_disabled(true);
+
+        assert!(rc.registry_names().contains(&"tuna".to_string()));
+    }
+
+    #[test]
+    fn disable_and_enable_are_written_back() {
+        let mut rc = config("");
+
+        rc.set_disabled("sjtu", true);
+        rc.set_disabled("tuna", true);
+        rc.set_disabled("sjtu", false);
+        rc.convert_from_map();
+
+        assert_eq!(rc.disabled_names(), ["tuna"]);
+        assert!(rc.config.toml_string().contains("disabled = [\"tuna\"]"));
+
+        rc.set_disabled("tuna", false);
+        rc.convert_from_map();
+
+        assert!(!rc.config.toml_string().contains("disabled"));
+    }
+
+    #[test]
+    fn removing_a_custom_registry_enables_it() {
+        let mut rc = config(
+            "disabled = [\"mine\"]\n\n[source.mine]\nregistry = \"https://m/git\"\ndl = \"https://m/dl\"\n",
+        );
+
+        rc.remove("mine");
+
+        assert!(!rc.is_disabled("mine"));
+    }
+
+    const MINE: &str = "[source.mine]\nregistry = \"https://m/git\"\ndl = \"https://m/dl\"\n";
+
+    #[test]
+    fn rename_rejects_builtin_and_unknown_names() {
+        let mut rc = config(MINE);
+
+        assert!(matches!(
+            rc.rename("tuna", "tuna2"),
+            Err(CrmError::BuiltinRename(name)) if name == "tuna"
+        ));
+        assert!(matches!(
+            rc.rename("missing", "other"),
+            Err(CrmError::UnknownRegistry { name, .. }) if name == "missing"
+        ));
+        assert!(rc.get("tuna").is_some());
+        assert!(rc.get("tuna2").is_none());
+    }
+
+    #[test]
+    fn rename_rejects_taken_names() {
+        let mut rc = config(&format!("{}\n[alias]\nfast = \"tuna\"\n", MINE));
+
+        assert!(matches!(
+            rc.rename("mine", "tuna"),
+            Err(CrmError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            rc.rename("mine", "fast"),
+            Err(CrmError::InvalidValue(_))
+        ));
+        assert!(rc.get_extend("mine").is_some());
+    }
+
+    #[test]
+    fn rename_moves_aliases_disabled_and_profiles() {
+        let mut rc = config(&format!(
+            "disabled = [\"mine\"]\n\n{}\n[alias]\nm = \"mine\"\nt = \"tuna\"\n\n[profile.work]\nregistry = \"mine\"\n",
+            MINE
+        ));
+
+        rc.rename("mine", "ours").unwrap();
+
+        assert!(rc.get_extend("mine").is_none());
+        assert_eq!(rc.get("ours").unwrap().registry, "https://m/git");
+        assert_eq!(rc.aliases()["m"], "ours");
+        assert_eq!(rc.aliases()["t"], "tuna");
+        assert_eq!(rc.get("m").unwrap().registry, "https://m/git");
+        assert!(!rc.is_disabled("mine"));
+        assert!(rc.is_disabled("ours"));
+        assert_eq!(rc.get_profile("work").unwrap().registry, "ours");
+    }
+
+    #[test]
+    fn save_alias_validates_names() {
+        let mut rc = config(MINE);
+
+        assert!(matches!(
+            rc.save_alias("tuna", "mine"),
+            Err(CrmError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            rc.save_alias("mine", "tuna"),
+            Err(CrmError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            rc.save_alias("x", "missing"),
+            Err(CrmError::UnknownRegistry { .. })
+        ));
+
+        rc.save_alias("fast", "mine").unwrap();
+        rc.save_alias("faster", "fast").unwrap();
+
+        assert_eq!(rc.aliases()["faster"], "mine");
+    }
+
+    #[test]
+    fn resolve_prefers_registry_names() {
+        let mut rc = config(&format!("{}\n[alias]\nfast = \"mine\"\n", MINE));
+
+        assert_eq!(rc.resolve("fast"), "mine");
+        assert_eq!(rc.resolve("unknown"), "unknown");
+
+        rc.save("fast", "https://f/git", "https://f/dl");
+
+        assert_eq!(rc.resolve("fast"), "fast");
+        assert_eq!(rc.get("fast").unwrap().registry, "https://f/git");
+    }
+
+    #[test]
+    fn rejects_invalid_disabled_field() {
+        let path = PathBuf::from("/nonexistent/.crmrc");
+
+        assert!(RuntimeConfig::from_content(path.clone(), "disabled = \"tuna\"\n").is_err());
+        assert!(RuntimeConfig::from_content(path, "disabled = [1]\n").is_err());
     }
 }
@@ -0,0 +1,242 @@
+//! # `sparse` 索引检查
+//!
+//! 仅仅能够连上镜像的主机并不代表镜像是可用的。该模块会按照 `sparse` 协议请求镜像的 `config.json`，
+//! 检查其中的 `dl` 是否与 `.crmrc` 中配置的一致，然后请求某个软件包的索引文件，
+//! 并确认它的每一行都是描述该软件包的某个版本的 `JSON` 对象。
+//!
+//! 除此之外，该模块还会将镜像中一些经常发布新版本的软件包的索引与官方索引进行比较，
+//! 以此来判断镜像是否停止了同步，以及落后了多少个版本。
+//!
+//! `crm verify` 则会从镜像索引和官方索引中读取软件包的 `cksum`，用于校验从镜像下载的软件包。
+
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    constants::OFFICIAL_SPARSE_INDEX,
+    i18n::Msg,
+    json::Json,
+    utils::{crate_prefix, fetch},
+};
+
+/// 检查通过时的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexProbe {
+    /// `config.json` 中的 `dl` 字段
+    pub dl: String,
+
+    /// `config.json` 中的 `api` 字段
+    pub api: Option<String>,
+
+    /// 索引文件中软件包的版本数量
+    pub versions: usize,
+}
+
+/// 检查的结果，失败时为失败的原因
+pub type IndexCheck = Result<IndexProbe, String>;
+
+/// 获取 `sparse` 镜像的索引地址，以 `/` 结尾。如果不是 `sparse` 镜像，则返回 `None`
+pub fn index_root(registry: &str) -> Option<String> {
+    let root = registry.strip_prefix("sparse+")?;
+
+    Some(format!("{}/", root.trim_end_matches('/')))
+}
+
+/// 获取软件包的索引文件在索引中的路径，例如 `serde` 的路径是 `se/rd/serde`
+pub fn index_path(krate: &str) -> String {
+    let name = krate.to_lowercase();
+
+    format!("{}/{}", crate_prefix(&name), name)
+}
+
+/// 去掉 `url` 末尾的 `/`，以便比较
+fn normalize(url: &str) -> &str {
+    url.trim_end_matches('/')
+}
+
+/// 检查 `sparse` 镜像的 `config.json` 和 `krate` 的索引文件
+///
+/// `registry` 是 `.crmrc` 中配置的镜像地址，`expected_dl` 是 `.crmrc` 中配置的 `dl`。
+pub fn probe(registry: &str, expected_dl: &str, krate: &str, timeout: Duration) -> IndexCheck {
+    let root = index_root(registry).ok_or_else(|| Msg::NotSparse.to_string())?;
+
+    // 检查 `config.json`
+    let config = Json::parse(&fetch(&format!("{}config.json", root), timeout)?)
+        .map_err(|e| Msg::ConfigJsonInvalid.format(&[&e]))?;
+    let dl = config
+        .get("dl")
+        .and_then(Json::as_str)
+        .ok_or_else(|| Msg::ConfigJsonMissingDl.to_string())?;
+    let api = config.get("api").and_then(Json::as_str).map(str::to_string);
+
+    if normalize(dl) != normalize(expected_dl) {
+        return Err(Msg::DlMismatch.format(&[&dl, &expected_dl]));
+    }
+
+    Ok(IndexProbe {
+        dl: dl.to_string(),
+        api,
+        versions: index_entries(&root, krate, timeout)?.len(),
+    })
+}
+
+/// 获取 `krate` 在索引中的所有条目，按照发布的顺序排列
+///
+/// 索引文件的每一行都必须是包含 `name` 和 `vers` 字段的 `JSON` 对象，并且至少有一行。
+pub fn index_entries(root: &str, krate: &str, timeout: Duration) -> Result<Vec<Json>, String> {
+    let path = index_path(krate);
+    let content = fetch(&format!("{}{}", root, path), timeout)?;
+    let mut entries = vec![];
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry =
+            Json::parse(line).map_err(|e| Msg::IndexLineInvalid.format(&[&path, &(i + 1), &e]))?;
+        let name = entry.get("name").and_then(Json::as_str);
+        let vers = entry.get("vers").and_then(Json::as_str);
+
+        if !name.is_some_and(|v| v.eq_ignore_ascii_case(krate)) || vers.is_none() {
+            return Err(Msg::IndexLineInvalid.format(&[&path, &(i + 1), &"name/vers"]));
+        }
+
+        entries.push(entry);
+    }
+
+    if entries.is_empty() {
+        return Err(Msg::IndexEmpty.format(&[&path]));
+    }
+
+    Ok(entries)
+}
+
+/// 获取 `krate` 在索引中的所有版本，按照发布的顺序排列
+pub fn index_versions(root: &str, krate: &str, timeout: Duration) -> Result<Vec<String>, String> {
+    Ok(index_entries(root, krate, timeout)?
+        .iter()
+        .filter_map(|v| v.get("vers").and_then(Json::as_str).map(str::to_string))
+        .collect())
+}
+
+/// 获取 `krate` 的 `version` 版本在索引中的 `cksum`
+pub fn index_cksum(
+    root: &str,
+    krate: &str,
+    version: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    index_entries(root, krate, timeout)?
+        .iter()
+        .find(|v| v.get("vers").and_then(Json::as_str) == Some(version))
+        .and_then(|v| v.get("cksum").and_then(Json::as_str))
+        .map(str::to_lowercase)
+        .ok_or_else(|| Msg::VersionNotInIndex.format(&[&krate, &version, &index_path(krate)]))
+}
+
+/// 某个软件包在镜像中落后于官方索引的情况
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lag {
+    /// 软件包名
+    pub krate: String,
+
+    /// 镜像中缺少的版本数量
+    pub missing: usize,
+
+    /// 镜像中最新发布的版本
+    pub newest: String,
+
+    /// 官方索引中最新发布的版本
+    pub upstream: String,
+}
+
+/// 镜像索引的同步情况
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Freshness {
+    /// 每个软件包的同步情况
+    pub lags: Vec<Lag>,
+}
+
+impl Freshness {
+    /// 镜像中缺少的版本总数，`0` 表示已经同步
+    pub fn missing(&self) -> usize {
+        self.lags.iter().map(|v| v.missing).sum()
+    }
+}
+
+/// 同步情况的检查结果，失败时为失败的原因
+pub type FreshnessCheck = Result<Freshness, String>;
+
+/// 从官方索引中获取 `crates` 中每个软件包的所有版本
+pub fn upstream_versions(
+    crates: &[String],
+    timeout: Duration,
+) -> Result<Vec<(String, Vec<String>)>, String> {
+    crates
+        .iter()
+        .map(|krate| {
+            index_versions(OFFICIAL_SPARSE_INDEX, krate, timeout)
+                .map(|versions| (krate.to_string(), versions))
+                .map_err(|e| Msg::UpstreamFailed.format(&[&e]))
+        })
+        .collect()
+}
+
+/// 将 `root` 索引与官方索引中的版本进行比较
+pub fn freshness(
+    root: &str,
+    upstream: &[(String, Vec<String>)],
+    timeout: Duration,
+) -> FreshnessCheck {
+    let mut lags = vec![];
+
+    for (krate, versions) in upstream {
+        let mirrored: HashSet<String> = index_versions(root, krate, timeout)?.into_iter().collect();
+        let missing = versions.iter().filter(|v| !mirrored.contains(*v)).count();
+
+        // 索引文件是按照发布的顺序追加的，所以最后一行就是最新发布的版本
+        let newest = versions
+            .iter()
+            .rev()
+            .find(|v| mirrored.contains(*v))
+            .cloned()
+            .unwrap_or_default();
+
+        lags.push(Lag {
+            krate: krate.to_string(),
+            missing,
+            newest,
+            upstream: versions.last().cloned().unwrap_or_default(),
+        });
+    }
+
+    Ok(Freshness { lags })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("serde"), "se/rd/serde");
+        assert_eq!(index_path("Serde_JSON"), "se/rd/serde_json");
+        assert_eq!(crate_prefix("cfg-if"), "cf/g-");
+    }
+
+    #[test]
+    fn index_roots() {
+        assert_eq!(
+            index_root("sparse+https://m.cn/index").as_deref(),
+            Some("https://m.cn/index/")
+        );
+        assert_eq!(
+            index_root("sparse+https://m.cn/index//").as_deref(),
+            Some("https://m.cn/index/")
+        );
+        assert_eq!(index_root("https://m.cn/git/index"), None);
+    }
+}
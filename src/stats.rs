@@ -0,0 +1,238 @@
+//! # 延迟统计
+//!
+//! 单次网络请求的耗时很容易受到网络抖动的影响，所以 `crm test` 和 `crm best` 可以通过 `--samples <n>`
+//! 参数对每个镜像进行多次测试，然后根据所有样本计算最小值、中位数、`p90`、抖动和成功率。
+//!
+//! 对镜像进行排名时使用的是中位数，并且每一次失败的测试都会按照超时时间的比例增加惩罚，
+//! 这样偶尔失败的镜像会排在稳定的镜像后面，但仍然优于全部失败的镜像。
+//!
+//! 除了延迟之外，`crm bench` 还可以通过下载较大的软件包来测试镜像的下载速度 (吞吐量)，
+//! `crm best --by throughput` 会选择下载速度最快的镜像。
+
+use std::{str::FromStr, time::Duration};
+
+use crate::{error::CrmError, i18n::Msg, net::NetError, utils::Delay};
+
+/// 对镜像进行排名的依据
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rank {
+    /// 延迟，越小越好
+    #[default]
+    Latency,
+
+    /// 下载速度，越大越好
+    Throughput,
+}
+
+impl FromStr for Rank {
+    type Err = CrmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "latency" => Ok(Rank::Latency),
+            "throughput" => Ok(Rank::Throughput),
+            _ => Err(CrmError::InvalidValue(Msg::InvalidRank.format(&[&s]))),
+        }
+    }
+}
+
+/// 下载测试的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throughput {
+    /// 下载的字节数
+    pub bytes: u64,
+
+    /// 下载所用的时间
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    /// 每秒下载的字节数
+    pub fn bytes_per_sec(&self) -> u64 {
+        (self.bytes as f64 / self.elapsed.as_secs_f64().max(0.001)) as u64
+    }
+
+    /// 每秒下载的兆字节数 (MB/s)
+    pub fn mbps(&self) -> f64 {
+        self.bytes_per_sec() as f64 / 1_000_000.0
+    }
+}
+
+/// 下载测试的结果，失败时为失败的原因
+pub type Bandwidth = Result<Throughput, NetError>;
+
+/// 对同一个镜像进行多次测试的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// 按测试顺序排列的所有样本
+    samples: Vec<Delay>,
+}
+
+impl Stats {
+    /// 根据按测试顺序排列的样本创建统计结果
+    pub fn new(samples: Vec<Delay>) -> Self {
+        Stats { samples }
+    }
+
+    /// 获取所有成功的样本的耗时
+    fn successes(&self) -> Vec<u128> {
+        self.samples.iter().filter_map(|v| v.clone().ok()).collect()
+    }
+
+    /// 获取排序后的成功样本的耗时
+    fn sorted(&self) -> Vec<u128> {
+        let mut delays = self.successes();
+        delays.sort_unstable();
+        delays
+    }
+
+    /// 按测试顺序排列的所有样本
+    pub fn samples(&self) -> &[Delay] {
+        &self.samples
+    }
+
+    /// 样本的数量
+    pub fn count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 成功的样本的数量
+    pub fn success_count(&self) -> usize {
+        self.samples.iter().filter(|v| v.is_ok()).count()
+    }
+
+    /// 成功率，取值范围为 `0.0` 到 `1.0`
+    pub fn success_rate(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+
+        self.success_count() as f64 / self.count() as f64
+    }
+
+    /// 最小的耗时
+    pub fn min(&self) -> Option<u128> {
+        self.successes().into_iter().min()
+    }
+
+    /// 耗时的中位数，样本数为偶数时取中间两个值的平均值
+    pub fn median(&self) -> Option<u128> {
+        let delays = self.sorted();
+        let mid = delays.len() / 2;
+
+        match delays.len() {
+            0 => None,
+            n if n % 2 == 0 => Some((delays[mid - 1] + delays[mid]) / 2),
+            _ => Some(delays[mid]),
+        }
+    }
+
+    /// 耗时的第 90 百分位数 (最近秩法)
+    pub fn p90(&self) -> Option<u128> {
+        let delays = self.sorted();
+        let rank = (delays.len() * 9).div_ceil(10);
+
+        delays.get(rank.checked_sub(1)?).copied()
+    }
+
+    /// 抖动，即相邻两次成功的测试之间耗时差值的平均值
+    pub fn jitter(&self) -> Option<u128> {
+        let delays = self.successes();
+
+        if delays.is_empty() {
+            return None;
+        }
+
+        let diffs: Vec<u128> = delays.windows(2).map(|v| v[0].abs_diff(v[1])).collect();
+
+        Some(diffs.iter().sum::<u128>() / diffs.len().max(1) as u128)
+    }
+
+    /// 最后一次失败的原因
+    pub fn error(&self) -> Option<&NetError> {
+        self.samples.iter().rev().find_map(|v| v.as_ref().err())
+    }
+
+    /// 用于排名的分数，越小越好，所有的测试都失败时返回 `None`
+    ///
+    /// 分数为中位数加上失败惩罚，失败惩罚为失败率乘以超时时间。
+    pub fn score(&self, timeout: Duration) -> Option<u128> {
+        let median = self.median()?;
+        let failures = (self.count() - self.success_count()) as u128;
+        let penalty = timeout.as_millis() * failures / self.count() as u128;
+
+        Some(median + penalty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(samples: &[Result<u128, &str>]) -> Stats {
+        Stats::new(
+            samples
+                .iter()
+                .map(|v| v.map_err(|e| e.to_string().into()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn empty_samples() {
+        let stats = stats(&[]);
+
+        assert_eq!(stats.success_rate(), 0.0);
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.median(), None);
+        assert_eq!(stats.p90(), None);
+        assert_eq!(stats.jitter(), None);
+        assert_eq!(stats.error(), None);
+        assert_eq!(stats.score(Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn one_sample() {
+        let stats = stats(&[Ok(120)]);
+
+        assert_eq!(stats.median(), Some(120));
+        assert_eq!(stats.p90(), Some(120));
+        assert_eq!(stats.jitter(), Some(0));
+        assert_eq!(stats.score(Duration::from_secs(1)), Some(120));
+    }
+
+    #[test]
+    fn median_and_p90() {
+        assert_eq!(stats(&[Ok(30), Ok(10), Ok(20)]).median(), Some(20));
+        assert_eq!(stats(&[Ok(40), Ok(10), Ok(30), Ok(20)]).median(), Some(25));
+
+        let ten: Vec<Result<u128, &str>> = (1..=10).rev().map(Ok).collect();
+
+        assert_eq!(stats(&ten).p90(), Some(9));
+        assert_eq!(stats(&[Ok(1), Ok(2)]).p90(), Some(2));
+    }
+
+    #[test]
+    fn jitter_skips_failures() {
+        let stats = stats(&[Ok(10), Err("timeout"), Ok(20), Ok(15)]);
+
+        assert_eq!(stats.jitter(), Some(7));
+        assert_eq!(stats.min(), Some(10));
+        assert_eq!(stats.success_count(), 3);
+        assert_eq!(stats.success_rate(), 0.75);
+    }
+
+    #[test]
+    fn score_penalizes_failures() {
+        let timeout = Duration::from_millis(1000);
+
+        assert_eq!(stats(&[Ok(100), Err("a")]).score(timeout), Some(600));
+        assert_eq!(stats(&[Err("a"), Err("b")]).score(timeout), None);
+        assert_eq!(
+            stats(&[Err("a"), Err("b")])
+                .error()
+                .map(ToString::to_string),
+            Some("b".to_string())
+        );
+    }
+}
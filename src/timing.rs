@@ -0,0 +1,376 @@
+//! # 分阶段计时
+//!
+//! `crm test` 输出的延迟只有一个数字，无法区分镜像慢在 DNS 解析、网络距离还是源站。
+//! `crm test --detailed` 会通过该模块直接建立连接并发送请求，分别记录 DNS 解析、TCP 连接、
+//! TLS 握手、首字节 (TTFB) 和响应体传输的耗时。
+//!
+//! 使用代理时，各个阶段的耗时反映的是到代理的连接，而不是到镜像的连接，所以会使用代理的地址不进行分阶段计时。
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use ureq::rustls::{pki_types::ServerName, ClientConnection, StreamOwned};
+
+use crate::{
+    constants::{APP_NAME, APP_VERSION},
+    i18n::Msg,
+    net,
+};
+
+/// 各个阶段的耗时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// DNS 解析
+    pub dns: Duration,
+
+    /// TCP 连接
+    pub tcp: Duration,
+
+    /// TLS 握手，`http` 地址为 `None`
+    pub tls: Option<Duration>,
+
+    /// 从发送请求到收到响应的第一个字节
+    pub ttfb: Duration,
+
+    /// 接收剩余的响应
+    pub body: Duration,
+
+    /// 响应的状态码
+    pub status: u16,
+}
+
+impl Timing {
+    /// 所有阶段的总耗时
+    pub fn total(&self) -> Duration {
+        self.dns + self.tcp + self.tls.unwrap_or_default() + self.ttfb + self.body
+    }
+}
+
+/// 计时的结果，失败时为失败的原因
+pub type TimingCheck = Result<Timing, String>;
+
+/// 请求的目标
+struct Target {
+    /// 是否使用 `TLS`
+    https: bool,
+
+    /// 主机名，不包括端口和 `IPv6` 地址两边的方括号
+    host: String,
+
+    /// 端口
+    port: u16,
+
+    /// `Host` 请求头的值
+    authority: String,
+
+    /// 请求的路径，包括查询参数
+    path: String,
+}
+
+/// 解析 `url`，只支持 `http` 和 `https` 地址
+fn target(url: &str) -> Result<Target, String> {
+    let invalid = || Msg::TimingInvalidUrl.format(&[&url]);
+    let (scheme, rest) = url.split_once("://").ok_or_else(invalid)?;
+    let https = match scheme.to_lowercase().as_str() {
+        "https" => true,
+        "http" => false,
+        _ => return Err(invalid()),
+    };
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    let authority = authority.rsplit_once('@').map_or(authority, |v| v.1);
+
+    // `IPv6` 地址的格式为 `[::1]:8080`
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(v) => {
+            let (host, port) = v.split_once(']').ok_or_else(invalid)?;
+
+            (host, port.strip_prefix(':'))
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid())?,
+        None if https => 443,
+        None => 80,
+    };
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Target {
+        https,
+        host: host.to_string(),
+        port,
+        authority: authority.to_string(),
+        path: match path.starts_with('/') {
+            true => path.to_string(),
+            false => format!("/{}", path),
+        },
+    })
+}
+
+/// 返回距离上一次调用经过的时间，并重新开始计时
+fn lap(mark: &mut Instant) -> Duration {
+    let now = Instant::now();
+    let elapsed = now - *mark;
+
+    *mark = now;
+    elapsed
+}
+
+/// 在 `headers` 中查找 `Content-Length` 请求头
+fn content_length(headers: &str) -> Option<usize> {
+    headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+
+        match name.trim().eq_ignore_ascii_case("content-length") {
+            true => value.trim().parse().ok(),
+            false => None,
+        }
+    })
+}
+
+/// 将读写连接时的错误转换为错误信息，超时的时候统一提示请求超时
+fn stream_error(e: io::Error) -> String {
+    match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => Msg::TimingTimedOut.to_string(),
+        _ => net::io_error(&e).to_string(),
+    }
+}
+
+/// 发送请求并接收响应，返回首字节的耗时、接收剩余响应的耗时和状态码
+fn exchange<S: Read + Write>(
+    mut stream: S,
+    target: &Target,
+    deadline: Instant,
+) -> Result<(Duration, Duration, u16), String> {
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}/{}\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+        target.path, target.authority, APP_NAME, APP_VERSION
+    );
+    let mut mark = Instant::now();
+
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(stream_error)?;
+
+    let mut buf = [0; 16 * 1024];
+    let mut data = vec![];
+    let mut ttfb = None;
+    let mut expected = None;
+
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+
+            // 服务器在关闭连接时没有发送 `close_notify`
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && ttfb.is_some() => break,
+            Err(e) => return Err(stream_error(e)),
+        }
+
+        if ttfb.is_none() {
+            ttfb = Some(lap(&mut mark));
+        }
+
+        // 响应头接收完毕之后，根据 `Content-Length` 判断响应体是否接收完毕，否则一直读取到连接关闭
+        if expected.is_none() {
+            expected = data.windows(4).position(|v| v == b"\r\n\r\n").map(|i| {
+                content_length(&String::from_utf8_lossy(&data[..i])).map(|len| i + 4 + len)
+            });
+        }
+
+        if let Some(Some(len)) = expected {
+            if data.len() >= len {
+                break;
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(Msg::TimingTimedOut.to_string());
+        }
+    }
+
+    let ttfb = ttfb.ok_or_else(|| Msg::TimingEmptyResponse.to_string())?;
+    let status = String::from_utf8_lossy(&data)
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1)?.parse().ok())
+        .ok_or_else(|| Msg::TimingInvalidResponse.to_string())?;
+
+    Ok((ttfb, lap(&mut mark), status))
+}
+
+/// 获取距离 `deadline` 的剩余时间，已经超时时返回错误
+fn remaining(deadline: Instant) -> Result<Duration, String> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|v| !v.is_zero())
+        .ok_or_else(|| Msg::TimingTimedOut.to_string())
+}
+
+/// 请求 `url`，并记录各个阶段的耗时，不会跟随重定向
+///
+/// 整个请求不会超过 `timeout`，建立连接、发送请求和接收响应都只能使用剩余的时间。
+/// DNS 解析使用的是系统的超时时间，但解析完成时已经超时的话，不会再建立连接。
+pub fn measure(url: &str, timeout: Duration) -> TimingCheck {
+    if net::is_proxied(url) {
+        return Err(Msg::TimingProxied.to_string());
+    }
+
+    let target = target(url)?;
+    let deadline = Instant::now() + timeout;
+    let mut mark = Instant::now();
+
+    let addrs: Vec<_> = (target.host.as_str(), target.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("DNS: {}", e))?
+        .collect();
+    let dns = lap(&mut mark);
+
+    // 依次尝试解析出的每一个地址，每次连接都只能使用剩余的时间
+    let mut last = None;
+    let mut stream = None;
+
+    for addr in &addrs {
+        match TcpStream::connect_timeout(addr, remaining(deadline)?) {
+            Ok(v) => {
+                stream = Some(v);
+                break;
+            }
+            Err(e) => last = Some(e),
+        }
+    }
+
+    let mut stream = stream.ok_or_else(|| match last {
+        Some(e) => format!("TCP: {}", e),
+        None => Msg::TimingNoAddress.format(&[&target.host]),
+    })?;
+    let tcp = lap(&mut mark);
+
+    let left = remaining(deadline)?;
+
+    stream
+        .set_read_timeout(Some(left))
+        .and_then(|_| stream.set_write_timeout(Some(left)))
+        .map_err(|e| e.to_string())?;
+
+    if !target.https {
+        let (ttfb, body, status) = exchange(stream, &target, deadline)?;
+
+        return Ok(Timing {
+            dns,
+            tcp,
+            tls: None,
+            ttfb,
+            body,
+            status,
+        });
+    }
+
+    let name = ServerName::try_from(target.host.clone()).map_err(|e| e.to_string())?;
+    let mut conn =
+        ClientConnection::new(net::tls_client_config()?, name).map_err(|e| e.to_string())?;
+
+    while conn.is_handshaking() {
+        conn.complete_io(&mut stream).map_err(stream_error)?;
+    }
+
+    let tls = lap(&mut mark);
+    let (ttfb, body, status) = exchange(StreamOwned::new(conn, stream), &target, deadline)?;
+
+    Ok(Timing {
+        dns,
+        tcp,
+        tls: Some(tls),
+        ttfb,
+        body,
+        status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Target {
+        target(url).unwrap()
+    }
+
+    #[test]
+    fn parses_targets() {
+        let plain = parse("https://example.com");
+
+        assert!(plain.https);
+        assert_eq!(
+            (plain.host.as_str(), plain.port, plain.path.as_str()),
+            ("example.com", 443, "/")
+        );
+
+        let ipv6 = parse("HTTP://user@[::1]:8080/a/b?c=1#d");
+
+        assert!(!ipv6.https);
+        assert_eq!(
+            (ipv6.host.as_str(), ipv6.port, ipv6.authority.as_str()),
+            ("::1", 8080, "[::1]:8080")
+        );
+        assert_eq!(ipv6.path, "/a/b?c=1");
+        assert_eq!(parse("http://h?x=1").path, "/?x=1");
+        assert_eq!(parse("http://h:81").port, 81);
+    }
+
+    #[test]
+    fn rejects_invalid_targets() {
+        for url in [
+            "example.com",
+            "ftp://example.com",
+            "http://",
+            "http://:80/",
+            "http://h:port",
+            "http://h:70000",
+            "http://[::1",
+        ] {
+            assert!(target(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn finds_content_length() {
+        assert_eq!(
+            content_length("HTTP/1.1 200 OK\r\ncontent-length: 12\r\n"),
+            Some(12)
+        );
+        assert_eq!(content_length("HTTP/1.1 200 OK\r\n"), None);
+    }
+
+    #[test]
+    fn remaining_time_until_deadline() {
+        let now = Instant::now();
+
+        assert!(remaining(now + Duration::from_secs(5)).unwrap() <= Duration::from_secs(5));
+        assert!(remaining(now).is_err());
+    }
+
+    #[test]
+    fn measure_stops_at_deadline() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let start = Instant::now();
+
+        // 服务器接受连接之后不返回任何数据
+        assert!(measure(&url, Duration::from_millis(300)).is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -3,14 +3,16 @@
 //! 该模块用来解析 `toml` 文件，可以对 `toml` 文件进行更改，当更改完成之后可以再序列化为 `toml` 字符串。
 
 use std::{
-    fs::{create_dir_all, write},
+    ffi::OsString,
+    fs::{create_dir_all, remove_file, rename, File},
+    io::Write,
     path::Path,
     process,
 };
 
 use toml_edit::{DocumentMut, Table, TomlError};
 
-use crate::utils::to_out;
+use crate::error::CrmResult;
 
 #[derive(Debug)]
 pub struct Toml {
@@ -43,16 +45,34 @@ impl Toml {
     }
 
     /// 写入到文件中
-    pub fn write<P: AsRef<Path>>(&self, path: P) {
-        let parent = path.as_ref().parent().unwrap();
+    ///
+    /// 内容会先写入到同一目录下的临时文件中，然后再重命名为目标文件，
+    /// 这样即使在写入的过程中程序被中断，也不会留下一个不完整的配置文件。
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> CrmResult<()> {
+        let path = path.as_ref();
 
-        if !parent.is_dir() {
-            create_dir_all(parent).unwrap();
+        if let Some(parent) = path.parent() {
+            if !parent.is_dir() {
+                create_dir_all(parent)?;
+            }
         }
 
-        if let Err(e) = write(path, self.toml_string()) {
-            to_out(format!("写入文件失败:\n  {}", e));
-            process::exit(13);
+        let mut name = OsString::from(".");
+        name.push(path.file_name().unwrap_or_default());
+        name.push(format!(".{}.tmp", process::id()));
+        let tmp_path = path.with_file_name(name);
+
+        let result = File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(self.toml_string().as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| rename(&tmp_path, path));
+
+        if result.is_err() {
+            let _ = remove_file(&tmp_path);
         }
+
+        Ok(result?)
     }
 }
@@ -10,88 +10,166 @@ use std::{
     fmt::Display,
     fs::{read_to_string, rename},
     io,
+    io::Read,
     path::{Path, PathBuf},
-    process::{self, Command, Output, Stdio},
-    sync::mpsc,
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
     thread,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
+use toml_edit::DocumentMut;
 use ureq::Error;
 
-use crate::constants::{CARGO_HOME, CONFIG, CONFIG_TOML, DOT_CARGO, UNC_PREFIX};
+use crate::{
+    constants::{
+        CARGO_HOME, CARGO_TOML, CONFIG, CONFIG_TOML, CRM_DIR, DOT_CARGO, MAX_DOWNLOAD_BYTES,
+        UNC_PREFIX, WORKSPACE,
+    },
+    error::{Argument, CrmError, CrmResult},
+    i18n::Msg,
+    net::{self, NetError},
+    stats::{Bandwidth, Stats, Throughput},
+};
+
+pub fn home_dir() -> CrmResult<PathBuf> {
+    env::home_dir().ok_or_else(|| {
+        CrmError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            Msg::NoHomeDir.to_string(),
+        ))
+    })
+}
 
-pub fn home_dir() -> PathBuf {
-    env::home_dir().unwrap()
+pub fn crm_dir() -> CrmResult<PathBuf> {
+    Ok(home_dir()?.join(CRM_DIR))
 }
 
-pub fn cargo_home() -> PathBuf {
+pub fn cargo_home() -> CrmResult<PathBuf> {
     match env::var_os(CARGO_HOME) {
-        Some(value) => PathBuf::from(value),
-        None => home_dir().join(DOT_CARGO),
+        Some(value) => Ok(PathBuf::from(value)),
+        None => Ok(home_dir()?.join(DOT_CARGO)),
     }
 }
 
-pub fn cargo_config_path() -> PathBuf {
-    let c = cargo_home();
+pub fn cargo_config_path() -> CrmResult<PathBuf> {
+    config_file_in(&cargo_home()?)
+}
 
+/// 获取 `dir` 目录中的 `Cargo` 配置文件，如果存在不带 `.toml` 扩展名的旧配置文件，则将其重命名
+fn config_file_in(dir: &Path) -> CrmResult<PathBuf> {
     // Rust v1.39 版本中添加了对该 `.toml` 扩展的支持，并且是首选形式
-    let path = c.join(CONFIG_TOML);
+    let path = dir.join(CONFIG_TOML);
 
     // Cargo 还读取不带 `.toml` 扩展名的配置文件，例如 `~/.cargo/config`
     // 如果该文件存在，Cargo 将首先使用不带扩展名的文件
     // https://doc.rust-lang.org/cargo/reference/config.html
-    let obsolete_path = c.join(CONFIG);
+    let obsolete_path = dir.join(CONFIG);
 
     if path.is_file() {
         if obsolete_path.is_file() {
-            to_out(format!("检测到了两种形式的配置文件，为了避免歧义，请将 {} 文件 (不再被推荐使用) 中的内容手动合并到 {} 文件中", obsolete_path.display(), path.display()));
-            process::exit(14);
+            return Err(CrmError::ConfigConflict {
+                obsolete: obsolete_path,
+                path,
+            });
         }
     } else if obsolete_path.is_file() {
-        to_out(format!(
-            "检测到了 {} 配置文件 (不再被推荐使用)，以后请使用 {} 配置文件",
-            obsolete_path.display(),
-            path.display()
-        ));
-        rename(obsolete_path, &path).unwrap();
+        to_out(Msg::ObsoleteConfig.format(&[&obsolete_path.display(), &path.display()]));
+        rename(obsolete_path, &path)?;
     }
 
-    path
+    Ok(path)
 }
 
-pub fn get_cargo_config() -> String {
-    match read_to_string(cargo_config_path()) {
-        Ok(content) => content,
-        Err(_) => "".to_string(),
-    }
+/// 从 `dir` 目录开始逐级向上查找所有已存在的项目级 `Cargo` 配置文件，距离 `dir` 最近的排在最前面
+///
+/// `${CARGO_HOME}` 目录中的全局配置文件不会被包含在内。
+pub fn local_cargo_config_paths(dir: &Path) -> CrmResult<Vec<PathBuf>> {
+    let home = cargo_home()?;
+    let home = home.canonicalize().unwrap_or(home);
+
+    Ok(dir
+        .ancestors()
+        .map(|v| v.join(DOT_CARGO))
+        .filter(|v| v.canonicalize().map_or(true, |v| v != home))
+        .filter_map(|v| {
+            [CONFIG_TOML, CONFIG]
+                .iter()
+                .map(|name| v.join(name))
+                .find(|path| path.is_file())
+        })
+        .collect())
 }
 
-pub fn is_registry_name(name: Option<&String>) -> &str {
-    if name.is_none() {
-        to_out("请输入正确的镜像名");
-        process::exit(1);
+/// 获取 `dir` 所在项目的根目录
+///
+/// 根目录是包含 `[workspace]` 属性的 `Cargo.toml` 所在的目录，不在工作空间中时则是距离 `dir` 最近的
+/// `Cargo.toml` 所在的目录。`dir` 不在任何项目中时返回 `None`。
+fn project_root(dir: &Path) -> Option<&Path> {
+    let mut manifests = dir.ancestors().filter(|v| v.join(CARGO_TOML).is_file());
+    let package = manifests.next()?;
+    let is_workspace = |dir: &Path| {
+        read_to_string(dir.join(CARGO_TOML))
+            .ok()
+            .and_then(|v| v.parse::<DocumentMut>().ok())
+            .is_some_and(|v| v.contains_key(WORKSPACE))
+    };
+
+    if is_workspace(package) {
+        return Some(package);
     }
 
-    name.unwrap().as_str()
+    manifests.find(|v| is_workspace(v)).or(Some(package))
 }
 
-pub fn is_registry_addr(addr: Option<&String>) -> &str {
-    if addr.is_none() {
-        to_out("请输入正确的镜像地址");
-        process::exit(2);
+/// 获取距离 `dir` 目录最近的项目级 `Cargo` 配置文件
+///
+/// 如果 `dir` 及其上级目录中都没有项目级配置文件，则返回项目根目录下的 `.cargo/config.toml`，
+/// 这样在项目中的任何目录执行 `cargo` 时都可以读取到它。`dir` 不在任何项目中时返回 `dir/.cargo/config.toml`。
+pub fn local_cargo_config_path(dir: &Path) -> CrmResult<PathBuf> {
+    let dir = absolute_path(&dir)?;
+
+    match local_cargo_config_paths(&dir)?
+        .first()
+        .and_then(|v| v.parent())
+    {
+        Some(path) => config_file_in(path),
+        None => Ok(project_root(&dir)
+            .unwrap_or(&dir)
+            .join(DOT_CARGO)
+            .join(CONFIG_TOML)),
     }
+}
 
-    addr.unwrap().as_str()
+pub fn get_cargo_config<P: AsRef<Path>>(path: P) -> String {
+    read_to_string(path).unwrap_or_default()
+}
+
+/// 获取镜像名，没有指定或者只包含空白字符时返回错误
+pub fn is_registry_name(name: Option<&String>) -> CrmResult<&str> {
+    match name {
+        Some(name) if !name.trim().is_empty() => Ok(name.as_str()),
+        _ => Err(CrmError::MissingArgument(Argument::Name)),
+    }
 }
 
-pub fn is_registry_dl(dl: Option<&String>) -> &str {
-    if dl.is_none() {
-        to_out("请输入正确的 dl。\n  每一个镜像源都是一个 Git 存储库，而在该存储库的根目录下有一个 config.json 文件，\n  其中，dl 属性是 config.json 文件中的一个字段。");
-        process::exit(3);
+/// 获取镜像地址，没有指定或者只包含空白字符时返回错误
+pub fn is_registry_addr(addr: Option<&String>) -> CrmResult<&str> {
+    match addr {
+        Some(addr) if !addr.trim().is_empty() => Ok(addr.as_str()),
+        _ => Err(CrmError::MissingArgument(Argument::Addr)),
     }
+}
 
-    dl.unwrap().as_str()
+/// 获取镜像的 `dl` 地址，没有指定或者只包含空白字符时返回错误
+pub fn is_registry_dl(dl: Option<&String>) -> CrmResult<&str> {
+    match dl {
+        Some(dl) if !dl.trim().is_empty() => Ok(dl.as_str()),
+        _ => Err(CrmError::MissingArgument(Argument::Dl)),
+    }
 }
 
 pub fn append_end_spaces(value: &str, total_len: Option<usize>) -> String {
@@ -110,94 +188,361 @@ pub fn append_end_spaces(value: &str, total_len: Option<usize>) -> String {
     format!("{}{}", value, pad)
 }
 
-pub fn request(url: &str, is_connect_only: bool) -> Option<u128> {
+/// 网络请求的结果，成功时为请求的耗时 (毫秒)，失败时为失败的原因
+pub type Delay = Result<u128, NetError>;
+
+pub fn request(url: &str, is_connect_only: bool, timeout: Duration) -> Delay {
     let time = SystemTime::now();
+    let elapsed = || time.elapsed().map(|v| v.as_millis()).unwrap_or_default();
 
-    match ureq::get(url).timeout(Duration::from_secs(5)).call() {
+    match net::get(url).timeout(timeout).call() {
         Ok(res) => {
             let status = res.status();
 
             if status >= 300 {
                 return match res.header("location") {
-                    Some(v) => request(v, is_connect_only),
-                    None => None,
+                    Some(v) => request(v, is_connect_only, timeout),
+                    None => Err(format!("HTTP {}", status).into()),
                 };
             }
 
             // 不管是不是 404，只要能连上主机，就成功返回
             if is_connect_only {
-                return Some(time.elapsed().unwrap().as_millis());
+                return Ok(elapsed());
             }
 
             if status >= 400 {
-                return None;
+                return Err(format!("HTTP {}", status).into());
             }
 
-            Some(time.elapsed().unwrap().as_millis())
+            Ok(elapsed())
         }
 
         // 连接成功，但返回的状态不是预期的
-        Err(Error::Status(_, _)) => {
+        Err(Error::Status(status, _)) => {
             if is_connect_only {
-                Some(time.elapsed().unwrap().as_millis())
+                Ok(elapsed())
             } else {
-                None
+                Err(format!("HTTP {}", status).into())
             }
         }
 
         // 其他错误，例如连接失败
-        Err(_) => None,
+        Err(Error::Transport(e)) => Err(net::transport_error(e)),
+    }
+}
+
+/// 网络测试的限制
+#[derive(Debug, Clone, Copy)]
+pub struct ProbeLimits {
+    /// 每次网络请求的超时时间
+    pub timeout: Duration,
+
+    /// 对每个镜像进行测试的次数
+    pub samples: usize,
+
+    /// 同时测试的镜像的最大数量
+    pub jobs: usize,
+
+    /// 所有测试的总时限，为 `None` 时不限制
+    pub deadline: Option<Duration>,
+}
+
+/// 并发任务可用的时间预算
+#[derive(Debug)]
+pub struct Budget<'a> {
+    /// 每次网络请求的超时时间
+    timeout: Duration,
+
+    /// 所有任务的截止时间
+    deadline: Option<Instant>,
+
+    /// 是否已经通知任务停止
+    cancelled: &'a AtomicBool,
+}
+
+impl Budget<'_> {
+    /// 是否已经通知任务停止
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 获取下一次请求的超时时间，它不会超过剩余的总时限，已经超过总时限时返回错误
+    pub fn timeout(&self) -> Result<Duration, String> {
+        let timeout = match self.deadline {
+            Some(v) => self
+                .timeout
+                .min(v.saturating_duration_since(Instant::now())),
+            None => self.timeout,
+        };
+
+        match timeout.is_zero() {
+            true => Err(Msg::DeadlineExceeded.to_string()),
+            false => Ok(timeout),
+        }
     }
 }
 
+/// 使用最多 `limits.jobs` 个线程对 `items` 中的每一项执行 `task`，并按照 `items` 的顺序返回结果
+///
+/// 每拿到一个结果都会调用 `is_done`，它返回 `true` 或者超过了总时限时，会通知其他的任务停止，
+/// 还没有开始的任务不会再执行。所有的线程都会在返回之前结束，没有及时完成的任务的结果为 `None`。
+pub fn run_bounded<T, R, F, D>(
+    items: &[T],
+    limits: ProbeLimits,
+    task: F,
+    mut is_done: D,
+) -> Vec<Option<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T, &Budget) -> R + Sync,
+    D: FnMut(&R) -> bool,
+{
+    let deadline = limits.deadline.map(|v| Instant::now() + v);
+    let next = AtomicUsize::new(0);
+    let cancelled = AtomicBool::new(false);
+    let budget = Budget {
+        timeout: limits.timeout,
+        deadline,
+        cancelled: &cancelled,
+    };
+    let mut ret: Vec<Option<R>> = items.iter().map(|_| None).collect();
+
+    thread::scope(|s| {
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..limits.jobs.clamp(1, items.len().max(1)) {
+            let (tx, budget, next, task) = (tx.clone(), &budget, &next, &task);
+
+            s.spawn(move || {
+                while !budget.is_cancelled() {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+
+                    // 如果接收者已经拿到了足够的结果，则停止执行
+                    if tx.send((index, task(item, budget))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        drop(tx);
+
+        loop {
+            let received = match deadline {
+                Some(v) => rx
+                    .recv_timeout(v.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => rx.recv().ok(),
+            };
+
+            let Some((index, value)) = received else {
+                break;
+            };
+            let done = is_done(&value);
+
+            ret[index] = Some(value);
+
+            if done {
+                break;
+            }
+        }
+
+        cancelled.store(true, Ordering::Relaxed);
+    });
+
+    ret
+}
+
+/// 对每个镜像进行 `samples` 次测试，并按照 `Stats::score` 从小到大排序，全部失败的镜像排在最前面
+///
+/// 最多有 `jobs` 个镜像同时测试，而同一个镜像的多次测试会依次进行，以免它们之间相互影响。
+///
+/// 当拿到 `sender_size` 个成功的结果或者超过了总时限时，会通知其他的测试停止，还没有开始的测试不会再发出请求，
+/// 正在进行的请求也不会超过总时限。全部失败时会返回所有镜像的结果，没有在总时限内完成测试的镜像会被记为失败。
 pub fn network_delay(
     values: Vec<(String, Option<String>)>,
     sender_size: Option<usize>,
     is_connect_only: bool,
-) -> Vec<(String, Option<u128>)> {
-    let (tx, rx) = mpsc::channel();
-    let iter = values.iter();
-    let len = sender_size.unwrap_or_else(|| iter.len());
-    let mut ret = vec![];
-
-    for v in iter {
-        let t = tx.clone();
-        let v = v.clone();
-
-        thread::spawn(move || {
-            let date = match &v.1 {
-                Some(url) => request(url, is_connect_only),
-                None => None,
-            };
+    limits: ProbeLimits,
+) -> Vec<(String, Stats)> {
+    let mut succeeded = 0;
+
+    let tested = run_bounded(
+        &values,
+        limits,
+        |(_, url), budget| {
+            let mut samples = vec![];
+
+            for _ in 0..limits.samples.max(1) {
+                if budget.is_cancelled() {
+                    break;
+                }
+
+                samples.push(match (url, budget.timeout()) {
+                    (_, Err(e)) => Err(e.into()),
+                    (Some(url), Ok(timeout)) => request(url, is_connect_only, timeout),
+                    (None, _) => Err(Msg::UnsupportedTest.to_string().into()),
+                });
+            }
 
-            if t.send((v.0.to_string(), date)).is_err() {
-                process::exit(0);
+            Stats::new(samples)
+        },
+        |stats| {
+            if stats.success_count() > 0 {
+                succeeded += 1;
             }
-        });
-    }
 
-    for _ in 0..len {
-        ret.push(rx.recv().unwrap());
-    }
+            sender_size.is_some_and(|v| succeeded >= v)
+        },
+    );
+
+    let is_early = sender_size.is_some_and(|v| succeeded >= v);
+
+    let mut ret: Vec<(String, Stats)> = values
+        .into_iter()
+        .zip(tested)
+        .filter_map(|((name, _), stats)| match stats {
+            Some(stats) => Some((name, stats)),
+
+            // 提前拿到了足够的结果时，其他镜像只是没有测试，而不是测试失败
+            None if is_early => None,
 
-    ret.sort_by(|a, b| a.1.cmp(&b.1));
+            // 没有在总时限内完成测试的镜像
+            None => Some((
+                name,
+                Stats::new(vec![Err(Msg::DeadlineExceeded.to_string().into())]),
+            )),
+        })
+        .collect();
+
+    ret.sort_by_key(|v| v.1.score(limits.timeout));
 
     ret
 }
 
-pub fn field_eprint<T: Display>(field_name: T, field_type: &str) {
-    to_out(format!(
-        "{} 文件中的 {} 字段不是一个{}，请修改后重试",
-        cargo_config_path().display(),
-        field_name,
-        field_type
-    ));
+/// 请求 `url` 并以字符串的形式返回响应的内容
+pub fn fetch(url: &str, timeout: Duration) -> Result<String, String> {
+    let res = net::get(url).timeout(timeout).call().map_err(|e| match e {
+        Error::Status(status, _) => format!("HTTP {}: {}", status, url),
+        Error::Transport(e) => net::transport_error(e).to_string(),
+    })?;
+
+    res.into_string().map_err(|e| e.to_string())
+}
+
+/// 下载 `url` 并以字节的形式返回响应的内容，内容超过 `MAX_DOWNLOAD_BYTES` 时返回错误
+pub fn download(url: &str, timeout: Duration) -> Result<Vec<u8>, String> {
+    let res = net::get(url).timeout(timeout).call().map_err(|e| match e {
+        Error::Status(status, _) => format!("HTTP {}: {}", status, url),
+        Error::Transport(e) => net::transport_error(e).to_string(),
+    })?;
+    let mut bytes = vec![];
+
+    res.into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(Msg::DownloadTooLarge.format(&[&url, &MAX_DOWNLOAD_BYTES]));
+    }
+
+    Ok(bytes)
+}
+
+/// 依次下载 `urls` 中的文件以测试下载速度
+///
+/// 当下载的总字节数达到 `max_bytes` 或总耗时达到 `max_time` 时停止下载，并根据已经下载的字节数计算下载速度。
+pub fn throughput(
+    urls: &[Option<String>],
+    timeout: Duration,
+    max_bytes: u64,
+    max_time: Duration,
+) -> Bandwidth {
+    let start = Instant::now();
+    let mut bytes = 0;
+    let mut buf = vec![0; 64 * 1024];
+
+    'urls: for url in urls {
+        let url = url
+            .as_ref()
+            .ok_or_else(|| NetError::from(Msg::UnsupportedTest.to_string()))?;
+        let res = net::get(url)
+            .timeout(timeout + max_time)
+            .call()
+            .map_err(|e| match e {
+                Error::Status(status, _) => format!("HTTP {}", status).into(),
+                Error::Transport(e) => net::transport_error(e),
+            })?;
+        let mut reader = res.into_reader();
+
+        while bytes < max_bytes && start.elapsed() < max_time {
+            match reader.read(&mut buf).map_err(|e| net::io_error(&e))? {
+                0 => continue 'urls,
+                n => bytes += n as u64,
+            }
+        }
+
+        break;
+    }
+
+    Ok(Throughput {
+        bytes,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// 获取软件包在索引和下载地址中使用的前缀，例如 `serde` 的前缀是 `se/rd`
+pub fn crate_prefix(name: &str) -> String {
+    match name.len() {
+        1 => "1".to_string(),
+        2 => "2".to_string(),
+        3 => format!("3/{}", &name[..1]),
+        _ => format!("{}/{}", &name[..2], &name[2..4]),
+    }
 }
 
 pub fn to_out<T: Display>(message: T) {
     println!(" {}", message);
 }
 
+/// 将警告信息输出到标准错误，以免影响结构化的输出
+pub fn to_err<T: Display>(message: T) {
+    eprintln!(" {}", message);
+}
+
+/// 将自 `UNIX_EPOCH` 以来的毫秒数格式化为 `UTC` 时间
+pub fn format_timestamp(millis: u128) -> String {
+    let secs = (millis / 1000) as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // 将天数转换为公历日期，参考 <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
 pub fn status_prefix(value1: &String, value2: &String) -> String {
     if value1.eq(value2) { "  * " } else { "    " }.to_string()
 }
@@ -218,7 +563,7 @@ pub fn absolute_path<T: AsRef<OsStr>>(dir: &T) -> io::Result<PathBuf> {
         if path_slice.len() > 260 {
             let error = io::Error::new(
                 io::ErrorKind::InvalidData,
-                format!("当前路径超过了 Windows 普通路径的最大长度: {}", path_str),
+                Msg::PathTooLong.format(&[&path_str]),
             );
 
             return Err(error);
@@ -230,15 +575,8 @@ pub fn absolute_path<T: AsRef<OsStr>>(dir: &T) -> io::Result<PathBuf> {
     Ok(path)
 }
 
-pub fn exec_command(command: &str, cwd: Option<&String>) -> io::Result<Output> {
-    let mut program = "sh";
-    let mut arg_c = "-c";
-
-    if is_windows() {
-        program = "cmd";
-        arg_c = "/c";
-    }
-
+/// 在 `cwd` 目录中执行 `program`，`args` 会原样传递给它，而不经过 `shell` 的解析
+pub fn exec_command(program: &str, args: &[String], cwd: Option<&String>) -> io::Result<Output> {
     let cwd = match cwd {
         Some(cwd) => match absolute_path(cwd) {
             Ok(path) => path,
@@ -246,12 +584,12 @@ pub fn exec_command(command: &str, cwd: Option<&String>) -> io::Result<Output> {
                 return Err(e);
             }
         },
-        None => env::current_dir().unwrap(),
+        None => env::current_dir()?,
     };
 
     Command::new(program)
         .current_dir(cwd)
-        .args([arg_c, command])
+        .args(args)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
@@ -259,61 +597,112 @@ pub fn exec_command(command: &str, cwd: Option<&String>) -> io::Result<Output> {
 }
 
 /// 获取最新的版本
-pub fn get_newest_version() -> Option<String> {
+pub fn get_newest_version() -> CrmResult<String> {
     let url = "https://crates.io/api/v1/crates/crm";
+    let body = fetch(url, Duration::from_secs(10)).map_err(CrmError::Network)?;
 
-    match ureq::get(url).timeout(Duration::from_secs(10)).call() {
-        Ok(res) => {
-            let status = res.status();
+    body.find("\"newest_version\"")
+        .and_then(|idx| {
+            let sub_str = body.get(idx + 18..idx + 38)?;
+            let version = &sub_str[..sub_str.find('\"')?];
 
-            if status >= 400 {
-                return None;
-            }
+            Some(version.to_string())
+        })
+        .ok_or_else(|| CrmError::Network(Msg::VersionParse.format(&[&url])))
+}
 
-            match res.into_string() {
-                Ok(body) => match body.find("\"newest_version\"") {
-                    Some(idx) => {
-                        let sub_str = &body[idx + 18..idx + 38];
-                        let version = &sub_str[..sub_str.find('\"').unwrap()];
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
 
-                        Some(version.to_string())
-                    }
+    use super::*;
 
-                    None => None,
-                },
+    /// 在临时目录中创建 `files` 中的文件，返回临时目录
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = env::temp_dir().join(format!("crm-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&root);
 
-                Err(_) => None,
-            }
+        for (file, content) in files {
+            let path = root.join(file);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
         }
 
-        Err(_) => None,
+        root
     }
-}
 
-pub fn not_command(command: &str) {
-    let r = r#"
-  crm best                    评估网络延迟并自动切换到最优的镜像
-    crm best git              仅评估 git 镜像源
-    crm best sparse           仅评估支持 sparse 协议的镜像源
-    crm best git-download     仅评估能够快速下载软件包的 git 镜像源 (推荐使用)
-    crm best sparse-download  仅评估能够快速下载软件包且支持 sparse 协议的镜像源 (推荐使用)
-  crm current                 获取当前所使用的镜像
-  crm default                 恢复为官方默认镜像
-  crm install [args]          使用官方镜像执行 "cargo install"
-  crm list                    从镜像配置文件中获取镜像列表
-  crm publish [args]          使用官方镜像执行 "cargo publish"
-  crm remove <name>           在镜像配置文件中删除镜像
-  crm save <name> <addr> <dl> 在镜像配置文件中添加/更新镜像
-  crm test [name]             下载测试包以评估网络延迟
-  crm update [args]           使用官方镜像执行 "cargo update"
-  crm use <name>              切换为要使用的镜像
-  crm version                 查看当前版本
-  crm check-update            检测版本更新
-"#;
-
-    to_out(format!(
-        "{} 命令无效。参考:\n{}\nHome：<https://github.com/wtklbm/crm>\n",
-        command, r
-    ));
-    process::exit(4);
+    #[test]
+    fn finds_project_root() {
+        let root = tree(
+            "root",
+            &[
+                ("ws/Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n"),
+                ("ws/crates/a/Cargo.toml", "[package]\nname = \"a\"\n"),
+                ("ws/crates/a/src/lib.rs", ""),
+                ("solo/Cargo.toml", "[package]\nname = \"solo\"\n"),
+                ("solo/inner/Cargo.toml", "[package]\nname = \"inner\"\n"),
+                ("solo/inner/src/lib.rs", ""),
+                ("empty/src/lib.rs", ""),
+            ],
+        );
+
+        assert_eq!(
+            project_root(&root.join("ws/crates/a/src")),
+            Some(root.join("ws").as_path())
+        );
+        assert_eq!(
+            project_root(&root.join("ws")),
+            Some(root.join("ws").as_path())
+        );
+        assert_eq!(
+            project_root(&root.join("solo/inner/src")),
+            Some(root.join("solo/inner").as_path())
+        );
+        assert_eq!(project_root(&root.join("empty/src")), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn finds_local_cargo_configs() {
+        let root = tree(
+            "configs",
+            &[
+                ("ws/Cargo.toml", "[workspace]\n"),
+                ("ws/.cargo/config.toml", ""),
+                ("ws/crates/a/Cargo.toml", "[package]\nname = \"a\"\n"),
+                ("ws/crates/a/.cargo/config", ""),
+                ("ws/crates/a/src/lib.rs", ""),
+                ("ws/crates/b/Cargo.toml", "[package]\nname = \"b\"\n"),
+                ("ws/crates/b/src/lib.rs", ""),
+            ],
+        );
+
+        assert_eq!(
+            local_cargo_config_paths(&root.join("ws/crates/a/src")).unwrap(),
+            [
+                root.join("ws/crates/a/.cargo/config"),
+                root.join("ws/.cargo/config.toml")
+            ]
+        );
+        assert_eq!(
+            local_cargo_config_paths(&root.join("ws/crates/b/src")).unwrap(),
+            [root.join("ws/.cargo/config.toml")]
+        );
+        assert_eq!(
+            local_cargo_config_path(&root.join("ws/crates/b/src")).unwrap(),
+            root.join("ws/.cargo/config.toml")
+        );
+
+        fs::remove_dir_all(root.join("ws/.cargo")).unwrap();
+
+        // 没有项目级配置文件时，使用工作空间根目录下的配置文件
+        assert_eq!(
+            local_cargo_config_path(&root.join("ws/crates/b/src")).unwrap(),
+            root.join("ws/.cargo/config.toml")
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
 }